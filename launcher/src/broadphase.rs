@@ -0,0 +1,196 @@
+use ct_lib::math::*;
+
+use std::collections::HashMap;
+
+/// Bodies that are much smaller than this waste time checking empty neighbours, bodies that are
+/// much larger end up in many cells
+pub const BROADPHASE_CELL_SIZE_DEFAULT: f32 = 32.0;
+
+#[derive(Debug, Copy, Clone)]
+pub struct BroadphaseBody {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub layers_own: u64,
+    pub layers_affects: u64,
+}
+
+impl BroadphaseBody {
+    fn interacts_with(&self, other: &BroadphaseBody) -> bool {
+        self.layers_own & other.layers_affects != 0 || other.layers_own & self.layers_affects != 0
+    }
+
+    fn intersects(&self, other: &BroadphaseBody) -> bool {
+        Vec2::distance_squared(self.pos, other.pos) < squared(self.radius + other.radius)
+    }
+
+    /// Returns the inclusive cell range (x_min, y_min, x_max, y_max) of our bounding box
+    fn get_cell_range(&self, cell_size: f32) -> (i32, i32, i32, i32) {
+        (
+            f32::floor((self.pos.x - self.radius) / cell_size) as i32,
+            f32::floor((self.pos.y - self.radius) / cell_size) as i32,
+            f32::floor((self.pos.x + self.radius) / cell_size) as i32,
+            f32::floor((self.pos.y + self.radius) / cell_size) as i32,
+        )
+    }
+}
+
+/// Returns the sorted index pairs `(a, b)` with `a < b` of all bodies that intersect and where at
+/// least one of them affects the other. Only compares bodies that share a cell of a uniform grid.
+pub fn find_collision_pairs(bodies: &[BroadphaseBody], cell_size: f32) -> Vec<(usize, usize)> {
+    assert!(cell_size > 0.0);
+
+    let cell_ranges: Vec<(i32, i32, i32, i32)> = bodies
+        .iter()
+        .map(|body| body.get_cell_range(cell_size))
+        .collect();
+
+    let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (index, &(x_min, y_min, x_max, y_max)) in cell_ranges.iter().enumerate() {
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                cells.entry((x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (&(cell_x, cell_y), indices) in &cells {
+        for (position, &index_a) in indices.iter().enumerate() {
+            for &index_b in &indices[(position + 1)..] {
+                // NOTE: Two bodies can share many cells. We only report them in the first cell
+                //       they share so that we do not need to deduplicate afterwards.
+                let range_a = cell_ranges[index_a];
+                let range_b = cell_ranges[index_b];
+                if cell_x != i32::max(range_a.0, range_b.0)
+                    || cell_y != i32::max(range_a.1, range_b.1)
+                {
+                    continue;
+                }
+
+                let body_a = &bodies[index_a];
+                let body_b = &bodies[index_b];
+                if body_a.interacts_with(body_b) && body_a.intersects(body_b) {
+                    // NOTE: Indices within a cell are ascending because we inserted them in order
+                    pairs.push((index_a, index_b));
+                }
+            }
+        }
+    }
+
+    // The cells are visited in arbitrary order
+    pairs.sort_unstable();
+    pairs
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ct_lib::random::*;
+
+    const LAYER_A: u64 = 1 << 0;
+    const LAYER_B: u64 = 1 << 1;
+    const LAYER_C: u64 = 1 << 2;
+
+    /// Compares every body with every other body
+    fn find_collision_pairs_bruteforce(bodies: &[BroadphaseBody]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for index_a in 0..bodies.len() {
+            for index_b in (index_a + 1)..bodies.len() {
+                let body_a = &bodies[index_a];
+                let body_b = &bodies[index_b];
+                if body_a.interacts_with(body_b) && body_a.intersects(body_b) {
+                    pairs.push((index_a, index_b));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn body(pos: Vec2, radius: f32, layers_own: u64, layers_affects: u64) -> BroadphaseBody {
+        BroadphaseBody {
+            pos,
+            radius,
+            layers_own,
+            layers_affects,
+        }
+    }
+
+    #[test]
+    fn grid_matches_bruteforce_on_random_bodies() {
+        let layer_setups = [
+            (LAYER_A, LAYER_B),
+            (LAYER_B, 0),
+            (LAYER_C, LAYER_A | LAYER_B),
+            (LAYER_C, 0),
+        ];
+        let mut random = Random::new_from_seed(1234);
+        let bodies: Vec<BroadphaseBody> = (0..500)
+            .map(|_| {
+                let (layers_own, layers_affects) = random.pick_from_slice(&layer_setups);
+                body(
+                    Vec2::new(
+                        random.f32_in_range_closed(-50.0, 500.0),
+                        random.f32_in_range_closed(-50.0, 300.0),
+                    ),
+                    random.f32_in_range_closed(0.5, 40.0),
+                    layers_own,
+                    layers_affects,
+                )
+            })
+            .collect();
+
+        let expected = find_collision_pairs_bruteforce(&bodies);
+        assert!(!expected.is_empty());
+        for &cell_size in &[8.0, BROADPHASE_CELL_SIZE_DEFAULT, 100.0] {
+            assert_eq!(find_collision_pairs(&bodies, cell_size), expected);
+        }
+    }
+
+    #[test]
+    fn grid_matches_bruteforce_on_cell_edges() {
+        let cell_size = BROADPHASE_CELL_SIZE_DEFAULT;
+        let bodies = vec![
+            // Bounding boxes that end exactly on a cell edge
+            body(Vec2::new(cell_size - 4.0, 10.0), 4.0, LAYER_A, LAYER_B),
+            body(Vec2::new(cell_size + 3.0, 10.0), 4.0, LAYER_B, 0),
+            // Centers exactly on a cell corner
+            body(Vec2::new(cell_size, cell_size), 2.0, LAYER_A, LAYER_B),
+            body(Vec2::new(cell_size + 1.0, cell_size + 1.0), 1.0, LAYER_B, 0),
+            body(Vec2::new(cell_size - 1.0, cell_size - 1.0), 1.0, LAYER_B, 0),
+            // Negative cells
+            body(Vec2::new(-cell_size, 0.0), 3.0, LAYER_A, LAYER_B),
+            body(Vec2::new(-cell_size + 2.0, 0.0), 3.0, LAYER_B, 0),
+            // Spans many cells and overlaps with all of the above
+            body(Vec2::new(0.0, 0.0), 3.0 * cell_size, LAYER_B, 0),
+        ];
+
+        let expected = find_collision_pairs_bruteforce(&bodies);
+        assert_eq!(find_collision_pairs(&bodies, cell_size), expected);
+        assert!(expected.contains(&(0, 1)));
+        assert!(expected.contains(&(2, 3)));
+        assert!(expected.contains(&(2, 4)));
+        assert!(expected.contains(&(5, 6)));
+        assert!(expected.contains(&(0, 7)));
+    }
+
+    #[test]
+    fn grid_skips_bodies_whose_layers_do_not_interact() {
+        let pos = Vec2::new(10.0, 10.0);
+        let bodies = vec![
+            body(pos, 5.0, LAYER_A, LAYER_B),
+            body(pos, 5.0, LAYER_B, 0),
+            body(pos, 5.0, LAYER_C, 0),
+            body(pos, 5.0, LAYER_C, LAYER_C),
+        ];
+
+        let expected = vec![(0, 1), (2, 3)];
+        assert_eq!(find_collision_pairs_bruteforce(&bodies), expected);
+        assert_eq!(
+            find_collision_pairs(&bodies, BROADPHASE_CELL_SIZE_DEFAULT),
+            expected
+        );
+    }
+}
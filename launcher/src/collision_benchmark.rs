@@ -0,0 +1,106 @@
+// Compares the spatial hash broadphase against the bruteforce reference and checks that both
+// find the same collisions
+//
+// Usage: cargo run --release --bin collision_benchmark
+
+use ct_lib::math::*;
+use ct_lib::random::*;
+
+use launcher::broadphase::{find_collision_pairs, BroadphaseBody, BROADPHASE_CELL_SIZE_DEFAULT};
+
+use std::time::{Duration, Instant};
+
+const CANVAS_WIDTH: f32 = 480.0;
+const CANVAS_HEIGHT: f32 = 270.0;
+
+const BODY_COUNTS: [usize; 2] = [1000, 5000];
+const ITERATION_COUNT: u32 = 10;
+
+// NOTE: These mirror the layer setups of the stage so that the layer filter sees realistic input
+const LAYER_SETUPS: [(u64, u64); 5] = [
+    (1 << 0, 1 << 1),
+    (1 << 1, 0),
+    (1 << 2, (1 << 1) | (1 << 3)),
+    (1 << 3, (1 << 0) | (1 << 2)),
+    (1 << 4, 1 << 1),
+];
+
+fn create_bodies(random: &mut Random, count: usize) -> Vec<BroadphaseBody> {
+    (0..count)
+        .map(|_| {
+            let (layers_own, layers_affects) = random.pick_from_slice(&LAYER_SETUPS);
+            BroadphaseBody {
+                pos: Vec2::new(
+                    random.f32_in_range_closed(0.0, CANVAS_WIDTH),
+                    random.f32_in_range_closed(0.0, CANVAS_HEIGHT),
+                ),
+                radius: random.f32_in_range_closed(1.0, 12.0),
+                layers_own,
+                layers_affects,
+            }
+        })
+        .collect()
+}
+
+/// Compares every body with every other body. This is what the broadphase replaced.
+fn find_collision_pairs_bruteforce(bodies: &[BroadphaseBody]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for index_a in 0..bodies.len() {
+        for index_b in (index_a + 1)..bodies.len() {
+            let body_a = &bodies[index_a];
+            let body_b = &bodies[index_b];
+            let interacts = body_a.layers_own & body_b.layers_affects != 0
+                || body_b.layers_own & body_a.layers_affects != 0;
+            let intersects = Vec2::distance_squared(body_a.pos, body_b.pos)
+                < squared(body_a.radius + body_b.radius);
+            if interacts && intersects {
+                pairs.push((index_a, index_b));
+            }
+        }
+    }
+    pairs
+}
+
+fn measure<F>(mut function: F) -> (Duration, Vec<(usize, usize)>)
+where
+    F: FnMut() -> Vec<(usize, usize)>,
+{
+    let start = Instant::now();
+    let mut pairs = Vec::new();
+    for _ in 0..ITERATION_COUNT {
+        pairs = function();
+    }
+    (start.elapsed() / ITERATION_COUNT, pairs)
+}
+
+fn main() {
+    let mut random = Random::new_from_seed(1234);
+
+    println!(
+        "{:>8} {:>8} {:>14} {:>14} {:>8}",
+        "bodies", "pairs", "bruteforce ms", "grid ms", "speedup"
+    );
+    for &body_count in &BODY_COUNTS {
+        let bodies = create_bodies(&mut random, body_count);
+
+        let (duration_bruteforce, pairs_bruteforce) =
+            measure(|| find_collision_pairs_bruteforce(&bodies));
+        let (duration_grid, pairs_grid) =
+            measure(|| find_collision_pairs(&bodies, BROADPHASE_CELL_SIZE_DEFAULT));
+
+        assert_eq!(
+            pairs_bruteforce, pairs_grid,
+            "Broadphase found different collisions for {} bodies",
+            body_count
+        );
+
+        println!(
+            "{:>8} {:>8} {:>14.3} {:>14.3} {:>7.1}x",
+            body_count,
+            pairs_grid.len(),
+            duration_bruteforce.as_secs_f64() * 1000.0,
+            duration_grid.as_secs_f64() * 1000.0,
+            duration_bruteforce.as_secs_f64() / duration_grid.as_secs_f64(),
+        );
+    }
+}
@@ -1,27 +1,1053 @@
+use crate::stage::{COLOR_BACKGROUND, COLOR_DEFAULT, COLOR_HP, COLOR_SKILL_POINT};
+
 use ct_lib::audio::*;
 use ct_lib::draw::*;
 use ct_lib::game::*;
+use ct_lib::math::*;
+
+use strum::IntoEnumIterator;
+
+const DEPTH_BACKGROUND: Depth = 0.0;
+const DEPTH_TEXT: Depth = 50.0;
+
+const TERMINAL_LINE_HEIGHT: f32 = 10.0;
+const TERMINAL_PADDING: f32 = 8.0;
+const TERMINAL_SCROLLBACK_MAX: usize = 256;
+const TERMINAL_HISTORY_MAX: usize = 64;
+const TERMINAL_PROMPT: &str = "> ";
+
+type Blinker = TimerStateSwitchBinary;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Keyboard text input
+
+/// Keys that produce text and their unshifted and shifted characters
+const TEXT_KEYS: [(Scancode, char, char); 48] = [
+    (Scancode::A, 'a', 'A'),
+    (Scancode::B, 'b', 'B'),
+    (Scancode::C, 'c', 'C'),
+    (Scancode::D, 'd', 'D'),
+    (Scancode::E, 'e', 'E'),
+    (Scancode::F, 'f', 'F'),
+    (Scancode::G, 'g', 'G'),
+    (Scancode::H, 'h', 'H'),
+    (Scancode::I, 'i', 'I'),
+    (Scancode::J, 'j', 'J'),
+    (Scancode::K, 'k', 'K'),
+    (Scancode::L, 'l', 'L'),
+    (Scancode::M, 'm', 'M'),
+    (Scancode::N, 'n', 'N'),
+    (Scancode::O, 'o', 'O'),
+    (Scancode::P, 'p', 'P'),
+    (Scancode::Q, 'q', 'Q'),
+    (Scancode::R, 'r', 'R'),
+    (Scancode::S, 's', 'S'),
+    (Scancode::T, 't', 'T'),
+    (Scancode::U, 'u', 'U'),
+    (Scancode::V, 'v', 'V'),
+    (Scancode::W, 'w', 'W'),
+    (Scancode::X, 'x', 'X'),
+    (Scancode::Y, 'y', 'Y'),
+    (Scancode::Z, 'z', 'Z'),
+    (Scancode::Num1, '1', '!'),
+    (Scancode::Num2, '2', '@'),
+    (Scancode::Num3, '3', '#'),
+    (Scancode::Num4, '4', '$'),
+    (Scancode::Num5, '5', '%'),
+    (Scancode::Num6, '6', '^'),
+    (Scancode::Num7, '7', '&'),
+    (Scancode::Num8, '8', '*'),
+    (Scancode::Num9, '9', '('),
+    (Scancode::Num0, '0', ')'),
+    (Scancode::Space, ' ', ' '),
+    (Scancode::Minus, '-', '_'),
+    (Scancode::Equals, '=', '+'),
+    (Scancode::Comma, ',', '<'),
+    (Scancode::Period, '.', '>'),
+    (Scancode::Slash, '/', '?'),
+    (Scancode::Semicolon, ';', ':'),
+    (Scancode::Apostrophe, '\'', '"'),
+    (Scancode::KpMinus, '-', '-'),
+    (Scancode::KpPlus, '+', '+'),
+    (Scancode::LeftBracket, '[', '{'),
+    (Scancode::RightBracket, ']', '}'),
+];
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Terminal
+
+pub enum TerminalAction {
+    Submit(String),
+    RequestCompletion,
+}
+
+/// A scrolling text buffer with an editable input line that can be embedded into any scene
+#[derive(Clone)]
+pub struct Terminal {
+    lines: Vec<(String, Color)>,
+    scroll_offset: usize,
+
+    input: Vec<char>,
+    cursor_pos: usize,
+    cursor_blinker: Blinker,
+    cursor_visible: bool,
+
+    history: Vec<String>,
+    history_index: Option<usize>,
+}
+
+impl Terminal {
+    pub fn new() -> Terminal {
+        Terminal {
+            lines: Vec::new(),
+            scroll_offset: 0,
+
+            input: Vec::new(),
+            cursor_pos: 0,
+            cursor_blinker: Blinker::new(true, 0.5, 0.5),
+            cursor_visible: true,
+
+            history: Vec::new(),
+            history_index: None,
+        }
+    }
+
+    pub fn print(&mut self, text: &str, color: Color) {
+        for line in text.lines() {
+            self.lines.push((line.to_string(), color));
+        }
+        if self.lines.len() > TERMINAL_SCROLLBACK_MAX {
+            let overflow = self.lines.len() - TERMINAL_SCROLLBACK_MAX;
+            self.lines.drain(0..overflow);
+        }
+        self.scroll_offset = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll_offset = 0;
+    }
+
+    pub fn get_input(&self) -> String {
+        self.input.iter().collect()
+    }
+
+    pub fn get_input_before_cursor(&self) -> String {
+        self.input[..self.cursor_pos].iter().collect()
+    }
+
+    pub fn set_input(&mut self, text: &str) {
+        self.input = text.chars().collect();
+        self.cursor_pos = self.input.len();
+    }
+
+    /// Reads the keyboard and runs submitted lines against the given commands
+    pub fn update_and_run_commands<ContextType>(
+        &mut self,
+        input: &GameInput,
+        deltatime: f32,
+        commands: &CommandRegistry<ContextType>,
+        context: &mut ContextType,
+        out_game_events: &mut Vec<GameEvent>,
+    ) {
+        match self.update(input, deltatime) {
+            Some(TerminalAction::Submit(line)) => {
+                let output = commands.execute(context, &line);
+                if output.clear_terminal {
+                    self.clear();
+                }
+                for (text, color) in &output.lines {
+                    self.print(text, *color);
+                }
+                out_game_events.extend(output.game_events);
+            }
+            Some(TerminalAction::RequestCompletion) => {
+                let candidates = commands.get_completions(&self.get_input_before_cursor());
+                self.complete(&candidates);
+            }
+            None => {}
+        }
+    }
+
+    pub fn update(&mut self, input: &GameInput, deltatime: f32) -> Option<TerminalAction> {
+        let keyboard = &input.keyboard;
+        let mut input_changed = false;
+
+        let shift_down = keyboard.is_down(Scancode::LShift) || keyboard.is_down(Scancode::RShift);
+        for &(scancode, character, character_shifted) in TEXT_KEYS.iter() {
+            if keyboard.recently_pressed_or_repeated(scancode) {
+                let character = if shift_down {
+                    character_shifted
+                } else {
+                    character
+                };
+                self.input.insert(self.cursor_pos, character);
+                self.cursor_pos += 1;
+                input_changed = true;
+            }
+        }
+
+        // Line editing
+        if keyboard.recently_pressed_or_repeated(Scancode::Backspace) && self.cursor_pos > 0 {
+            self.cursor_pos -= 1;
+            self.input.remove(self.cursor_pos);
+            input_changed = true;
+        }
+        if keyboard.recently_pressed_or_repeated(Scancode::Delete)
+            && self.cursor_pos < self.input.len()
+        {
+            self.input.remove(self.cursor_pos);
+            input_changed = true;
+        }
+        if keyboard.recently_pressed_or_repeated(Scancode::Left) && self.cursor_pos > 0 {
+            self.cursor_pos -= 1;
+            input_changed = true;
+        }
+        if keyboard.recently_pressed_or_repeated(Scancode::Right)
+            && self.cursor_pos < self.input.len()
+        {
+            self.cursor_pos += 1;
+            input_changed = true;
+        }
+        if keyboard.recently_pressed(Scancode::Home) {
+            self.cursor_pos = 0;
+            input_changed = true;
+        }
+        if keyboard.recently_pressed(Scancode::End) {
+            self.cursor_pos = self.input.len();
+            input_changed = true;
+        }
+
+        // History
+        if keyboard.recently_pressed_or_repeated(Scancode::Up) && !self.history.is_empty() {
+            let index = match self.history_index {
+                Some(index) => index.saturating_sub(1),
+                None => self.history.len() - 1,
+            };
+            self.history_index = Some(index);
+            let text = self.history[index].clone();
+            self.set_input(&text);
+            input_changed = true;
+        }
+        if keyboard.recently_pressed_or_repeated(Scancode::Down) {
+            if let Some(index) = self.history_index {
+                if index + 1 < self.history.len() {
+                    self.history_index = Some(index + 1);
+                    let text = self.history[index + 1].clone();
+                    self.set_input(&text);
+                } else {
+                    self.history_index = None;
+                    self.set_input("");
+                }
+                input_changed = true;
+            }
+        }
+
+        // Scrolling
+        if keyboard.recently_pressed_or_repeated(Scancode::PageUp) {
+            self.scroll_offset = usize::min(self.scroll_offset + 1, self.lines.len());
+        }
+        if keyboard.recently_pressed_or_repeated(Scancode::PageDown) {
+            self.scroll_offset = self.scroll_offset.saturating_sub(1);
+        }
+
+        // Keep the cursor visible while typing
+        if input_changed {
+            self.cursor_blinker = Blinker::new(true, 0.5, 0.5);
+        }
+        self.cursor_visible = self.cursor_blinker.update_and_check(deltatime);
+
+        if keyboard.recently_pressed(Scancode::Return) {
+            let line = self.get_input();
+            self.print(&format!("{}{}", TERMINAL_PROMPT, line), COLOR_DEFAULT);
+            if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                self.history.push(line.clone());
+                if self.history.len() > TERMINAL_HISTORY_MAX {
+                    self.history.remove(0);
+                }
+            }
+            self.history_index = None;
+            self.set_input("");
+            return Some(TerminalAction::Submit(line));
+        }
+        if keyboard.recently_pressed(Scancode::Tab) {
+            return Some(TerminalAction::RequestCompletion);
+        }
+
+        None
+    }
+
+    /// Replaces the word left of the cursor with the only candidate or with the longest prefix
+    /// that all candidates share. Lists the candidates if there is more than one.
+    pub fn complete(&mut self, candidates: &[String]) {
+        if candidates.is_empty() {
+            return;
+        }
+
+        let word_start = self.input[..self.cursor_pos]
+            .iter()
+            .rposition(|character| character.is_whitespace())
+            .map_or(0, |index| index + 1);
+
+        let mut completion: Vec<char> = candidates[0].chars().collect();
+        for candidate in &candidates[1..] {
+            let common_len = completion
+                .iter()
+                .zip(candidate.chars())
+                .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+                .count();
+            completion.truncate(common_len);
+        }
+        if candidates.len() == 1 {
+            completion.push(' ');
+        } else {
+            self.print(&candidates.join("  "), COLOR_SKILL_POINT);
+        }
+
+        // NOTE: We only ever extend the word so that completing never loses typed text
+        if completion.len() >= self.cursor_pos - word_start {
+            self.input
+                .splice(word_start..self.cursor_pos, completion.iter().cloned());
+            self.cursor_pos = word_start + completion.len();
+        }
+        self.cursor_blinker = Blinker::new(true, 0.5, 0.5);
+    }
+
+    pub fn draw(&self, draw: &mut Drawstate, font: &SpriteFont, rect: Rect, depth: Depth) {
+        let line_count_visible =
+            ((rect.dim.y - 2.0 * TERMINAL_PADDING) / TERMINAL_LINE_HEIGHT) as usize;
+        let line_count_scrollback = line_count_visible.saturating_sub(1);
+
+        let lines_end = self.lines.len().saturating_sub(self.scroll_offset);
+        let lines_begin = lines_end.saturating_sub(line_count_scrollback);
+        let text_pos = rect.pos + Vec2::filled(TERMINAL_PADDING);
+
+        for (line_index, (text, color)) in self.lines[lines_begin..lines_end].iter().enumerate() {
+            draw.draw_text(
+                text,
+                font,
+                1.0,
+                text_pos + Vec2::filled_y(line_index as f32 * TERMINAL_LINE_HEIGHT),
+                Vec2::zero(),
+                None,
+                None,
+                depth,
+                *color,
+                ADDITIVITY_NONE,
+            );
+        }
+
+        // Input line with cursor
+        let input_pos =
+            text_pos + Vec2::filled_y((lines_end - lines_begin) as f32 * TERMINAL_LINE_HEIGHT);
+        let text_before_cursor: String = self.input[..self.cursor_pos].iter().collect();
+        let text_after_cursor: String = self.input[self.cursor_pos..].iter().collect();
+
+        let offset = draw.draw_text(
+            TERMINAL_PROMPT,
+            font,
+            1.0,
+            input_pos,
+            Vec2::zero(),
+            None,
+            None,
+            depth,
+            COLOR_SKILL_POINT,
+            ADDITIVITY_NONE,
+        );
+        let offset = draw.draw_text(
+            &text_before_cursor,
+            font,
+            1.0,
+            input_pos,
+            offset,
+            None,
+            None,
+            depth,
+            COLOR_DEFAULT,
+            ADDITIVITY_NONE,
+        );
+        if self.cursor_visible {
+            draw.draw_rect(
+                Rect::from_pos_width_height(input_pos + offset, 4.0, TERMINAL_LINE_HEIGHT - 2.0),
+                true,
+                depth,
+                COLOR_DEFAULT,
+                ADDITIVITY_NONE,
+            );
+        }
+        draw.draw_text(
+            &text_after_cursor,
+            font,
+            1.0,
+            input_pos,
+            offset,
+            None,
+            None,
+            depth,
+            COLOR_DEFAULT,
+            ADDITIVITY_NONE,
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Commands
+
+#[derive(Clone, Copy)]
+pub enum ArgType {
+    Int,
+    Float,
+    /// The name of an enum variant like `Blast` for `AttackType`. Holds the enum name and a
+    /// function that lists all its variant names (see `enum_variant_names`).
+    Enum(&'static str, fn() -> Vec<String>),
+    /// Free text. If it is the last argument of a command it takes the remainder of the line.
+    String,
+}
+
+impl ArgType {
+    fn get_name(&self) -> &'static str {
+        match self {
+            ArgType::Int => "int",
+            ArgType::Float => "float",
+            ArgType::Enum(enum_name, _) => enum_name,
+            ArgType::String => "string",
+        }
+    }
+}
+
+pub fn enum_variant_names<EnumType: IntoEnumIterator + std::fmt::Debug>() -> Vec<String> {
+    EnumType::iter()
+        .map(|variant| format!("{:?}", variant))
+        .collect()
+}
+
+/// An argument that was already parsed and checked against its declared `ArgType`
+#[derive(Debug, Clone)]
+pub enum CommandArg {
+    Int(i64),
+    Float(f32),
+    Enum(String),
+    String(String),
+}
+
+impl CommandArg {
+    pub fn as_int(&self) -> i64 {
+        match self {
+            CommandArg::Int(value) => *value,
+            _ => panic!("Command argument {:?} is not an int", self),
+        }
+    }
+
+    pub fn as_float(&self) -> f32 {
+        match self {
+            CommandArg::Float(value) => *value,
+            _ => panic!("Command argument {:?} is not a float", self),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            CommandArg::Enum(value) | CommandArg::String(value) => value,
+            _ => panic!("Command argument {:?} is not a string", self),
+        }
+    }
+
+    pub fn as_enum<EnumType: IntoEnumIterator + std::fmt::Debug>(&self) -> EnumType {
+        EnumType::iter()
+            .find(|variant| format!("{:?}", variant) == self.as_str())
+            .unwrap_or_else(|| panic!("Command argument {:?} is not a known variant", self))
+    }
+}
+
+/// Collects everything a command wants to tell the outside world
+pub struct CommandOutput {
+    pub lines: Vec<(String, Color)>,
+    pub game_events: Vec<GameEvent>,
+    pub clear_terminal: bool,
+}
+
+impl CommandOutput {
+    fn new() -> CommandOutput {
+        CommandOutput {
+            lines: Vec::new(),
+            game_events: Vec::new(),
+            clear_terminal: false,
+        }
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.lines.push((text.to_string(), COLOR_DEFAULT));
+    }
+
+    pub fn print_error(&mut self, text: &str) {
+        self.lines.push((text.to_string(), COLOR_HP));
+    }
+
+    pub fn switch_to_scene(&mut self, scene_name: &str) {
+        self.game_events.push(GameEvent::SwitchToScene {
+            scene_name: scene_name.to_string(),
+        });
+    }
+}
+
+/// Gets the context of the scene that registered the command
+pub type CommandHandler<ContextType> =
+    fn(&mut ContextType, &[CommandArg], &mut CommandOutput) -> Result<(), String>;
+
+pub struct Command<ContextType> {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub args: &'static [(&'static str, ArgType)],
+    pub handler: CommandHandler<ContextType>,
+}
+
+// NOTE: We implement these by hand because deriving would require `ContextType: Clone`
+impl<ContextType> Clone for Command<ContextType> {
+    fn clone(&self) -> Self {
+        Command {
+            name: self.name,
+            help: self.help,
+            args: self.args,
+            handler: self.handler,
+        }
+    }
+}
+
+impl<ContextType> Command<ContextType> {
+    fn get_usage(&self) -> String {
+        let mut usage = self.name.to_string();
+        for (arg_name, arg_type) in self.args {
+            usage += &format!(" <{}: {}>", arg_name, arg_type.get_name());
+        }
+        usage
+    }
+}
+
+/// A whitespace separated word of a command line together with its byte position in the line
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut token_start = None;
+    for (index, character) in line.char_indices() {
+        match (character.is_whitespace(), token_start) {
+            (false, None) => token_start = Some(index),
+            (true, Some(start)) => {
+                tokens.push(Token {
+                    text: &line[start..index],
+                    start,
+                });
+                token_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = token_start {
+        tokens.push(Token {
+            text: &line[start..],
+            start,
+        });
+    }
+    tokens
+}
+
+/// Repeats the line with the offending token marked so that the user sees what went wrong
+fn format_error_at_token(line: &str, token: Option<Token>, message: &str) -> String {
+    let marked_line = match token {
+        Some(token) => format!(
+            "{}[{}]{}",
+            &line[..token.start],
+            token.text,
+            &line[token.start + token.text.len()..]
+        ),
+        None => format!("{} [?]", line.trim_end()),
+    };
+    format!("  {}\n{}", marked_line, message)
+}
+
+fn parse_arg(token: &str, arg_type: ArgType) -> Result<CommandArg, String> {
+    match arg_type {
+        ArgType::Int => token
+            .parse::<i64>()
+            .map(CommandArg::Int)
+            .map_err(|_| format!("'{}' is not an int", token)),
+        ArgType::Float => token
+            .parse::<f32>()
+            .map(CommandArg::Float)
+            .map_err(|_| format!("'{}' is not a float", token)),
+        ArgType::Enum(enum_name, variant_names) => variant_names()
+            .into_iter()
+            .find(|variant| variant.eq_ignore_ascii_case(token))
+            .map(CommandArg::Enum)
+            .ok_or_else(|| format!("'{}' is not a known {}", token, enum_name)),
+        ArgType::String => Ok(CommandArg::String(token.to_string())),
+    }
+}
+
+/// Lets scenes expose their own commands to a `Terminal`. The handlers get passed the scene
+/// specific `ContextType` on execution.
+pub struct CommandRegistry<ContextType> {
+    commands: Vec<Command<ContextType>>,
+}
+
+impl<ContextType> Clone for CommandRegistry<ContextType> {
+    fn clone(&self) -> Self {
+        CommandRegistry {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<ContextType> CommandRegistry<ContextType> {
+    pub fn new() -> CommandRegistry<ContextType> {
+        CommandRegistry {
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        args: &'static [(&'static str, ArgType)],
+        handler: CommandHandler<ContextType>,
+    ) {
+        assert!(
+            name != "help" && self.get(name).is_none(),
+            "Console command '{}' was registered twice",
+            name
+        );
+        self.commands.push(Command {
+            name,
+            help,
+            args,
+            handler,
+        });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command<ContextType>> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    fn get_command_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .commands
+            .iter()
+            .map(|command| command.name.to_string())
+            .collect();
+        names.push("help".to_string());
+        names.sort();
+        names
+    }
+
+    pub fn execute(&self, context: &mut ContextType, line: &str) -> CommandOutput {
+        let mut output = CommandOutput::new();
+
+        let tokens = tokenize(line);
+        let (name_token, arg_tokens) = match tokens.split_first() {
+            Some((name_token, arg_tokens)) => (*name_token, arg_tokens),
+            None => return output,
+        };
+
+        if name_token.text == "help" {
+            self.print_help(&mut output, line, arg_tokens);
+            return output;
+        }
+
+        let command = match self.get(name_token.text) {
+            Some(command) => command,
+            None => {
+                output.print_error(&format_error_at_token(
+                    line,
+                    Some(name_token),
+                    "Unknown command - type 'help' for a list",
+                ));
+                return output;
+            }
+        };
+
+        match self.parse_args(command, line, arg_tokens) {
+            Ok(args) => {
+                if let Err(error) = (command.handler)(context, &args, &mut output) {
+                    output.print_error(&error);
+                }
+            }
+            Err(error) => {
+                output.print_error(&error);
+                output.print(&format!("Usage: {}", command.get_usage()));
+            }
+        }
+
+        output
+    }
+
+    fn parse_args(
+        &self,
+        command: &Command<ContextType>,
+        line: &str,
+        arg_tokens: &[Token],
+    ) -> Result<Vec<CommandArg>, String> {
+        let mut args = Vec::new();
+        for (arg_index, (arg_name, arg_type)) in command.args.iter().enumerate() {
+            let token = match arg_tokens.get(arg_index) {
+                Some(token) => *token,
+                None => {
+                    return Err(format_error_at_token(
+                        line,
+                        None,
+                        &format!("Missing argument <{}: {}>", arg_name, arg_type.get_name()),
+                    ))
+                }
+            };
+
+            let is_last_arg = arg_index == command.args.len() - 1;
+            if is_last_arg {
+                if let ArgType::String = arg_type {
+                    args.push(CommandArg::String(
+                        line[token.start..].trim_end().to_string(),
+                    ));
+                    return Ok(args);
+                }
+            }
+
+            let arg = parse_arg(token.text, *arg_type)
+                .map_err(|error| format_error_at_token(line, Some(token), &error))?;
+            args.push(arg);
+        }
+
+        if let Some(token) = arg_tokens.get(command.args.len()) {
+            return Err(format_error_at_token(
+                line,
+                Some(*token),
+                &format!(
+                    "Too many arguments - '{}' takes {}",
+                    command.name,
+                    command.args.len()
+                ),
+            ));
+        }
+
+        Ok(args)
+    }
+
+    fn print_help(&self, output: &mut CommandOutput, line: &str, arg_tokens: &[Token]) {
+        match arg_tokens.first() {
+            None => {
+                for command in &self.commands {
+                    output.print(&format!("{} - {}", command.get_usage(), command.help));
+                }
+                output.print("help <command> - Shows the usage of a command");
+            }
+            Some(token) => match self.get(token.text) {
+                Some(command) => {
+                    output.print(&format!("Usage: {}", command.get_usage()));
+                    output.print(command.help);
+                }
+                None => output.print_error(&format_error_at_token(
+                    line,
+                    Some(*token),
+                    "Unknown command - type 'help' for a list",
+                )),
+            },
+        }
+    }
+
+    /// Returns all candidates for the last (possibly empty) word of the given partial line
+    pub fn get_completions(&self, line: &str) -> Vec<String> {
+        let tokens = tokenize(line);
+        let ends_with_whitespace = line.chars().last().map_or(true, char::is_whitespace);
+        let (token_index, partial) = if ends_with_whitespace {
+            (tokens.len(), "")
+        } else {
+            (tokens.len() - 1, tokens[tokens.len() - 1].text)
+        };
+
+        let candidates = if token_index == 0 {
+            self.get_command_names()
+        } else if tokens[0].text == "help" {
+            if token_index == 1 {
+                self.get_command_names()
+            } else {
+                Vec::new()
+            }
+        } else {
+            match self
+                .get(tokens[0].text)
+                .and_then(|command| command.args.get(token_index - 1))
+            {
+                Some((_, ArgType::Enum(_, variant_names))) => variant_names(),
+                _ => Vec::new(),
+            }
+        };
+
+        candidates
+            .into_iter()
+            .filter(|candidate| {
+                candidate.len() >= partial.len()
+                    && candidate[..partial.len()].eq_ignore_ascii_case(partial)
+            })
+            .collect()
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Console Scene
 
 #[derive(Clone)]
-pub struct SceneConsole {}
+pub struct SceneConsole {
+    terminal: Terminal,
+    commands: CommandRegistry<()>,
+}
 
 impl SceneConsole {
     pub fn new() -> SceneConsole {
-        SceneConsole {}
+        let mut terminal = Terminal::new();
+        terminal.print("BYTEPATH TUTORIAL", COLOR_SKILL_POINT);
+        terminal.print("Type 'help' for a list of commands", COLOR_DEFAULT);
+
+        let mut commands = CommandRegistry::new();
+        commands.register("start", "Starts a new run", &[], |_, _, output| {
+            output.switch_to_scene("stage");
+            Ok(())
+        });
+        commands.register("replay", "Plays back the last run", &[], |_, _, output| {
+            output.switch_to_scene("replay");
+            Ok(())
+        });
+        commands.register("skilltree", "Opens the skilltree", &[], |_, _, output| {
+            output.switch_to_scene("skilltree");
+            Ok(())
+        });
+        commands.register("clear", "Clears the screen", &[], |_, _, output| {
+            output.clear_terminal = true;
+            Ok(())
+        });
+        commands.register(
+            "echo",
+            "Prints the given text",
+            &[("text", ArgType::String)],
+            |_, args, output| {
+                output.print(args[0].as_str());
+                Ok(())
+            },
+        );
+
+        SceneConsole { terminal, commands }
     }
 }
+
 impl Scene for SceneConsole {
     fn update_and_draw(
         &mut self,
-        _draw: &mut Drawstate,
+        draw: &mut Drawstate,
         _audio: &mut Audiostate,
         _assets: &mut GameAssets,
-        _input: &GameInput,
-        _globals: &mut Globals,
-        _out_game_events: &mut Vec<GameEvent>,
+        input: &GameInput,
+        globals: &mut Globals,
+        out_game_events: &mut Vec<GameEvent>,
     ) {
+        draw.set_clear_color_and_depth(COLOR_BACKGROUND, DEPTH_BACKGROUND);
+
+        self.terminal.update_and_run_commands(
+            input,
+            globals.deltatime,
+            &self.commands,
+            &mut (),
+            out_game_events,
+        );
+
+        self.terminal.draw(
+            draw,
+            &globals.font_default,
+            Rect::from_width_height(globals.canvas_width, globals.canvas_height),
+            DEPTH_TEXT,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stage::AttackType;
+
+    /// Collects the arguments of every command that ran successfully
+    type TestContext = Vec<CommandArg>;
+
+    fn collect_args(
+        context: &mut TestContext,
+        args: &[CommandArg],
+        _output: &mut CommandOutput,
+    ) -> Result<(), String> {
+        context.extend_from_slice(args);
+        Ok(())
+    }
+
+    fn create_test_registry() -> CommandRegistry<TestContext> {
+        let mut commands = CommandRegistry::new();
+        commands.register(
+            "give",
+            "Gives an attack",
+            &[(
+                "attack",
+                ArgType::Enum("AttackType", enum_variant_names::<AttackType>),
+            )],
+            collect_args,
+        );
+        commands.register(
+            "spawn",
+            "Spawns things",
+            &[("x", ArgType::Float), ("count", ArgType::Int)],
+            collect_args,
+        );
+        commands.register(
+            "say",
+            "Says something multiple times",
+            &[("count", ArgType::Int), ("text", ArgType::String)],
+            collect_args,
+        );
+        commands
+    }
+
+    fn parse_test_line(line: &str) -> Result<Vec<CommandArg>, String> {
+        let commands = create_test_registry();
+        let tokens = tokenize(line);
+        let command = commands.get(tokens[0].text).unwrap();
+        commands.parse_args(command, line, &tokens[1..])
+    }
+
+    fn assert_parse_error(line: &str, marked_line_expected: &str, message_expected: &str) {
+        let error = parse_test_line(line).unwrap_err();
+        assert_eq!(
+            error,
+            format!("  {}\n{}", marked_line_expected, message_expected)
+        );
+    }
+
+    #[test]
+    fn tokenize_splits_on_whitespace_and_keeps_byte_positions() {
+        let tokens = tokenize("  give\tBlast  ä x ");
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+        let starts: Vec<usize> = tokens.iter().map(|token| token.start).collect();
+        assert_eq!(texts, ["give", "Blast", "ä", "x"]);
+        assert_eq!(starts, [2, 7, 14, 17]);
+
+        assert!(tokenize("").is_empty());
+        assert!(tokenize(" \t ").is_empty());
+    }
+
+    #[test]
+    fn format_error_at_token_marks_token_or_end_of_line() {
+        let line = "spawn 1 x ";
+        let tokens = tokenize(line);
+        assert_eq!(
+            format_error_at_token(line, Some(tokens[2]), "Bad"),
+            "  spawn 1 [x] \nBad"
+        );
+        assert_eq!(
+            format_error_at_token(line, None, "Missing"),
+            "  spawn 1 x [?]\nMissing"
+        );
+    }
+
+    #[test]
+    fn parse_args_converts_to_declared_types() {
+        let args = parse_test_line("spawn -1.5 3").unwrap();
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].as_float(), -1.5);
+        assert_eq!(args[1].as_int(), 3);
+
+        // NOTE: Enum arguments are matched case insensitive but always passed on as declared
+        let args = parse_test_line("give bLAST").unwrap();
+        assert_eq!(args[0].as_str(), "Blast");
+        assert_eq!(args[0].as_enum::<AttackType>(), AttackType::Blast);
+    }
+
+    #[test]
+    fn parse_args_passes_remainder_of_line_to_last_string_arg() {
+        let args = parse_test_line("say 2 hello   world ").unwrap();
+        assert_eq!(args[0].as_int(), 2);
+        assert_eq!(args[1].as_str(), "hello   world");
+    }
+
+    #[test]
+    fn parse_args_reports_type_errors_at_token() {
+        assert_parse_error("spawn 1 2.5", "spawn 1 [2.5]", "'2.5' is not an int");
+        assert_parse_error("spawn one 2", "spawn [one] 2", "'one' is not a float");
+        assert_parse_error("say x hi", "say [x] hi", "'x' is not an int");
+    }
+
+    #[test]
+    fn parse_args_reports_unknown_enum_variant() {
+        assert_parse_error(
+            "give Blastt",
+            "give [Blastt]",
+            "'Blastt' is not a known AttackType",
+        );
+    }
+
+    #[test]
+    fn parse_args_reports_missing_and_superfluous_args() {
+        assert_parse_error("spawn 1", "spawn 1 [?]", "Missing argument <count: int>");
+        assert_parse_error(
+            "give Blast Spread",
+            "give Blast [Spread]",
+            "Too many arguments - 'give' takes 1",
+        );
+    }
+
+    #[test]
+    fn execute_runs_handler_or_prints_usage() {
+        let commands = create_test_registry();
+        let mut context = TestContext::new();
+
+        let output = commands.execute(&mut context, "give Spread");
+        assert!(output.lines.is_empty());
+        assert_eq!(context.len(), 1);
+
+        let output = commands.execute(&mut context, "spawn 1");
+        assert_eq!(output.lines.len(), 2);
+        assert_eq!(output.lines[1].0, "Usage: spawn <x: float> <count: int>");
+        assert_eq!(context.len(), 1);
+
+        let output = commands.execute(&mut context, "spwan 1 2");
+        assert_eq!(
+            output.lines[0].0,
+            "  [spwan] 1 2\nUnknown command - type 'help' for a list"
+        );
+    }
+
+    #[test]
+    fn get_completions_completes_commands() {
+        let commands = create_test_registry();
+        assert_eq!(
+            commands.get_completions(""),
+            ["give", "help", "say", "spawn"]
+        );
+        assert_eq!(commands.get_completions("s"), ["say", "spawn"]);
+        assert_eq!(commands.get_completions("SP"), ["spawn"]);
+        assert_eq!(commands.get_completions("help g"), ["give"]);
+        assert!(commands.get_completions("help give ").is_empty());
+        assert!(commands.get_completions("x").is_empty());
+    }
+
+    #[test]
+    fn get_completions_completes_enum_args() {
+        let commands = create_test_registry();
+        assert_eq!(
+            commands.get_completions("give "),
+            enum_variant_names::<AttackType>()
+        );
+        assert_eq!(
+            commands.get_completions("give sp"),
+            ["Spread", "Spin", "Split2", "Split4"]
+        );
+        assert!(commands.get_completions("give Blast ").is_empty());
+        assert!(commands.get_completions("spawn ").is_empty());
+        assert!(commands.get_completions("unknown ").is_empty());
     }
 }
@@ -0,0 +1,423 @@
+use ct_lib::game::*;
+use ct_lib::math::*;
+use ct_lib::random::*;
+
+use serde_derive::Deserialize;
+use strum_macros::EnumIter;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, EnumIter, Deserialize)]
+pub enum ResourceType {
+    Boost,
+    Skillpoint,
+    Health,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, EnumIter, Deserialize)]
+pub enum EnemyType {
+    Rock,
+    Shooter,
+    BigRock,
+    Waver,
+    Seeker,
+    Orbitter,
+    Sapper,
+    Trailer,
+    Boss,
+}
+
+pub const DIRECTOR_CONFIG_FILEPATH: &str = "resources/director.json";
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct EnemyWeight {
+    pub enemy: EnemyType,
+    pub weight: usize,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct ResourceWeight {
+    pub resource: ResourceType,
+    pub weight: usize,
+}
+
+/// Every round picks a random weight in [weight_min, weight_max[ for the enemy
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct EnemyWeightRange {
+    pub enemy: EnemyType,
+    pub weight_min: usize,
+    pub weight_max: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DifficultyTable {
+    pub difficulty: usize,
+    pub spending_points: i32,
+    pub enemy_weights: Vec<EnemyWeight>,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum SpendingPointsStep {
+    Add(i32),
+    Multiply(f32),
+    Divide(f32),
+}
+impl SpendingPointsStep {
+    fn apply(self, points: i32) -> i32 {
+        match self {
+            SpendingPointsStep::Add(value) => points + value,
+            SpendingPointsStep::Multiply(factor) => (points as f32 * factor) as i32,
+            SpendingPointsStep::Divide(divisor) => (points as f32 / divisor) as i32,
+        }
+    }
+}
+
+/// Used for difficulties above the last table
+#[derive(Debug, Clone, Deserialize)]
+pub struct DifficultyFallback {
+    /// Going from difficulty `n` to `n + 1` applies step `(n - 1) % len`
+    pub spending_points_steps: Vec<SpendingPointsStep>,
+    pub enemy_weight_ranges: Vec<EnemyWeightRange>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DirectorConfig {
+    pub round_duration: f32,
+    pub attack_spawn_interval: f32,
+    pub resource_spawn_interval: f32,
+    /// Every n-th round is followed by a boss round
+    pub boss_round_interval: usize,
+    pub resource_weights: Vec<ResourceWeight>,
+    /// NOTE: Sorted by difficulty
+    pub difficulties: Vec<DifficultyTable>,
+    pub fallback: DifficultyFallback,
+}
+
+impl DirectorConfig {
+    /// Interpolates between the surrounding tables and continues with the fallback steps after
+    /// the last table
+    pub fn get_spending_points_for_difficulty(&self, difficulty: usize) -> i32 {
+        let table_last = self.difficulties.last().unwrap();
+        if difficulty >= table_last.difficulty {
+            let steps = &self.fallback.spending_points_steps;
+            let mut points = table_last.spending_points;
+            for n in table_last.difficulty..difficulty {
+                points = steps[(n - 1) % steps.len()].apply(points);
+            }
+            return points;
+        }
+
+        let index_next = self
+            .difficulties
+            .iter()
+            .position(|table| table.difficulty > difficulty)
+            .unwrap();
+        let table_next = &self.difficulties[index_next];
+        if index_next == 0 {
+            return table_next.spending_points;
+        }
+        let table_prev = &self.difficulties[index_next - 1];
+        let percentage = (difficulty - table_prev.difficulty) as f32
+            / (table_next.difficulty - table_prev.difficulty) as f32;
+        lerp(
+            table_prev.spending_points as f32,
+            table_next.spending_points as f32,
+            percentage,
+        )
+        .round() as i32
+    }
+
+    /// Difficulties between two tables use the lower table
+    pub fn get_enemy_spawn_chance(
+        &self,
+        random: &mut Random,
+        difficulty: usize,
+    ) -> Vec<(EnemyType, usize)> {
+        let table_last = self.difficulties.last().unwrap();
+        if difficulty > table_last.difficulty {
+            return self
+                .fallback
+                .enemy_weight_ranges
+                .iter()
+                .map(|range| {
+                    (
+                        range.enemy,
+                        random.gen_range(range.weight_min, range.weight_max),
+                    )
+                })
+                .collect();
+        }
+
+        let table = self
+            .difficulties
+            .iter()
+            .rev()
+            .find(|table| table.difficulty <= difficulty)
+            .unwrap_or(&self.difficulties[0]);
+        table
+            .enemy_weights
+            .iter()
+            .map(|entry| (entry.enemy, entry.weight))
+            .collect()
+    }
+
+    pub fn get_resource_spawn_chance(&self) -> Vec<(ResourceType, usize)> {
+        self.resource_weights
+            .iter()
+            .map(|entry| (entry.resource, entry.weight))
+            .collect()
+    }
+}
+
+pub fn load_director_config_from_file(filepath: &str) -> Result<DirectorConfig, String> {
+    let content = std::fs::read_to_string(filepath)
+        .map_err(|error| format!("Could not read director config '{}': {}", filepath, error))?;
+    let config: DirectorConfig = serde_json::from_str(&content)
+        .map_err(|error| format!("Could not parse director config '{}': {}", filepath, error))?;
+    validate_director_config(&config)
+        .map_err(|error| format!("Director config '{}' is invalid:\n{}", filepath, error))?;
+    Ok(config)
+}
+
+fn is_spawnable_enemy(enemy: EnemyType, weight: usize) -> bool {
+    // NOTE: Bosses only appear in boss rounds
+    enemy != EnemyType::Boss && weight > 0
+}
+
+/// Returns all validation errors at once so that they can be fixed in one go
+fn validate_director_config(config: &DirectorConfig) -> Result<(), String> {
+    let mut errors = Vec::new();
+
+    if config.round_duration <= 0.0
+        || config.attack_spawn_interval <= 0.0
+        || config.resource_spawn_interval <= 0.0
+    {
+        errors.push("Round duration and spawn intervals must be positive".to_owned());
+    }
+    if config.boss_round_interval == 0 {
+        errors.push("Boss round interval must be positive".to_owned());
+    }
+    if config
+        .resource_weights
+        .iter()
+        .all(|entry| entry.weight == 0)
+    {
+        errors.push("Resource weights have no spawnable resources".to_owned());
+    }
+
+    if config.difficulties.is_empty() {
+        errors.push("There are no difficulty tables".to_owned());
+    }
+    let mut difficulty_prev = 0;
+    for table in &config.difficulties {
+        if table.difficulty <= difficulty_prev {
+            errors.push(format!(
+                "Difficulty {}: tables must be sorted and start at difficulty 1 or above",
+                table.difficulty
+            ));
+        }
+        difficulty_prev = table.difficulty;
+
+        if table.spending_points <= 0 {
+            errors.push(format!(
+                "Difficulty {}: spending points {} must be positive",
+                table.difficulty, table.spending_points
+            ));
+        }
+        if table
+            .enemy_weights
+            .iter()
+            .any(|entry| entry.enemy == EnemyType::Boss)
+        {
+            errors.push(format!(
+                "Difficulty {}: bosses must not be in the spawn tables",
+                table.difficulty
+            ));
+        }
+        if !table
+            .enemy_weights
+            .iter()
+            .any(|entry| is_spawnable_enemy(entry.enemy, entry.weight))
+        {
+            errors.push(format!(
+                "Difficulty {}: table has no spawnable enemies",
+                table.difficulty
+            ));
+        }
+    }
+
+    let fallback = &config.fallback;
+    if fallback.spending_points_steps.is_empty() {
+        errors.push("Fallback: there are no spending point steps".to_owned());
+    }
+    for step in &fallback.spending_points_steps {
+        match step {
+            SpendingPointsStep::Add(_) => {}
+            SpendingPointsStep::Multiply(value) | SpendingPointsStep::Divide(value) => {
+                if *value <= 0.0 {
+                    errors.push(format!("Fallback: step {:?} must be positive", step));
+                }
+            }
+        }
+    }
+    for range in &fallback.enemy_weight_ranges {
+        if range.enemy == EnemyType::Boss {
+            errors.push("Fallback: bosses must not be in the spawn tables".to_owned());
+        }
+        if range.weight_min >= range.weight_max {
+            errors.push(format!(
+                "Fallback: {:?} weight range [{}, {}[ is empty",
+                range.enemy, range.weight_min, range.weight_max
+            ));
+        }
+    }
+    // NOTE: Random weights can only be relied upon when their minimum is above zero
+    if !fallback
+        .enemy_weight_ranges
+        .iter()
+        .any(|range| is_spawnable_enemy(range.enemy, range.weight_min))
+    {
+        errors.push("Fallback: table has no spawnable enemies".to_owned());
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+#[derive(Clone)]
+pub struct Director {
+    pub difficulty: usize,
+    pub timer_spawn_attack: TriggerRepeating,
+    pub timer_spawn_resource: TriggerRepeating,
+    pub timer_round: TimerSimple,
+    pub round_enemies_and_spawntimes: std::collections::VecDeque<(EnemyType, f32)>,
+    pub is_boss_round: bool,
+    pub config: DirectorConfig,
+}
+impl Director {
+    pub fn new(random: &mut Random, config: DirectorConfig) -> Director {
+        let difficulty = 1;
+        Director {
+            difficulty,
+            timer_spawn_attack: TriggerRepeating::new(config.attack_spawn_interval),
+            timer_spawn_resource: TriggerRepeating::new(config.resource_spawn_interval),
+            timer_round: TimerSimple::new_started(config.round_duration),
+            round_enemies_and_spawntimes: Director::create_enemies_and_spawntimes(
+                random, &config, difficulty,
+            ),
+            is_boss_round: false,
+            config,
+        }
+    }
+
+    pub fn update_and_spawn_enemies_and_collectibles(
+        &mut self,
+        random: &mut Random,
+        deltatime: f32,
+        boss_alive: bool,
+    ) -> (Option<EnemyType>, Option<ResourceType>, bool) {
+        self.timer_round.update(deltatime);
+
+        // NOTE: We need to check this before the boss gets taken from the queue because it only
+        //       shows up in the world after we return
+        let boss_defeated =
+            self.is_boss_round && self.round_enemies_and_spawntimes.is_empty() && !boss_alive;
+
+        // NOTE: Which attack to spawn is up to the caller
+        let spawn_attack = self.timer_spawn_attack.update_and_check(deltatime);
+
+        let resource_to_spawn = if self.timer_spawn_resource.update_and_check(deltatime) {
+            let mut shufflebag =
+                Shufflebag::new_with_counts(&self.config.get_resource_spawn_chance());
+            let resourcetype = shufflebag.get_next(random);
+            Some(resourcetype)
+        } else {
+            None
+        };
+
+        let enemy_to_spawn = {
+            if let Some((enemy, spawn_time)) = self.round_enemies_and_spawntimes.front().cloned() {
+                if spawn_time <= self.timer_round.time_cur {
+                    self.round_enemies_and_spawntimes.pop_front();
+                    Some(enemy)
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        };
+
+        // Update difficulty
+        if self.is_boss_round {
+            // Normal spawns pause until the boss dies
+            if boss_defeated {
+                self.restart_round(random, self.difficulty + 1);
+            }
+        } else if self.timer_round.is_finished() {
+            if self.difficulty % self.config.boss_round_interval == 0 {
+                self.start_boss_round();
+            } else {
+                self.restart_round(random, self.difficulty + 1);
+            }
+        }
+
+        (enemy_to_spawn, resource_to_spawn, spawn_attack)
+    }
+
+    pub fn restart_round(&mut self, random: &mut Random, difficulty: usize) {
+        self.difficulty = difficulty;
+        self.is_boss_round = false;
+        self.timer_round.restart();
+        self.round_enemies_and_spawntimes =
+            Director::create_enemies_and_spawntimes(random, &self.config, self.difficulty);
+    }
+
+    pub fn start_boss_round(&mut self) {
+        self.is_boss_round = true;
+        self.timer_round.restart();
+        self.round_enemies_and_spawntimes = vec![(EnemyType::Boss, 0.0)].into_iter().collect();
+    }
+
+    fn create_enemies_and_spawntimes(
+        random: &mut Random,
+        config: &DirectorConfig,
+        difficulty: usize,
+    ) -> std::collections::VecDeque<(EnemyType, f32)> {
+        let enemy_spawn_chances = config.get_enemy_spawn_chance(random, difficulty);
+
+        let mut points = config.get_spending_points_for_difficulty(difficulty);
+        let mut enemybag = Shufflebag::new_with_counts(&enemy_spawn_chances);
+        let mut enemies = Vec::new();
+
+        while points > 0 {
+            let enemy = enemybag.get_next(random);
+            points -= Director::get_spending_cost_for_enemy(enemy);
+            enemies.push(enemy);
+        }
+
+        let mut spawn_times = Vec::new();
+        for _ in 0..enemies.len() {
+            spawn_times.push(random.f32_in_range_open(0.0, config.round_duration))
+        }
+        spawn_times.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+
+        enemies.into_iter().zip(spawn_times.into_iter()).collect()
+    }
+
+    fn get_spending_cost_for_enemy(enemytype: EnemyType) -> i32 {
+        match enemytype {
+            EnemyType::Rock => 1,
+            EnemyType::Shooter => 2,
+            EnemyType::BigRock => 3,
+            EnemyType::Waver => 3,
+            EnemyType::Seeker => 4,
+            EnemyType::Orbitter => 6,
+            EnemyType::Sapper => 4,
+            EnemyType::Trailer => 5,
+            // NOTE: Bosses only appear in boss rounds and never in the spawn tables
+            EnemyType::Boss => 40,
+        }
+    }
+}
@@ -0,0 +1,296 @@
+// Runs the director without any window or drawing to see how spawn budgets evolve over the rounds
+//
+// Usage: director_simulator [--seed N] [--duration SECONDS] [--boss-duration SECONDS]
+//                           [--config FILEPATH] [--format csv|json]
+
+use ct_lib::random::*;
+
+use launcher::director::{
+    load_director_config_from_file, Director, EnemyType, ResourceType, DIRECTOR_CONFIG_FILEPATH,
+};
+
+use serde_derive::Serialize;
+use strum::IntoEnumIterator;
+
+use std::collections::BTreeMap;
+
+const DELTATIME: f32 = 1.0 / 60.0;
+
+/// Enemies that spawn closer together than this count as one cluster
+const SPAWN_CLUSTER_WINDOW: f32 = 2.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OutputFormat {
+    Csv,
+    Json,
+}
+
+struct Options {
+    seed: u64,
+    duration: f32,
+    /// How long a spawned boss takes to get killed as we have no player
+    boss_duration: f32,
+    config_filepath: String,
+    format: OutputFormat,
+}
+
+impl Options {
+    fn from_args(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            seed: 0,
+            duration: 600.0,
+            boss_duration: 30.0,
+            config_filepath: DIRECTOR_CONFIG_FILEPATH.to_owned(),
+            format: OutputFormat::Csv,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for '{}'", arg))?;
+            match arg.as_str() {
+                "--seed" => {
+                    options.seed = value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed '{}'", value))?
+                }
+                "--duration" => {
+                    options.duration = value
+                        .parse()
+                        .map_err(|_| format!("Invalid duration '{}'", value))?
+                }
+                "--boss-duration" => {
+                    options.boss_duration = value
+                        .parse()
+                        .map_err(|_| format!("Invalid boss duration '{}'", value))?
+                }
+                "--config" => options.config_filepath = value.clone(),
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "csv" => OutputFormat::Csv,
+                        "json" => OutputFormat::Json,
+                        _ => return Err(format!("Unknown format '{}'", value)),
+                    }
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+
+        if options.duration <= 0.0 || options.boss_duration <= 0.0 {
+            return Err("Durations must be positive".to_owned());
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RoundStats {
+    round: usize,
+    difficulty: usize,
+    is_boss_round: bool,
+    spending_points: i32,
+    duration: f32,
+    enemy_counts: BTreeMap<String, usize>,
+    resource_counts: BTreeMap<String, usize>,
+    attack_count: usize,
+    /// Most enemies that spawned within `SPAWN_CLUSTER_WINDOW` seconds of each other
+    max_spawns_in_window: usize,
+    spawn_gap_min: f32,
+    spawn_gap_mean: f32,
+
+    #[serde(skip)]
+    spawn_times: Vec<f32>,
+}
+
+impl RoundStats {
+    fn new(round: usize, director: &Director) -> RoundStats {
+        RoundStats {
+            round,
+            difficulty: director.difficulty,
+            is_boss_round: director.is_boss_round,
+            spending_points: if director.is_boss_round {
+                0
+            } else {
+                director
+                    .config
+                    .get_spending_points_for_difficulty(director.difficulty)
+            },
+            duration: 0.0,
+            enemy_counts: EnemyType::iter()
+                .map(|enemytype| (format!("{:?}", enemytype), 0))
+                .collect(),
+            resource_counts: ResourceType::iter()
+                .map(|resourcetype| (format!("{:?}", resourcetype), 0))
+                .collect(),
+            attack_count: 0,
+            max_spawns_in_window: 0,
+            spawn_gap_min: 0.0,
+            spawn_gap_mean: 0.0,
+            spawn_times: Vec::new(),
+        }
+    }
+
+    fn finish(&mut self) {
+        // NOTE: Spawn times are recorded in increasing order
+        let mut window_start = 0;
+        for window_end in 0..self.spawn_times.len() {
+            while self.spawn_times[window_end] - self.spawn_times[window_start]
+                > SPAWN_CLUSTER_WINDOW
+            {
+                window_start += 1;
+            }
+            self.max_spawns_in_window =
+                usize::max(self.max_spawns_in_window, window_end - window_start + 1);
+        }
+
+        let gaps: Vec<f32> = self
+            .spawn_times
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        if !gaps.is_empty() {
+            self.spawn_gap_min = gaps.iter().cloned().fold(std::f32::MAX, f32::min);
+            self.spawn_gap_mean = gaps.iter().sum::<f32>() / gaps.len() as f32;
+        }
+    }
+}
+
+fn simulate(options: &Options) -> Result<Vec<RoundStats>, String> {
+    let config = load_director_config_from_file(&options.config_filepath)?;
+    let mut random = Random::new_from_seed(options.seed);
+    let mut director = Director::new(&mut random, config);
+
+    let mut rounds = Vec::new();
+    let mut current = RoundStats::new(1, &director);
+    let mut boss_time_left: Option<f32> = None;
+
+    let frame_count = (options.duration / DELTATIME) as usize;
+    for _ in 0..frame_count {
+        let (enemy_to_spawn, resource_to_spawn, spawn_attack) = director
+            .update_and_spawn_enemies_and_collectibles(
+                &mut random,
+                DELTATIME,
+                boss_time_left.is_some(),
+            );
+
+        // NOTE: Spawns still belong to the round that was active before the update
+        current.duration += DELTATIME;
+        if let Some(enemytype) = enemy_to_spawn {
+            *current
+                .enemy_counts
+                .get_mut(&format!("{:?}", enemytype))
+                .unwrap() += 1;
+            current.spawn_times.push(current.duration);
+            if enemytype == EnemyType::Boss {
+                boss_time_left = Some(options.boss_duration);
+            }
+        }
+        if let Some(resourcetype) = resource_to_spawn {
+            *current
+                .resource_counts
+                .get_mut(&format!("{:?}", resourcetype))
+                .unwrap() += 1;
+        }
+        if spawn_attack {
+            current.attack_count += 1;
+        }
+
+        boss_time_left = boss_time_left
+            .map(|time_left| time_left - DELTATIME)
+            .filter(|&time_left| time_left > 0.0);
+
+        if director.difficulty != current.difficulty
+            || director.is_boss_round != current.is_boss_round
+        {
+            let next = RoundStats::new(current.round + 1, &director);
+            let mut finished = std::mem::replace(&mut current, next);
+            finished.finish();
+            rounds.push(finished);
+        }
+    }
+    current.finish();
+    rounds.push(current);
+
+    Ok(rounds)
+}
+
+fn print_csv(rounds: &[RoundStats]) {
+    let enemy_names: Vec<String> = EnemyType::iter()
+        .map(|enemytype| format!("{:?}", enemytype))
+        .collect();
+    let resource_names: Vec<String> = ResourceType::iter()
+        .map(|resourcetype| format!("{:?}", resourcetype))
+        .collect();
+
+    let mut header = vec![
+        "round".to_owned(),
+        "difficulty".to_owned(),
+        "is_boss_round".to_owned(),
+        "spending_points".to_owned(),
+        "duration".to_owned(),
+    ];
+    header.extend(enemy_names.iter().map(|name| format!("enemy_{}", name)));
+    header.extend(resource_names.iter().map(|name| format!("resource_{}", name)));
+    header.extend(
+        [
+            "attacks",
+            "max_spawns_in_window",
+            "spawn_gap_min",
+            "spawn_gap_mean",
+        ]
+        .iter()
+        .map(|name| name.to_string()),
+    );
+    println!("{}", header.join(","));
+
+    for stats in rounds {
+        let mut row = vec![
+            stats.round.to_string(),
+            stats.difficulty.to_string(),
+            stats.is_boss_round.to_string(),
+            stats.spending_points.to_string(),
+            format!("{:.2}", stats.duration),
+        ];
+        row.extend(
+            enemy_names
+                .iter()
+                .map(|name| stats.enemy_counts[name].to_string()),
+        );
+        row.extend(
+            resource_names
+                .iter()
+                .map(|name| stats.resource_counts[name].to_string()),
+        );
+        row.push(stats.attack_count.to_string());
+        row.push(stats.max_spawns_in_window.to_string());
+        row.push(format!("{:.2}", stats.spawn_gap_min));
+        row.push(format!("{:.2}", stats.spawn_gap_mean));
+        println!("{}", row.join(","));
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!(
+            "Usage: director_simulator [--seed N] [--duration SECONDS] \
+             [--boss-duration SECONDS] [--config FILEPATH] [--format csv|json]"
+        );
+        std::process::exit(1);
+    });
+
+    let rounds = simulate(&options).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+
+    match options.format {
+        OutputFormat::Csv => print_csv(&rounds),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&rounds).expect("Could not serialize round stats")
+        ),
+    }
+}
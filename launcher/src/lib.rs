@@ -0,0 +1,14 @@
+// Game code shared by the launcher and its command line tools. The binaries only contain what is
+// specific to them.
+
+pub mod main_launcher_info;
+
+pub mod broadphase;
+pub mod console;
+pub mod director;
+pub mod physics;
+pub mod profile;
+pub mod replay;
+pub mod shipselect;
+pub mod skilltree;
+pub mod stage;
@@ -5,15 +5,13 @@ use ct_lib::math::*;
 use ct_lib::random::*;
 use ct_platform;
 
-use console::SceneConsole;
-use skilltree::SceneSkilltree;
-use stage::SceneStage;
-
-mod main_launcher_info;
-
-mod console;
-mod skilltree;
-mod stage;
+use launcher::console::SceneConsole;
+use launcher::main_launcher_info;
+use launcher::profile::{self, PlayerProfile};
+use launcher::replay::Replay;
+use launcher::shipselect::SceneShipSelect;
+use launcher::skilltree::SceneSkilltree;
+use launcher::stage::SceneStage;
 
 const CANVAS_WIDTH: f32 = 480.0;
 const CANVAS_HEIGHT: f32 = 270.0;
@@ -39,10 +37,42 @@ pub struct GameState {
 
     scene_current: String,
 
+    profile: PlayerProfile,
+
     scene_debug: SceneDebug,
     scene_stage: SceneStage,
     scene_console: SceneConsole,
     scene_skilltree: SceneSkilltree,
+    scene_shipselect: SceneShipSelect,
+}
+
+impl GameState {
+    /// Leaving a scene commits its progress to the profile
+    fn commit_scene_current(&mut self) {
+        match self.scene_current.as_str() {
+            "stage" if self.scene_stage.is_replay_playback() => {
+                // NOTE: Watching a replay must not earn anything
+            }
+            "stage" => {
+                if let Some(replay) = self.scene_stage.get_replay_recorded() {
+                    let filepath = profile::get_replay_filepath();
+                    if let Err(error) = replay.save_to_file(&filepath) {
+                        log::error!("Could not save replay '{}': {}", filepath.display(), error);
+                    }
+                }
+                self.profile.commit_stage_run(
+                    self.scene_stage.get_skillpoints_earned(),
+                    self.scene_stage.get_score(),
+                );
+                self.profile.save();
+            }
+            "skilltree" => {
+                self.scene_skilltree.write_to_profile(&mut self.profile);
+                self.profile.save();
+            }
+            _ => {}
+        }
+    }
 }
 
 impl GameStateInterface for GameState {
@@ -96,9 +126,20 @@ impl GameStateInterface for GameState {
         };
 
         let scene_debug = SceneDebug::new(draw, audio, assets, input, "Grand9K_Pixel_bordered");
-        let scene_stage = SceneStage::new(draw, audio, assets, input, &mut globals);
+        let profile = PlayerProfile::load_or_default();
+        let scene_skilltree = SceneSkilltree::new(&profile);
+        let scene_shipselect = SceneShipSelect::new();
+        let scene_stage = SceneStage::new(
+            draw,
+            audio,
+            assets,
+            input,
+            &mut globals,
+            scene_shipselect.get_ship_type(),
+            &scene_skilltree.get_skills_owned(),
+            &profile.settings,
+        );
         let scene_console = SceneConsole::new();
-        let scene_skilltree = SceneSkilltree::new();
 
         GameState {
             globals,
@@ -107,10 +148,13 @@ impl GameStateInterface for GameState {
 
             scene_current: "stage".to_string(),
 
+            profile,
+
             scene_debug,
             scene_stage,
             scene_console,
             scene_skilltree,
+            scene_shipselect,
         }
     }
 
@@ -122,6 +166,8 @@ impl GameStateInterface for GameState {
         input: &GameInput,
     ) {
         if input.keyboard.recently_pressed(Scancode::F5) {
+            // NOTE: The new state loads the profile from disk so we need to save our progress first
+            self.commit_scene_current();
             *self = GameState::new(draw, audio, assets, input);
         }
 
@@ -137,24 +183,30 @@ impl GameStateInterface for GameState {
 
         // DEBUG GAMESPEED MANIPULATION
         //
+        // NOTE: Debug keys are disabled while the user types into a terminal
+        let debug_keys_enabled = match self.scene_current.as_str() {
+            "console" => false,
+            "stage" => !self.scene_stage.is_debug_terminal_open(),
+            _ => true,
+        };
         if !is_effectively_zero(self.debug_deltatime_factor - 1.0) {
             draw.debug_log(format!("Timefactor: {:.1}", self.debug_deltatime_factor));
         }
-        if input.keyboard.recently_pressed(Scancode::KpPlus) {
+        if debug_keys_enabled && input.keyboard.recently_pressed(Scancode::KpPlus) {
             self.debug_deltatime_factor += 0.1;
         }
-        if input.keyboard.recently_pressed(Scancode::KpMinus) {
+        if debug_keys_enabled && input.keyboard.recently_pressed(Scancode::KpMinus) {
             self.debug_deltatime_factor -= 0.1;
             if self.debug_deltatime_factor < 0.1 {
                 self.debug_deltatime_factor = 0.1;
             }
         }
-        if input.keyboard.recently_pressed(Scancode::Space) {
+        if debug_keys_enabled && input.keyboard.recently_pressed(Scancode::Space) {
             self.globals.is_paused = !self.globals.is_paused;
         }
         let mut deltatime = input.target_deltatime * self.debug_deltatime_factor;
         if self.globals.is_paused {
-            if input.keyboard.recently_pressed_or_repeated(Scancode::N) {
+            if debug_keys_enabled && input.keyboard.recently_pressed_or_repeated(Scancode::N) {
                 deltatime = input.target_deltatime * self.debug_deltatime_factor;
             } else {
                 deltatime = 0.0;
@@ -195,6 +247,14 @@ impl GameStateInterface for GameState {
                 &mut self.globals,
                 &mut game_events,
             ),
+            "shipselect" => self.scene_shipselect.update_and_draw(
+                draw,
+                audio,
+                assets,
+                input,
+                &mut self.globals,
+                &mut game_events,
+            ),
             "debug" => self.scene_debug.update_and_draw(
                 draw,
                 audio,
@@ -240,23 +300,63 @@ impl GameStateInterface for GameState {
         );
         */
 
+        // Purchases in the skilltree must survive a crash or a closed window
+        if self.scene_current == "skilltree" && self.scene_skilltree.has_uncommitted_changes() {
+            self.scene_skilltree.write_to_profile(&mut self.profile);
+            self.profile.save();
+        }
+
         let deltatime = self.globals.deltatime;
         self.globals.camera.update(deltatime);
         draw.set_shaderparams_simple(Color::white(), self.globals.camera.proj_view_matrix());
 
         for event in game_events {
             match event {
-                GameEvent::SwitchToScene { scene_name } => {
+                GameEvent::SwitchToScene { mut scene_name } => {
+                    self.commit_scene_current();
+
                     match scene_name.as_str() {
                         "stage" => {
-                            self.scene_stage =
-                                SceneStage::new(draw, audio, assets, input, &mut self.globals);
+                            self.scene_stage = SceneStage::new(
+                                draw,
+                                audio,
+                                assets,
+                                input,
+                                &mut self.globals,
+                                self.scene_shipselect.get_ship_type(),
+                                &self.scene_skilltree.get_skills_owned(),
+                                &self.profile.settings,
+                            );
+                        }
+                        "replay" => {
+                            // NOTE: Replays are played back by the stage scene
+                            match Replay::load_from_file(&profile::get_replay_filepath()) {
+                                Ok(replay) => {
+                                    self.scene_stage = SceneStage::new_playback(
+                                        draw,
+                                        audio,
+                                        assets,
+                                        input,
+                                        &mut self.globals,
+                                        replay,
+                                        &self.profile.settings,
+                                    );
+                                    scene_name = "stage".to_string();
+                                }
+                                Err(error) => {
+                                    log::error!("Could not start replay: {}", error);
+                                    scene_name = "console".to_string();
+                                }
+                            }
                         }
                         "console" => {
-                            self.scene_console = SceneConsole::new();
+                            // NOTE: The console keeps its scrollback and history between visits
                         }
                         "skilltree" => {
-                            self.scene_skilltree = SceneSkilltree::new();
+                            self.scene_skilltree = SceneSkilltree::new(&self.profile);
+                        }
+                        "shipselect" => {
+                            // NOTE: The ship selection is kept so that it is preselected next time
                         }
                         _ => panic!("Unknown scene '{}'", scene_name),
                     };
@@ -1,320 +1,4 @@
-// THIS FILE IS CURRENTLY UNUSED UNTIL WE FIGURE OUT WHAT TO DO WITH PHYSICS
-
-const COLLISION_LAYER_CIRCLES: u64 = 1 << 0;
-const COLLISION_LAYER_RECTANGLES: u64 = 1 << 1;
-
-struct Test {
-    bodies: Vec<Body>,
-    contact_points: Vec<(usize, Vec2, Vec2)>,
-}
-
-impl Test {
-    fn test(&mut self) {
-        //------------------------------------------------------------------------------------------
-        // UPDATE PHYSICS
-
-        if self.bodies.len() == 0 {
-            let pos = globals.random.vec2_in_rect(Rect::from_width_height(
-                globals.canvas_width,
-                globals.canvas_height,
-            ));
-            let shape = Shape::Disk { radius: 10.0 };
-            let bouncyness = 1.0;
-            let mass = 2.0;
-            let inertia = 2.0;
-            self.bodies.push(Body::new(
-                COLLISION_LAYER_CIRCLES,
-                COLLISION_LAYER_CIRCLES | COLLISION_LAYER_RECTANGLES,
-                pos,
-                0.0,
-                shape,
-                mass,
-                inertia,
-                bouncyness,
-            ));
-        }
-        self.bodies[0].pos = globals.cursors.mouse_coords.pos_canvas;
-        self.bodies[0].vel = if deltatime != 0.0 {
-            globals.cursors.mouse_coords.delta_canvas / deltatime
-        } else {
-            Vec2::zero()
-        };
-        let cursor_canvas = globals.cursors.mouse_coords.pos_canvas;
-        let cursor_world = globals.cursors.mouse_coords.pos_world;
-        let cursor_screen = globals.cursors.mouse_coords.pos_screen;
-        draw.debug_log(dformat!(cursor_canvas));
-        draw.debug_log(dformat!(cursor_world));
-        draw.debug_log(dformat!(cursor_screen));
-
-        // Add static objects
-        if self.bodies.len() == 1 {
-            // static circles
-            for _ in 0..5 {
-                let pos = globals.random.vec2_in_rect(Rect::from_width_height(
-                    globals.canvas_width,
-                    globals.canvas_height,
-                ));
-                let shape = Shape::Disk {
-                    radius: globals.random.f32_in_range_closed(5.0, 15.0),
-                };
-                let bouncyness = globals.random.f32_in_range_closed(0.5, 1.0);
-                let mass = 0.0;
-                let inertia = 0.0;
-                self.bodies.push(Body::new(
-                    COLLISION_LAYER_CIRCLES,
-                    COLLISION_LAYER_CIRCLES | COLLISION_LAYER_RECTANGLES,
-                    pos,
-                    0.0,
-                    shape,
-                    mass,
-                    inertia,
-                    bouncyness,
-                ));
-            }
-            // static boxes
-            for _ in 0..5 {
-                let pos = globals.random.vec2_in_rect(Rect::from_width_height(
-                    globals.canvas_width,
-                    globals.canvas_height,
-                ));
-                let dim = Vec2::new(
-                    globals.random.f32_in_range_closed(10.0, 20.0),
-                    globals.random.f32_in_range_closed(10.0, 20.0),
-                );
-                let angle = globals.random.f32_in_range_closed(0.0, 360.0);
-                let shape = Shape::Box {
-                    width: dim.x,
-                    height: dim.y,
-                };
-                let bouncyness = globals.random.f32_in_range_closed(0.5, 1.0);
-                let mass = 0.0;
-                let inertia = 0.0;
-                self.bodies.push(Body::new(
-                    COLLISION_LAYER_RECTANGLES,
-                    COLLISION_LAYER_CIRCLES,
-                    pos,
-                    angle,
-                    shape,
-                    mass,
-                    inertia,
-                    bouncyness,
-                ));
-            }
-        }
-
-        // Add dynamic objects
-        if self.bodies.len() < 30 {
-            // moving circles
-            {
-                let canvas_rect =
-                    Rect::from_width_height(globals.canvas_width, globals.canvas_height);
-                let pos = loop {
-                    let point = globals
-                        .random
-                        .vec2_in_rect(canvas_rect.extended_uniformly_by(100.0));
-                    if !canvas_rect.contains_point(point) {
-                        break point;
-                    }
-                };
-                let dir = ((canvas_rect.center()
-                    + (globals.canvas_height / 2.0) * globals.random.vec2_in_unit_disk())
-                    - pos)
-                    .normalized();
-                let vel = dir * globals.random.f32_in_range_closed(100.0, 200.0);
-
-                let shape = Shape::Disk {
-                    radius: globals.random.f32_in_range_closed(1.0, 20.0),
-                };
-                let bouncyness = globals.random.f32_in_range_closed(0.5, 1.0);
-                let mass = globals.random.f32_in_range_closed(1.0, 2.0);
-                let inertia = mass;
-                let mut body = Body::new(
-                    COLLISION_LAYER_CIRCLES,
-                    COLLISION_LAYER_CIRCLES | COLLISION_LAYER_RECTANGLES,
-                    pos,
-                    0.0,
-                    shape,
-                    mass,
-                    inertia,
-                    bouncyness,
-                );
-                body.vel = vel;
-                self.bodies.push(body);
-            }
-            // moving boxes
-            {
-                let canvas_rect =
-                    Rect::from_width_height(globals.canvas_width, globals.canvas_height);
-                let pos = loop {
-                    let point = globals
-                        .random
-                        .vec2_in_rect(canvas_rect.extended_uniformly_by(100.0));
-                    if !canvas_rect.contains_point(point) {
-                        break point;
-                    }
-                };
-                let dim = Vec2::new(
-                    globals.random.f32_in_range_closed(10.0, 20.0),
-                    globals.random.f32_in_range_closed(10.0, 20.0),
-                );
-                let angle = globals.random.f32_in_range_closed(0.0, 360.0);
-                let shape = Shape::Box {
-                    width: dim.x,
-                    height: dim.y,
-                };
-
-                let dir = ((canvas_rect.center()
-                    + (globals.canvas_height / 2.0) * globals.random.vec2_in_unit_disk())
-                    - pos)
-                    .normalized();
-                let vel = dir * globals.random.f32_in_range_closed(100.0, 200.0);
-
-                let bouncyness = globals.random.f32_in_range_closed(0.5, 1.0);
-                let mass = globals.random.f32_in_range_closed(1.0, 2.0);
-                let inertia = mass;
-                let mut body = Body::new(
-                    COLLISION_LAYER_RECTANGLES,
-                    COLLISION_LAYER_CIRCLES,
-                    pos,
-                    angle,
-                    shape,
-                    mass,
-                    inertia,
-                    bouncyness,
-                );
-                body.vel = vel;
-                self.bodies.push(body);
-            }
-        }
-
-        // Broadphase: Find collisions
-        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
-        for index_a in 0..self.bodies.len() {
-            for index_b in 0..self.bodies.len() {
-                if index_a == index_b {
-                    continue;
-                }
-
-                let body_a = &self.bodies[index_a];
-                let body_b = &self.bodies[index_b];
-
-                if body_a.layers & body_b.layers_affects == 0
-                    && body_b.layers & body_a.layers_affects == 0
-                {
-                    continue;
-                }
-
-                let a_aabb = body_a.compute_aabb();
-                let b_aabb = body_b.compute_aabb();
-
-                if intersects_rect_rect(a_aabb, b_aabb) {
-                    if index_a < index_b {
-                        pairs.insert((index_a, index_b));
-                    } else {
-                        pairs.insert((index_b, index_a));
-                    }
-                }
-            }
-        }
-
-        // Resolve collisions
-        for (index_a, index_b) in pairs {
-            let mut body_a = self.bodies[index_a];
-            let mut body_b = self.bodies[index_b];
-            if let Some(contact) = Body::collide(&body_a, &body_b) {
-                collision_resolve(&mut body_a, &mut body_b, &contact);
-                // collision_positional_correction(&mut a.obj, &mut b.obj, &manifold);
-
-                for _ in 0..5 {
-                    self.contact_points.push((0, contact.point, contact.normal));
-                }
-
-                self.bodies[index_a] = body_a;
-                self.bodies[index_b] = body_b;
-            }
-        }
-
-        for (drawcount, point, normal) in self.contact_points.iter_mut() {
-            draw.circle_filled(*point, 2.0, DEPTH_DEBUG, Color::magenta(), ADDITIVITY_NONE);
-            draw.debug_arrow(*point, 5.0 * *normal, Color::yellow(), ADDITIVITY_NONE);
-            *drawcount += 1;
-        }
-        self.contact_points
-            .retain(|(drawcount, _, _)| *drawcount <= 20);
-
-        // Update forces
-        for body in self.bodies.iter_mut() {
-            body.force = Vec2::zero();
-            let gravity = body.mass * Vec2::filled_y(50.0);
-            // body.force += gravity;
-
-            body.vel += body.force * body.mass_inverse * deltatime;
-        }
-
-        // Update objects
-        for body in self.bodies.iter_mut() {
-            body.pos += body.vel * deltatime;
-            body.angle += body.angle_vel * deltatime;
-        }
-
-        // Draw objects
-        for body in &self.bodies {
-            let color = if body.mass == 0.0 {
-                Color::green()
-            } else {
-                Color::white()
-            };
-            draw.pixel(body.pos, DEPTH_PROJECTILE, color, ADDITIVITY_NONE);
-            match body.shape {
-                Shape::Disk { radius } => {
-                    draw.circle_bresenham(
-                        body.pos,
-                        radius,
-                        DEPTH_PROJECTILE,
-                        color,
-                        ADDITIVITY_NONE,
-                    );
-                    draw.line_bresenham(
-                        body.pos,
-                        body.pos + radius * Vec2::from_angle_flipped_y(deg_to_rad(body.angle)),
-                        DEPTH_PROJECTILE,
-                        color,
-                        ADDITIVITY_NONE,
-                    );
-                    draw.pixel(body.pos, DEPTH_PROJECTILE, Color::red(), ADDITIVITY_NONE);
-                }
-                Shape::Box { width, height } => {
-                    draw.rect_transformed(
-                        Vec2::new(width, height),
-                        Vec2::new(width, height) / 2.0,
-                        body.pos,
-                        Vec2::ones(),
-                        Vec2::from_angle_flipped_y(deg_to_rad(body.angle)),
-                        DEPTH_PROJECTILE,
-                        color,
-                        ADDITIVITY_NONE,
-                    );
-                    draw.line_bresenham(
-                        body.pos,
-                        body.pos + 0.5 * width * Vec2::from_angle_flipped_y(deg_to_rad(body.angle)),
-                        DEPTH_PROJECTILE,
-                        Color::red(),
-                        ADDITIVITY_NONE,
-                    );
-                    draw.pixel(body.pos, DEPTH_PROJECTILE, Color::red(), ADDITIVITY_NONE);
-                }
-            }
-        }
-
-        // Remove objects that are out of bounds
-        let canvas_rect = Rect::from_width_height(globals.canvas_width, globals.canvas_height);
-        self.bodies.retain(|body| {
-            canvas_rect
-                .extended_uniformly_by(100.0)
-                .contains_point(body.pos)
-        });
-    }
-}
+use ct_lib::math::*;
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Physics
@@ -322,8 +6,8 @@ impl Test {
 // Based on
 // https://gamedevelopment.tutsplus.com/tutorials/how-to-create-a-custom-2d-physics-engine-the-basics-and-impulse-resolution--gamedev-6331
 
-#[derive(Clone, Copy)]
-enum Shape {
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
     Box { width: f32, height: f32 },
     Disk { radius: f32 },
 }
@@ -346,33 +30,31 @@ impl Shape {
         }
     }
 
-    fn compute_mass(&self, density: f32) -> MassData {
+    pub fn compute_mass(&self, density: f32) -> MassData {
         let mass = density * self.compute_volume();
-        let mass_inverse = if mass == 0.0 { 0.0 } else { 1.0 / mass };
-
         let inertia = self.compute_inertia(mass);
-        let inertia_inverse = if inertia == 0.0 { 0.0 } else { 1.0 / inertia };
+        MassData { mass, inertia }
+    }
 
-        MassData {
-            mass,
-            mass_inverse,
-            inertia,
-            inertia_inverse,
+    /// Radius of the smallest circle around our center that contains the whole shape regardless
+    /// of its rotation
+    pub fn compute_bounding_radius(&self) -> f32 {
+        match self {
+            Shape::Disk { radius } => *radius,
+            Shape::Box { width, height } => 0.5 * f32::sqrt(squared(*width) + squared(*height)),
         }
     }
 }
 
-#[derive(Clone, Copy)]
-struct MassData {
+#[derive(Debug, Clone, Copy)]
+pub struct MassData {
     pub mass: f32,
-    pub mass_inverse: f32,
-
     pub inertia: f32,
-    pub inertia_inverse: f32,
 }
 
-#[derive(Clone, Copy)]
-struct Body {
+/// A body with a mass of zero is static and does not get moved by collisions
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
     pub layers: u64,
     pub layers_affects: u64,
 
@@ -386,24 +68,22 @@ struct Body {
 
     pub pos: Vec2,
     pub vel: Vec2,
-    pub force: Vec2,
 
     pub angle: f32,
     pub angle_vel: f32,
-    pub angle_force: f32,
 }
 
 impl Body {
-    fn new(
+    pub fn new(
         layers: u64,
         layers_affects: u64,
         pos: Vec2,
         angle: f32,
         shape: Shape,
-        mass: f32,
-        inertia: f32,
+        mass_data: MassData,
         bouncyness: f32,
     ) -> Body {
+        let MassData { mass, inertia } = mass_data;
         let mass_inverse = if mass == 0.0 { 0.0 } else { 1.0 / mass };
         let inertia_inverse = if inertia == 0.0 { 0.0 } else { 1.0 / inertia };
 
@@ -421,27 +101,14 @@ impl Body {
 
             pos,
             vel: Vec2::zero(),
-            force: Vec2::zero(),
 
             angle,
             angle_vel: 0.0,
-            angle_force: 0.0,
         }
     }
 
-    fn compute_aabb(&self) -> Rect {
-        match self.shape {
-            Shape::Disk { radius } => {
-                Rect::from_square(2.0 * radius).centered_in_position(self.pos)
-            }
-            Shape::Box { width, height } => {
-                Rect::from_width_height(2.0 * width, 2.0 * height).centered_in_position(self.pos)
-                //TODO: angle
-            }
-        }
-    }
-
-    fn collide(a: &Body, b: &Body) -> Option<Contact> {
+    /// Returns the contact with a normal pointing from `a` to `b` if the bodies overlap
+    pub fn collide(a: &Body, b: &Body) -> Option<Contact> {
         match a.shape {
             Shape::Disk { radius } => {
                 let a_circle = Circle {
@@ -488,8 +155,8 @@ impl Body {
     }
 }
 
-#[derive(Clone, Copy)]
-struct Contact {
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
     pub penetration_depth: f32,
     pub normal: Vec2,
     pub point: Vec2,
@@ -522,6 +189,49 @@ fn collide_circle_circle(a: Circle, b: Circle) -> Option<Contact> {
     }
 }
 
+/// Moves both circles along their movement vectors and returns the earliest time of impact in
+/// [0, 1] where 0 is the start and 1 is the end of the movement. Circles that already overlap at
+/// the start have a time of impact of 0.
+pub fn sweep_circle_circle(
+    a: Circle,
+    a_movement: Vec2,
+    b: Circle,
+    b_movement: Vec2,
+) -> Option<f32> {
+    // We look at it from the perspective of `a` so that only `b` moves
+    let vec_a_to_b = b.center - a.center;
+    let movement = b_movement - a_movement;
+    let radius_sum = a.radius + b.radius;
+
+    let distance_squared_minus_radius_sum_squared =
+        vec_a_to_b.magnitude_squared() - squared(radius_sum);
+    if distance_squared_minus_radius_sum_squared <= 0.0 {
+        return Some(0.0);
+    }
+
+    // Solve |vec_a_to_b + t * movement|^2 = radius_sum^2 for t
+    let quadratic_a = movement.magnitude_squared();
+    let quadratic_b = 2.0 * Vec2::dot(vec_a_to_b, movement);
+    let quadratic_c = distance_squared_minus_radius_sum_squared;
+    if quadratic_a == 0.0 || quadratic_b >= 0.0 {
+        // The circles do not move towards each other
+        return None;
+    }
+
+    let discriminant = squared(quadratic_b) - 4.0 * quadratic_a * quadratic_c;
+    if discriminant < 0.0 {
+        // The circles pass each other
+        return None;
+    }
+
+    let time_of_impact = (-quadratic_b - f32::sqrt(discriminant)) / (2.0 * quadratic_a);
+    if time_of_impact <= 1.0 {
+        Some(time_of_impact)
+    } else {
+        None
+    }
+}
+
 fn collide_box_box(a: Rect, a_angle: f32, b: Rect, b_angle: f32) -> Option<Contact> {
     assert!(
         a_angle == 0.0 && b_angle == 0.0,
@@ -560,7 +270,7 @@ fn collide_box_box(a: Rect, a_angle: f32, b: Rect, b_angle: f32) -> Option<Conta
                 // A is below of B
                 Vec2::new(0.0, -1.0)
             };
-            let penetration_depth = overlap_x;
+            let penetration_depth = overlap_y;
             return Some(Contact {
                 penetration_depth,
                 normal,
@@ -640,7 +350,7 @@ fn collide_rect_circle(rect: Rect, circle: Circle) -> Option<Contact> {
     if circle_center_inside_rect {
         // We need to flip the normal so that it points outside the rect
         Some(Contact {
-            penetration_depth: circle.radius - distance,
+            penetration_depth: circle.radius + distance,
             normal: -normal / distance,
             point: circle.center,
         })
@@ -653,7 +363,8 @@ fn collide_rect_circle(rect: Rect, circle: Circle) -> Option<Contact> {
     }
 }
 
-fn collision_resolve(a: &mut Body, b: &mut Body, collision: &Contact) {
+/// Applies an impulse to both bodies so that they stop moving into each other
+pub fn collision_resolve(a: &mut Body, b: &mut Body, collision: &Contact) {
     if a.mass == 0.0 && b.mass == 0.0 {
         // Nothing to do
         return;
@@ -677,11 +388,12 @@ fn collision_resolve(a: &mut Body, b: &mut Body, collision: &Contact) {
 
     let a_r = collision.point - a.pos;
     let b_r = collision.point - b.pos;
-    a.angle_vel -= rad_to_deg(Vec2::cross_z(a_r, collision.normal) * a.inertia_inverse);
-    b.angle_vel -= rad_to_deg(Vec2::cross_z(b_r, collision.normal) * b.inertia_inverse);
+    a.angle_vel -= rad_to_deg(Vec2::cross_z(a_r, impulse_vector) * a.inertia_inverse);
+    b.angle_vel += rad_to_deg(Vec2::cross_z(b_r, impulse_vector) * b.inertia_inverse);
 }
 
-fn collision_positional_correction(a: &mut Body, b: &mut Body, collision: &Contact) {
+/// Pushes overlapping bodies apart so that they do not sink into each other over time
+pub fn collision_positional_correction(a: &mut Body, b: &mut Body, collision: &Contact) {
     if a.mass == 0.0 && b.mass == 0.0 {
         // Nothing to do
         return;
@@ -710,3 +422,270 @@ fn collision_positional_correction(a: &mut Body, b: &mut Body, collision: &Conta
 // Open questions:
 // - How do we get the contact points of the collision in case we are doing
 //   rectange-rectangle collisions?
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.001;
+
+    fn assert_approx_eq(a: f32, b: f32) {
+        assert!(f32::abs(a - b) < EPSILON, "{} != {}", a, b);
+    }
+
+    fn assert_approx_eq_vec(a: Vec2, b: Vec2) {
+        assert!(
+            f32::abs(a.x - b.x) < EPSILON && f32::abs(a.y - b.y) < EPSILON,
+            "({}, {}) != ({}, {})",
+            a.x,
+            a.y,
+            b.x,
+            b.y
+        );
+    }
+
+    fn new_disk(pos: Vec2, vel: Vec2, radius: f32, mass: f32, bouncyness: f32) -> Body {
+        let mut body = Body::new(
+            1,
+            1,
+            pos,
+            0.0,
+            Shape::Disk { radius },
+            MassData {
+                mass,
+                inertia: mass,
+            },
+            bouncyness,
+        );
+        body.vel = vel;
+        body
+    }
+
+    #[test]
+    fn collide_circle_circle_separated() {
+        let a = Circle {
+            center: Vec2::new(0.0, 0.0),
+            radius: 2.0,
+        };
+        let b = Circle {
+            center: Vec2::new(5.0, 0.0),
+            radius: 2.0,
+        };
+        assert!(collide_circle_circle(a, b).is_none());
+    }
+
+    #[test]
+    fn collide_circle_circle_overlapping() {
+        let a = Circle {
+            center: Vec2::new(0.0, 0.0),
+            radius: 2.0,
+        };
+        let b = Circle {
+            center: Vec2::new(3.0, 0.0),
+            radius: 2.0,
+        };
+        let contact = collide_circle_circle(a, b).unwrap();
+        assert_approx_eq(contact.penetration_depth, 1.0);
+        assert_approx_eq_vec(contact.normal, Vec2::new(1.0, 0.0));
+        assert_approx_eq_vec(contact.point, Vec2::new(1.5, 0.0));
+
+        // Swapping the circles flips the normal
+        let contact = collide_circle_circle(b, a).unwrap();
+        assert_approx_eq(contact.penetration_depth, 1.0);
+        assert_approx_eq_vec(contact.normal, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn collide_circle_circle_same_position() {
+        let a = Circle {
+            center: Vec2::new(4.0, 4.0),
+            radius: 3.0,
+        };
+        let b = Circle {
+            center: Vec2::new(4.0, 4.0),
+            radius: 1.0,
+        };
+        let contact = collide_circle_circle(a, b).unwrap();
+        assert_approx_eq(contact.penetration_depth, 3.0);
+        assert_approx_eq_vec(contact.normal, Vec2::new(1.0, 0.0));
+        assert_approx_eq_vec(contact.point, Vec2::new(4.0, 4.0));
+    }
+
+    #[test]
+    fn collide_box_circle_separated() {
+        let rect = Rect::from_width_height(10.0, 10.0).centered_in_position(Vec2::zero());
+        let circle = Circle {
+            center: Vec2::new(9.0, 0.0),
+            radius: 3.0,
+        };
+        assert!(collide_box_circle(rect, 0.0, circle).is_none());
+    }
+
+    #[test]
+    fn collide_box_circle_touching_edge() {
+        let rect = Rect::from_width_height(10.0, 10.0).centered_in_position(Vec2::zero());
+        let circle = Circle {
+            center: Vec2::new(7.0, 0.0),
+            radius: 3.0,
+        };
+        let contact = collide_box_circle(rect, 0.0, circle).unwrap();
+        assert_approx_eq(contact.penetration_depth, 1.0);
+        assert_approx_eq_vec(contact.normal, Vec2::new(1.0, 0.0));
+        assert_approx_eq_vec(contact.point, Vec2::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn collide_box_circle_center_inside() {
+        let rect = Rect::from_width_height(10.0, 10.0).centered_in_position(Vec2::zero());
+        let circle = Circle {
+            center: Vec2::new(0.0, 3.0),
+            radius: 1.0,
+        };
+        let contact = collide_box_circle(rect, 0.0, circle).unwrap();
+        // The circle needs to travel to the nearest edge and then its full radius to get out
+        assert_approx_eq(contact.penetration_depth, 3.0);
+        assert_approx_eq_vec(contact.normal, Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn collide_box_circle_rotated() {
+        // Rotated by 45 degrees the corners of the box lie on the axes
+        let rect = Rect::from_width_height(10.0, 10.0).centered_in_position(Vec2::zero());
+        let corner_distance = 5.0 * f32::sqrt(2.0);
+        let circle = Circle {
+            center: Vec2::new(corner_distance + 1.0, 0.0),
+            radius: 2.0,
+        };
+        assert!(collide_box_circle(rect, 0.0, circle).is_none());
+
+        let contact = collide_box_circle(rect, 45.0, circle).unwrap();
+        assert_approx_eq(contact.penetration_depth, 1.0);
+        assert_approx_eq_vec(contact.normal, Vec2::new(1.0, 0.0));
+        assert_approx_eq_vec(contact.point, Vec2::new(corner_distance, 0.0));
+    }
+
+    #[test]
+    fn collision_resolve_equal_masses_swap_velocities() {
+        let mut a = new_disk(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), 2.0, 1.0, 1.0);
+        let mut b = new_disk(Vec2::new(3.0, 0.0), Vec2::new(-1.0, 0.0), 2.0, 1.0, 1.0);
+        let contact = Body::collide(&a, &b).unwrap();
+        collision_resolve(&mut a, &mut b, &contact);
+
+        assert_approx_eq_vec(a.vel, Vec2::new(-1.0, 0.0));
+        assert_approx_eq_vec(b.vel, Vec2::new(1.0, 0.0));
+        // A head-on hit goes through both centers and must not cause any spin
+        assert_approx_eq(a.angle_vel, 0.0);
+        assert_approx_eq(b.angle_vel, 0.0);
+    }
+
+    #[test]
+    fn collision_resolve_static_body_does_not_move() {
+        let mut a = new_disk(Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0), 2.0, 1.0, 0.5);
+        let mut b = new_disk(Vec2::new(3.0, 0.0), Vec2::zero(), 2.0, 0.0, 1.0);
+        let contact = Body::collide(&a, &b).unwrap();
+        collision_resolve(&mut a, &mut b, &contact);
+
+        // The smaller bouncyness is used
+        assert_approx_eq_vec(a.vel, Vec2::new(-1.0, 0.0));
+        assert_approx_eq_vec(b.vel, Vec2::zero());
+    }
+
+    #[test]
+    fn collision_resolve_ignores_separating_bodies() {
+        let mut a = new_disk(Vec2::new(0.0, 0.0), Vec2::new(-1.0, 0.0), 2.0, 1.0, 1.0);
+        let mut b = new_disk(Vec2::new(3.0, 0.0), Vec2::new(1.0, 0.0), 2.0, 1.0, 1.0);
+        let contact = Body::collide(&a, &b).unwrap();
+        collision_resolve(&mut a, &mut b, &contact);
+
+        assert_approx_eq_vec(a.vel, Vec2::new(-1.0, 0.0));
+        assert_approx_eq_vec(b.vel, Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn collision_resolve_conserves_momentum() {
+        let mut a = new_disk(Vec2::new(0.0, 0.0), Vec2::new(3.0, 1.0), 2.0, 2.0, 0.7);
+        let mut b = new_disk(Vec2::new(3.0, 1.0), Vec2::new(-1.0, 0.0), 2.0, 5.0, 0.7);
+        let momentum_before = a.mass * a.vel + b.mass * b.vel;
+        let contact = Body::collide(&a, &b).unwrap();
+        collision_resolve(&mut a, &mut b, &contact);
+        let momentum_after = a.mass * a.vel + b.mass * b.vel;
+
+        assert_approx_eq_vec(momentum_before, momentum_after);
+        // Afterwards the bodies must not move into each other anymore
+        assert!(Vec2::dot(b.vel - a.vel, contact.normal) >= 0.0);
+    }
+
+    #[test]
+    fn sweep_circle_circle_finds_tunneling_hit() {
+        // A small fast bullet that jumps over the whole rock within one frame
+        let bullet = Circle {
+            center: Vec2::new(0.0, 0.0),
+            radius: 1.0,
+        };
+        let rock = Circle {
+            center: Vec2::new(50.0, 0.0),
+            radius: 8.0,
+        };
+        let movement = Vec2::new(100.0, 0.0);
+        assert!(collide_circle_circle(
+            Circle {
+                center: bullet.center + movement,
+                radius: bullet.radius
+            },
+            rock
+        )
+        .is_none());
+
+        let time_of_impact = sweep_circle_circle(bullet, movement, rock, Vec2::zero()).unwrap();
+        assert_approx_eq(time_of_impact, 0.41);
+    }
+
+    #[test]
+    fn sweep_circle_circle_both_moving() {
+        let a = Circle {
+            center: Vec2::new(0.0, 0.0),
+            radius: 1.0,
+        };
+        let b = Circle {
+            center: Vec2::new(10.0, 0.0),
+            radius: 1.0,
+        };
+        let time_of_impact =
+            sweep_circle_circle(a, Vec2::new(4.0, 0.0), b, Vec2::new(-4.0, 0.0)).unwrap();
+        assert_approx_eq(time_of_impact, 1.0);
+    }
+
+    #[test]
+    fn sweep_circle_circle_misses() {
+        let a = Circle {
+            center: Vec2::new(0.0, 0.0),
+            radius: 1.0,
+        };
+        let b = Circle {
+            center: Vec2::new(50.0, 10.0),
+            radius: 8.0,
+        };
+        // Passes by
+        assert!(sweep_circle_circle(a, Vec2::new(100.0, 0.0), b, Vec2::zero()).is_none());
+        // Stops short
+        assert!(sweep_circle_circle(a, Vec2::new(0.0, 1.0), b, Vec2::zero()).is_none());
+        // Moves away
+        assert!(sweep_circle_circle(a, Vec2::new(-100.0, 0.0), b, Vec2::zero()).is_none());
+    }
+
+    #[test]
+    fn sweep_circle_circle_overlapping_at_start() {
+        let a = Circle {
+            center: Vec2::new(0.0, 0.0),
+            radius: 2.0,
+        };
+        let b = Circle {
+            center: Vec2::new(3.0, 0.0),
+            radius: 2.0,
+        };
+        assert_eq!(
+            sweep_circle_circle(a, Vec2::new(-100.0, 0.0), b, Vec2::zero()),
+            Some(0.0)
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::main_launcher_info::{LAUNCHER_COMPANY_NAME, LAUNCHER_SAVE_FOLDER_NAME};
+use crate::skilltree::SkilltreeNodeId;
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+
+const PROFILE_FILENAME: &str = "profile.json";
+const REPLAY_FILENAME: &str = "last_run.replay";
+
+/// Must be incremented whenever the layout of `PlayerProfile` changes. Every increment needs a
+/// matching step in `migrate_profile`.
+const PROFILE_VERSION: u64 = 2;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Player profile
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    pub screenshake_enabled: bool,
+}
+
+impl Default for ProfileSettings {
+    fn default() -> ProfileSettings {
+        ProfileSettings {
+            screenshake_enabled: true,
+        }
+    }
+}
+
+/// Everything about the player that outlives a single stage run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub version: u64,
+
+    pub skillpoints: usize,
+    pub skilltree_nodes_owned: Vec<SkilltreeNodeId>,
+
+    pub highscore: usize,
+    pub settings: ProfileSettings,
+}
+
+impl Default for PlayerProfile {
+    fn default() -> PlayerProfile {
+        PlayerProfile::new()
+    }
+}
+
+impl PlayerProfile {
+    pub fn new() -> PlayerProfile {
+        PlayerProfile {
+            version: PROFILE_VERSION,
+
+            skillpoints: 0,
+            skilltree_nodes_owned: Vec::new(),
+
+            highscore: 0,
+            settings: ProfileSettings::default(),
+        }
+    }
+
+    /// Never fails. If there is no profile yet or it can't be read we start with a fresh one.
+    pub fn load_or_default() -> PlayerProfile {
+        PlayerProfile::load_from_file_or_default(&get_profile_filepath())
+    }
+
+    fn load_from_file_or_default(filepath: &PathBuf) -> PlayerProfile {
+        if !filepath.exists() {
+            log::info!(
+                "No profile found at '{}' - starting with a new one",
+                filepath.display()
+            );
+            return PlayerProfile::new();
+        }
+
+        match PlayerProfile::load_from_file(filepath) {
+            Ok(profile) => profile,
+            Err(error) => {
+                log::warn!(
+                    "Could not load profile '{}' - falling back to a new one: {}",
+                    filepath.display(),
+                    error
+                );
+
+                // Keep the broken file around so that it does not get overwritten on next save
+                let filepath_corrupt = filepath.with_extension("json.corrupt");
+                if let Err(error) = std::fs::rename(filepath, &filepath_corrupt) {
+                    log::warn!(
+                        "Could not move corrupt profile to '{}': {}",
+                        filepath_corrupt.display(),
+                        error
+                    );
+                }
+
+                PlayerProfile::new()
+            }
+        }
+    }
+
+    fn load_from_file(filepath: &PathBuf) -> Result<PlayerProfile, String> {
+        let content = std::fs::read_to_string(filepath).map_err(|error| error.to_string())?;
+        let value: serde_json::Value =
+            serde_json::from_str(&content).map_err(|error| error.to_string())?;
+        let value = migrate_profile(value)?;
+        serde_json::from_value(value).map_err(|error| error.to_string())
+    }
+
+    /// Writes into a temporary file first and then replaces the actual profile with it so that we
+    /// never end up with a half written profile
+    pub fn save(&self) {
+        let filepath = get_profile_filepath();
+        if let Err(error) = self.save_to_file(&filepath) {
+            log::error!("Could not save profile '{}': {}", filepath.display(), error);
+        }
+    }
+
+    fn save_to_file(&self, filepath: &PathBuf) -> Result<(), String> {
+        if let Some(parent_dir) = filepath.parent() {
+            std::fs::create_dir_all(parent_dir).map_err(|error| error.to_string())?;
+        }
+
+        let content = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        let filepath_temp = filepath.with_extension("json.tmp");
+        std::fs::write(&filepath_temp, content).map_err(|error| error.to_string())?;
+        std::fs::rename(&filepath_temp, filepath).map_err(|error| error.to_string())
+    }
+
+    pub fn commit_stage_run(&mut self, skillpoints_earned: usize, score: usize) {
+        self.skillpoints += skillpoints_earned;
+        self.highscore = usize::max(self.highscore, score);
+    }
+}
+
+fn get_profile_filepath() -> PathBuf {
+    get_save_dir().join(PROFILE_FILENAME)
+}
+
+/// The replay of the most recent stage run lives next to the profile
+pub fn get_replay_filepath() -> PathBuf {
+    get_save_dir().join(REPLAY_FILENAME)
+}
+
+/// The same folder that the platform layer uses for the rest of our save data
+fn get_save_dir() -> PathBuf {
+    match sdl2::filesystem::pref_path(LAUNCHER_COMPANY_NAME, LAUNCHER_SAVE_FOLDER_NAME) {
+        Ok(save_dir) => PathBuf::from(save_dir),
+        Err(error) => {
+            log::warn!(
+                "Could not determine save folder - falling back to working directory: {:?}",
+                error
+            );
+            PathBuf::from(".")
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Migration
+
+/// Upgrades a raw profile of any older version step by step to `PROFILE_VERSION`
+fn migrate_profile(mut value: serde_json::Value) -> Result<serde_json::Value, String> {
+    let mut version = value
+        .get("version")
+        .and_then(|version| version.as_u64())
+        // NOTE: The very first profiles did not store a version
+        .unwrap_or(1);
+
+    if version > PROFILE_VERSION {
+        return Err(format!(
+            "Profile version {} is newer than the supported version {}",
+            version, PROFILE_VERSION
+        ));
+    }
+
+    while version < PROFILE_VERSION {
+        let object = value
+            .as_object_mut()
+            .ok_or_else(|| "Profile is not a JSON object".to_string())?;
+        match version {
+            1 => {
+                // Version 2 added highscores and settings
+                object.insert("highscore".to_string(), serde_json::json!(0));
+                object.insert(
+                    "settings".to_string(),
+                    serde_json::to_value(ProfileSettings::default())
+                        .map_err(|error| error.to_string())?,
+                );
+            }
+            _ => unreachable!("Missing profile migration step for version {}", version),
+        }
+        version += 1;
+        object.insert("version".to_string(), serde_json::json!(version));
+    }
+
+    Ok(value)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_profile_without_version_to_current() {
+        let value = serde_json::json!({
+            "skillpoints": 12,
+            "skilltree_nodes_owned": [],
+        });
+        let value = migrate_profile(value).unwrap();
+        let profile: PlayerProfile = serde_json::from_value(value).unwrap();
+
+        assert_eq!(profile.version, PROFILE_VERSION);
+        assert_eq!(profile.skillpoints, 12);
+        assert_eq!(profile.highscore, 0);
+        assert!(profile.settings.screenshake_enabled);
+    }
+
+    #[test]
+    fn migrate_profile_rejects_newer_version() {
+        let value = serde_json::json!({ "version": PROFILE_VERSION + 1 });
+        assert!(migrate_profile(value).is_err());
+    }
+
+    #[test]
+    fn load_corrupt_profile_falls_back_and_keeps_file() {
+        let test_dir =
+            std::env::temp_dir().join(format!("bytepath_profile_test_{}", std::process::id()));
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let filepath = test_dir.join(PROFILE_FILENAME);
+        std::fs::write(&filepath, "{ this is not json").unwrap();
+
+        let profile = PlayerProfile::load_from_file_or_default(&filepath);
+        let filepath_corrupt = filepath.with_extension("json.corrupt");
+
+        assert_eq!(profile.skillpoints, 0);
+        assert!(!filepath.exists());
+        assert_eq!(
+            std::fs::read_to_string(&filepath_corrupt).unwrap(),
+            "{ this is not json"
+        );
+
+        std::fs::remove_dir_all(&test_dir).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use ct_lib::math::*;
+
+use crate::stage::{ShipType, Skill};
+
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+use std::path::Path;
+
+/// Must be incremented whenever the layout of `ReplayFile` changes. We do not migrate old replays
+/// as they desync anyway as soon as the stage logic changes.
+const REPLAY_VERSION: u64 = 1;
+
+/// How many frames pass between two checksums of the stage state
+pub const REPLAY_CHECKSUM_INTERVAL: usize = 60;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Frames
+
+/// The keys that the stage reacts to during gameplay
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter)]
+pub enum ReplayKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    S,
+    T,
+    C,
+}
+
+impl ReplayKey {
+    fn mask(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+/// Everything the stage reads from its input during one update
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub deltatime: f32,
+    keys_down: u16,
+    keys_pressed: u16,
+    /// Any key was pressed, not only the ones in `ReplayKey`
+    pub any_key_pressed: bool,
+}
+
+impl ReplayFrame {
+    pub fn new(deltatime: f32) -> ReplayFrame {
+        ReplayFrame {
+            deltatime,
+            ..ReplayFrame::default()
+        }
+    }
+
+    pub fn set_key(&mut self, key: ReplayKey, is_down: bool, recently_pressed: bool) {
+        if is_down {
+            self.keys_down |= key.mask();
+        }
+        if recently_pressed {
+            self.keys_pressed |= key.mask();
+        }
+    }
+
+    pub fn is_down(&self, key: ReplayKey) -> bool {
+        self.keys_down & key.mask() != 0
+    }
+
+    pub fn recently_pressed(&self, key: ReplayKey) -> bool {
+        self.keys_pressed & key.mask() != 0
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Checksum
+
+/// FNV-1a hash over the raw bits of the given values so that even the smallest difference between
+/// two runs shows up
+#[derive(Debug, Copy, Clone)]
+pub struct Checksum {
+    hash: u64,
+}
+
+impl Default for Checksum {
+    fn default() -> Checksum {
+        Checksum {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Checksum {
+    pub fn add_u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes().iter() {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    pub fn add_f32(&mut self, value: f32) {
+        self.add_u64(u64::from(value.to_bits()));
+    }
+
+    pub fn add_vec2(&mut self, value: Vec2) {
+        self.add_f32(value.x);
+        self.add_f32(value.y);
+    }
+
+    pub fn finish(self) -> u64 {
+        self.hash
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Replay
+
+/// A recorded stage run. Playing it back with the same build and data files reproduces the run
+/// frame by frame.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub ship_type: ShipType,
+    pub skills: Vec<Skill>,
+
+    pub frames: Vec<ReplayFrame>,
+    /// The state checksum after every `REPLAY_CHECKSUM_INTERVAL` frames
+    pub checksums: Vec<u64>,
+}
+
+/// On disk we store consecutive equal frames as a single run. This keeps files small as the
+/// deltatime rarely changes and keys are usually held for many frames.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReplayFile {
+    version: u64,
+    seed: u64,
+    ship_type: ShipType,
+    skills: Vec<Skill>,
+    checksum_interval: usize,
+    frame_runs: Vec<(usize, ReplayFrame)>,
+    checksums: Vec<u64>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, ship_type: ShipType, skills: &[Skill]) -> Replay {
+        Replay {
+            seed,
+            ship_type,
+            skills: skills.to_vec(),
+            frames: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn load_from_file(filepath: &Path) -> Result<Replay, String> {
+        let content = std::fs::read_to_string(filepath).map_err(|error| {
+            format!("Could not read replay '{}': {}", filepath.display(), error)
+        })?;
+        let file: ReplayFile = serde_json::from_str(&content).map_err(|error| {
+            format!("Could not parse replay '{}': {}", filepath.display(), error)
+        })?;
+
+        if file.version != REPLAY_VERSION {
+            return Err(format!(
+                "Replay '{}' has version {} but we can only play version {}",
+                filepath.display(),
+                file.version,
+                REPLAY_VERSION
+            ));
+        }
+        if file.checksum_interval != REPLAY_CHECKSUM_INTERVAL {
+            return Err(format!(
+                "Replay '{}' has a checksum interval of {} but we expect {}",
+                filepath.display(),
+                file.checksum_interval,
+                REPLAY_CHECKSUM_INTERVAL
+            ));
+        }
+
+        let frames = file
+            .frame_runs
+            .iter()
+            .flat_map(|&(count, frame)| std::iter::repeat(frame).take(count))
+            .collect();
+        Ok(Replay {
+            seed: file.seed,
+            ship_type: file.ship_type,
+            skills: file.skills,
+            frames,
+            checksums: file.checksums,
+        })
+    }
+
+    pub fn save_to_file(&self, filepath: &Path) -> Result<(), String> {
+        let mut frame_runs: Vec<(usize, ReplayFrame)> = Vec::new();
+        for &frame in &self.frames {
+            match frame_runs.last_mut() {
+                Some((count, frame_last)) if *frame_last == frame => *count += 1,
+                _ => frame_runs.push((1, frame)),
+            }
+        }
+
+        let file = ReplayFile {
+            version: REPLAY_VERSION,
+            seed: self.seed,
+            ship_type: self.ship_type,
+            skills: self.skills.clone(),
+            checksum_interval: REPLAY_CHECKSUM_INTERVAL,
+            frame_runs,
+            checksums: self.checksums.clone(),
+        };
+        let content = serde_json::to_string(&file).map_err(|error| error.to_string())?;
+
+        if let Some(parent_dir) = filepath.parent() {
+            std::fs::create_dir_all(parent_dir).map_err(|error| error.to_string())?;
+        }
+        std::fs::write(filepath, content).map_err(|error| error.to_string())
+    }
+}
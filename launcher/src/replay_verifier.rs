@@ -0,0 +1,163 @@
+// Plays back a replay without any window, drawing or audio as fast as possible and reports how the
+// run went. Comparing its output before and after a change shows whether gameplay changed.
+//
+// Usage: replay_verifier --replay FILEPATH [--resources DIRPATH] [--format text|json]
+//
+// The data files are read from the `resources` folder of the working directory unless
+// `--resources` points somewhere else, e.g. to `assets_copy` of a checkout.
+
+use ct_lib::game::*;
+use ct_lib::math::*;
+
+use launcher::profile::ProfileSettings;
+use launcher::replay::{Replay, ReplayFrame};
+use launcher::stage::{self, SceneStage, StageContext};
+
+use serde_derive::Serialize;
+
+use std::path::PathBuf;
+
+const CANVAS_WIDTH: f32 = 480.0;
+const CANVAS_HEIGHT: f32 = 270.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+struct Options {
+    replay_filepath: PathBuf,
+    resources_dirpath: Option<String>,
+    format: OutputFormat,
+}
+
+impl Options {
+    fn from_args(args: &[String]) -> Result<Options, String> {
+        let mut replay_filepath = None;
+        let mut resources_dirpath = None;
+        let mut format = OutputFormat::Text;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for '{}'", arg))?;
+            match arg.as_str() {
+                "--replay" => replay_filepath = Some(PathBuf::from(value)),
+                "--resources" => resources_dirpath = Some(value.clone()),
+                "--format" => {
+                    format = match value.as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        _ => return Err(format!("Unknown format '{}'", value)),
+                    }
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+
+        Ok(Options {
+            replay_filepath: replay_filepath
+                .ok_or_else(|| "Missing argument '--replay'".to_owned())?,
+            resources_dirpath,
+            format,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReplayReport {
+    frame_count: usize,
+    score: usize,
+    difficulty: usize,
+    /// The first frame after which the player was dead
+    frame_of_death: Option<usize>,
+    /// The first frame at which the playback diverged from the recorded run
+    desync_frame: Option<usize>,
+}
+
+fn verify(replay: Replay) -> ReplayReport {
+    let frame_count = replay.frames.len();
+
+    let mut camera = GameCamera::new(Vec2::zero(), CANVAS_WIDTH, CANVAS_HEIGHT);
+    let mut context = StageContext {
+        camera: &mut camera,
+        deltatime: 0.0,
+        canvas_width: CANVAS_WIDTH,
+        canvas_height: CANVAS_HEIGHT,
+    };
+    let mut stage = SceneStage::new_headless(&mut context, replay, &ProfileSettings::default());
+
+    let mut frame_of_death = None;
+    let mut game_events = Vec::new();
+    for frame_index in 0..frame_count {
+        // NOTE: We have no scenes to switch to so we drop what the stage requested
+        game_events.clear();
+
+        // NOTE: The stage ignores our input frame and takes the recorded one
+        stage.update(ReplayFrame::new(0.0), &mut context, &mut game_events);
+
+        // Keeps the screenshakes from piling up
+        let deltatime = context.deltatime;
+        context.camera.update(deltatime);
+
+        if frame_of_death.is_none() && !stage.is_player_alive() {
+            frame_of_death = Some(frame_index + 1);
+        }
+    }
+
+    ReplayReport {
+        frame_count,
+        score: stage.get_score(),
+        difficulty: stage.get_difficulty(),
+        frame_of_death,
+        desync_frame: stage.get_replay_desync_frame(),
+    }
+}
+
+fn print_text(report: &ReplayReport) {
+    let frame_or_none = |frame: Option<usize>| match frame {
+        Some(frame) => frame.to_string(),
+        None => "none".to_owned(),
+    };
+    println!("frames:         {}", report.frame_count);
+    println!("score:          {}", report.score);
+    println!("difficulty:     {}", report.difficulty);
+    println!("frame of death: {}", frame_or_none(report.frame_of_death));
+    println!("desync frame:   {}", frame_or_none(report.desync_frame));
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!(
+            "Usage: replay_verifier --replay FILEPATH [--resources DIRPATH] [--format text|json]"
+        );
+        std::process::exit(1);
+    });
+
+    if let Some(dirpath) = &options.resources_dirpath {
+        stage::set_resources_dirpath(dirpath);
+    }
+
+    let replay = Replay::load_from_file(&options.replay_filepath).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+
+    let report = verify(replay);
+    match options.format {
+        OutputFormat::Text => print_text(&report),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Could not serialize replay report")
+        ),
+    }
+
+    // A desynced replay does not tell us anything about the current build
+    if report.desync_frame.is_some() {
+        std::process::exit(2);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::stage::{
+    get_draw_lines_for_ship, get_stats_for_ship, ShipStats, ShipType, COLOR_AMMO, COLOR_BACKGROUND,
+    COLOR_BOOST, COLOR_DEFAULT, COLOR_HP, COLOR_SKILL_POINT,
+};
+
+use ct_lib::audio::*;
+use ct_lib::draw::*;
+use ct_lib::game::*;
+use ct_lib::math::*;
+
+use strum::IntoEnumIterator;
+
+const DEPTH_BACKGROUND: Depth = 0.0;
+const DEPTH_SHIP: Depth = 10.0;
+const DEPTH_GUI: Depth = 55.0;
+
+const PREVIEW_SCALE: f32 = 4.0;
+const PREVIEW_TURN_SPEED: f32 = 45.0;
+
+const STAT_BAR_WIDTH: f32 = 64.0;
+const STAT_BAR_HEIGHT: f32 = 4.0;
+const STAT_LINE_HEIGHT: f32 = 12.0;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Ship select scene
+
+#[derive(Clone)]
+pub struct SceneShipSelect {
+    ship_types: Vec<ShipType>,
+    ship_index: usize,
+    preview_angle: f32,
+}
+
+impl SceneShipSelect {
+    pub fn new() -> SceneShipSelect {
+        let ship_types: Vec<ShipType> = ShipType::iter().collect();
+        let ship_index = ship_types
+            .iter()
+            .position(|&ship_type| ship_type == ShipType::Sorcerer)
+            .unwrap_or(0);
+        SceneShipSelect {
+            ship_types,
+            ship_index,
+            preview_angle: -90.0,
+        }
+    }
+
+    pub fn get_ship_type(&self) -> ShipType {
+        self.ship_types[self.ship_index]
+    }
+}
+
+impl Scene for SceneShipSelect {
+    fn update_and_draw(
+        &mut self,
+        draw: &mut Drawstate,
+        _audio: &mut Audiostate,
+        _assets: &mut GameAssets,
+        input: &GameInput,
+        globals: &mut Globals,
+        out_game_events: &mut Vec<GameEvent>,
+    ) {
+        draw.set_clear_color_and_depth(COLOR_BACKGROUND, DEPTH_BACKGROUND);
+
+        //------------------------------------------------------------------------------------------
+        // INPUT
+
+        let ship_count = self.ship_types.len();
+        if input.keyboard.recently_pressed_or_repeated(Scancode::Left) {
+            self.ship_index = (self.ship_index + ship_count - 1) % ship_count;
+        }
+        if input.keyboard.recently_pressed_or_repeated(Scancode::Right) {
+            self.ship_index = (self.ship_index + 1) % ship_count;
+        }
+
+        if input.keyboard.recently_pressed(Scancode::Return) {
+            out_game_events.push(GameEvent::SwitchToScene {
+                scene_name: "stage".to_string(),
+            });
+        } else if input.keyboard.recently_pressed(Scancode::T) {
+            out_game_events.push(GameEvent::SwitchToScene {
+                scene_name: "skilltree".to_string(),
+            });
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW SHIP PREVIEW
+
+        let ship_type = self.get_ship_type();
+        let canvas_center = Vec2::new(globals.canvas_width, globals.canvas_height) / 2.0;
+        let preview_center = canvas_center - Vec2::filled_y(40.0);
+
+        self.preview_angle += PREVIEW_TURN_SPEED * globals.deltatime;
+        let preview_dir = Vec2::from_angle_flipped_y(deg_to_rad(self.preview_angle));
+        for linestrip_raw in &get_draw_lines_for_ship(ship_type) {
+            let linestrip = Vec2::multiple_transformed(
+                linestrip_raw,
+                preview_center,
+                Vec2::zero(),
+                Vec2::filled(PREVIEW_SCALE),
+                preview_dir,
+            );
+            draw.draw_linestrip_bresenham(
+                &linestrip,
+                false,
+                DEPTH_SHIP,
+                COLOR_DEFAULT,
+                ADDITIVITY_NONE,
+            );
+        }
+
+        draw.draw_text(
+            &format!("< {} >", ship_type.to_string().to_uppercase()),
+            &globals.font_default,
+            1.0,
+            preview_center + Vec2::filled_y(36.0),
+            Vec2::zero(),
+            Some(TextAlignment {
+                x: AlignmentHorizontal::Center,
+                y: AlignmentVertical::Center,
+                origin_is_baseline: false,
+                ignore_whitespace: true,
+            }),
+            None,
+            DEPTH_GUI,
+            COLOR_SKILL_POINT,
+            ADDITIVITY_NONE,
+        );
+
+        //------------------------------------------------------------------------------------------
+        // DRAW STATS
+
+        // NOTE: Bars are relative to the best ship for every stat so that ships can be compared
+        let stats = get_stats_for_ship(ship_type);
+        let stats_all: Vec<ShipStats> = self
+            .ship_types
+            .iter()
+            .map(|&ship_type| get_stats_for_ship(ship_type))
+            .collect();
+        let stat_rows: [(&str, fn(&ShipStats) -> f32, Color); 7] = [
+            ("SPEED", |stats| stats.speed, COLOR_BOOST),
+            ("TURN SPEED", |stats| stats.turn_speed, COLOR_BOOST),
+            ("HP", |stats| stats.hp_multiplier, COLOR_HP),
+            ("AMMO", |stats| stats.ammo_multiplier, COLOR_AMMO),
+            ("BOOST", |stats| stats.boost_multiplier, COLOR_BOOST),
+            (
+                "ATTACK SPEED",
+                |stats| stats.attack_speed_multiplier,
+                COLOR_AMMO,
+            ),
+            (
+                "CYCLE SPEED",
+                |stats| stats.cycle_speed_multiplier,
+                COLOR_DEFAULT,
+            ),
+        ];
+
+        let stats_top = preview_center.y + 56.0;
+        for (row_index, (name, get_value, color)) in stat_rows.iter().enumerate() {
+            let value_max = stats_all
+                .iter()
+                .map(get_value)
+                .fold(0.0, |acc: f32, value| f32::max(acc, value));
+            let row_center_y = stats_top + row_index as f32 * STAT_LINE_HEIGHT;
+
+            draw.draw_text(
+                name,
+                &globals.font_default,
+                1.0,
+                Vec2::new(canvas_center.x - 8.0, row_center_y),
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Right,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                *color,
+                ADDITIVITY_NONE,
+            );
+
+            let bar_left_top = Vec2::new(canvas_center.x, row_center_y - STAT_BAR_HEIGHT / 2.0);
+            draw.draw_rect(
+                Rect::from_pos_width_height(
+                    bar_left_top,
+                    STAT_BAR_WIDTH * get_value(&stats) / value_max,
+                    STAT_BAR_HEIGHT,
+                ),
+                true,
+                DEPTH_GUI,
+                *color,
+                ADDITIVITY_NONE,
+            );
+            draw.draw_rect(
+                Rect::from_pos_width_height(bar_left_top, STAT_BAR_WIDTH, STAT_BAR_HEIGHT),
+                false,
+                DEPTH_GUI,
+                *color,
+                ADDITIVITY_NONE,
+            );
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW GUI
+
+        draw.draw_text(
+            "LEFT/RIGHT TO CHOOSE - ENTER TO START - T FOR THE SKILLTREE",
+            &globals.font_default,
+            1.0,
+            Vec2::new(globals.canvas_width / 2.0, globals.canvas_height - 16.0),
+            Vec2::zero(),
+            Some(TextAlignment {
+                x: AlignmentHorizontal::Center,
+                y: AlignmentVertical::Center,
+                origin_is_baseline: false,
+                ignore_whitespace: true,
+            }),
+            None,
+            DEPTH_GUI,
+            COLOR_DEFAULT,
+            ADDITIVITY_NONE,
+        );
+    }
+}
@@ -1,28 +1,816 @@
+use crate::profile::PlayerProfile;
+use crate::stage::{
+    AttackType, OneTimeAction, Skill, COLOR_BACKGROUND, COLOR_DEFAULT, COLOR_HP, COLOR_SKILL_POINT,
+};
+
 use ct_lib::audio::*;
 use ct_lib::draw::*;
 use ct_lib::game::*;
+use ct_lib::math::*;
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+const DEPTH_BACKGROUND: Depth = 0.0;
+const DEPTH_LINKS: Depth = 10.0;
+const DEPTH_NODES_BACKGROUND: Depth = 20.0;
+const DEPTH_NODES: Depth = 21.0;
+const DEPTH_TOOLTIP_BACKGROUND: Depth = 50.0;
+const DEPTH_GUI: Depth = 55.0;
+
+const NODE_SIZE: f32 = 8.0;
+const TEXT_LINE_HEIGHT: f32 = 10.0;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Skilltree
+
+const SKILLTREE_FILEPATH: &str = "resources/skilltree.json";
+
+pub type SkilltreeNodeId = usize;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SkilltreeNodeDefinition {
+    id: SkilltreeNodeId,
+    x: f32,
+    y: f32,
+    cost: u32,
+    skills: Vec<Skill>,
+}
+
+/// The on-disk representation of a skilltree as it is authored by hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SkilltreeDefinition {
+    root: SkilltreeNodeId,
+    nodes: Vec<SkilltreeNodeDefinition>,
+    links: Vec<(SkilltreeNodeId, SkilltreeNodeId)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkilltreeNode {
+    pub id: SkilltreeNodeId,
+    /// Given relative to the root node
+    pub pos: Vec2,
+    pub cost: u32,
+    pub skills: Vec<Skill>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Skilltree {
+    pub root: SkilltreeNodeId,
+    pub nodes: Vec<SkilltreeNode>,
+    pub links: Vec<(SkilltreeNodeId, SkilltreeNodeId)>,
+
+    node_indices: HashMap<SkilltreeNodeId, usize>,
+    node_neighbors: HashMap<SkilltreeNodeId, Vec<SkilltreeNodeId>>,
+}
+
+impl Skilltree {
+    pub fn load_from_file(filepath: &str) -> Result<Skilltree, String> {
+        let content = std::fs::read_to_string(filepath)
+            .map_err(|error| format!("Could not read skilltree '{}': {}", filepath, error))?;
+        let definition: SkilltreeDefinition = serde_json::from_str(&content)
+            .map_err(|error| format!("Could not parse skilltree '{}': {}", filepath, error))?;
+        Skilltree::from_definition(definition)
+            .map_err(|error| format!("Skilltree '{}' is invalid:\n{}", filepath, error))
+    }
+
+    /// Returns all validation errors at once so that they can be fixed in one go
+    fn from_definition(definition: SkilltreeDefinition) -> Result<Skilltree, String> {
+        let mut errors = Vec::new();
+
+        let mut node_indices: HashMap<SkilltreeNodeId, usize> = HashMap::new();
+        for (index, node) in definition.nodes.iter().enumerate() {
+            if node_indices.insert(node.id, index).is_some() {
+                errors.push(format!("Node {}: id is used by more than one node", node.id));
+            }
+            if node.skills.is_empty() {
+                errors.push(format!("Node {}: has no skills", node.id));
+            }
+            for skill in &node.skills {
+                // NOTE: The stage adds up skill values and uses some of them as counts
+                if let Some(value) = skill.value() {
+                    if value < 0 {
+                        errors.push(format!(
+                            "Node {}: {} value {} is negative",
+                            node.id, skill.name(), value
+                        ));
+                    }
+                }
+                if let Skill::Proc(proc) = skill {
+                    if proc.chance <= 0 || proc.chance > 100 {
+                        errors.push(format!(
+                            "Node {}: proc chance {} is not in range [1, 100]",
+                            node.id, proc.chance
+                        ));
+                    }
+                    if proc.action == OneTimeAction::SpawnAttack(AttackType::Neutral) {
+                        errors.push(format!(
+                            "Node {}: proc can not spawn the neutral attack",
+                            node.id
+                        ));
+                    }
+                }
+            }
+        }
+        if !node_indices.contains_key(&definition.root) {
+            errors.push(format!("Root node {} does not exist", definition.root));
+        }
+
+        let mut node_neighbors: HashMap<SkilltreeNodeId, Vec<SkilltreeNodeId>> = HashMap::new();
+        for (index, &(node_a, node_b)) in definition.links.iter().enumerate() {
+            let mut link_is_valid = true;
+            for &node in &[node_a, node_b] {
+                if !node_indices.contains_key(&node) {
+                    errors.push(format!(
+                        "Link {} ({} - {}): node {} does not exist",
+                        index, node_a, node_b, node
+                    ));
+                    link_is_valid = false;
+                }
+            }
+            if node_a == node_b {
+                errors.push(format!(
+                    "Link {} ({} - {}): links a node to itself",
+                    index, node_a, node_b
+                ));
+                link_is_valid = false;
+            }
+            if let Some(index_other) = definition.links[..index].iter().position(|&link| {
+                link == (node_a, node_b) || link == (node_b, node_a)
+            }) {
+                errors.push(format!(
+                    "Link {} ({} - {}): duplicates link {}",
+                    index, node_a, node_b, index_other
+                ));
+                link_is_valid = false;
+            }
+
+            if link_is_valid {
+                node_neighbors.entry(node_a).or_insert_with(Vec::new).push(node_b);
+                node_neighbors.entry(node_b).or_insert_with(Vec::new).push(node_a);
+            }
+        }
+
+        // Every node must be purchasable eventually
+        if node_indices.contains_key(&definition.root) {
+            let mut reachable = HashSet::new();
+            let mut to_visit = vec![definition.root];
+            while let Some(node) = to_visit.pop() {
+                if reachable.insert(node) {
+                    if let Some(neighbors) = node_neighbors.get(&node) {
+                        to_visit.extend(neighbors.iter().cloned());
+                    }
+                }
+            }
+            for node in &definition.nodes {
+                if !reachable.contains(&node.id) {
+                    errors.push(format!(
+                        "Node {}: is not reachable from root node {}",
+                        node.id, definition.root
+                    ));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+
+        let nodes = definition
+            .nodes
+            .into_iter()
+            .map(|node| SkilltreeNode {
+                id: node.id,
+                pos: Vec2::new(node.x, node.y),
+                cost: node.cost,
+                skills: node.skills,
+            })
+            .collect();
+
+        Ok(Skilltree {
+            root: definition.root,
+            nodes,
+            links: definition.links,
+            node_indices,
+            node_neighbors,
+        })
+    }
+
+    pub fn get_node(&self, node_id: SkilltreeNodeId) -> Option<&SkilltreeNode> {
+        self.node_indices
+            .get(&node_id)
+            .map(|&index| &self.nodes[index])
+    }
+
+    pub fn get_neighbors(&self, node_id: SkilltreeNodeId) -> &[SkilltreeNodeId] {
+        self.node_neighbors
+            .get(&node_id)
+            .map(|neighbors| neighbors.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// A node can only be purchased if it is not yet owned and directly linked to an owned node
+    pub fn can_purchase_node(
+        &self,
+        node_id: SkilltreeNodeId,
+        nodes_owned: &HashSet<SkilltreeNodeId>,
+    ) -> bool {
+        if self.get_node(node_id).is_none() || nodes_owned.contains(&node_id) {
+            return false;
+        }
+        self.get_neighbors(node_id)
+            .iter()
+            .any(|neighbor| nodes_owned.contains(neighbor))
+    }
+
+    /// A node can only be refunded if all other owned nodes stay connected to the root without it
+    pub fn can_refund_node(
+        &self,
+        node_id: SkilltreeNodeId,
+        nodes_owned: &HashSet<SkilltreeNodeId>,
+    ) -> bool {
+        if node_id == self.root || !nodes_owned.contains(&node_id) {
+            return false;
+        }
+
+        let mut nodes_remaining = nodes_owned.clone();
+        nodes_remaining.remove(&node_id);
+
+        let mut reachable = HashSet::new();
+        let mut to_visit = vec![self.root];
+        while let Some(node) = to_visit.pop() {
+            if nodes_remaining.contains(&node) && reachable.insert(node) {
+                to_visit.extend(self.get_neighbors(node).iter().cloned());
+            }
+        }
+        reachable.len() == nodes_remaining.len()
+    }
+
+    pub fn collect_skills(&self, nodes_owned: &HashSet<SkilltreeNodeId>) -> Vec<Skill> {
+        self.nodes
+            .iter()
+            .filter(|node| nodes_owned.contains(&node.id))
+            .flat_map(|node| node.skills.iter().cloned())
+            .collect()
+    }
+
+    fn get_node_at_pos(&self, pos: Vec2, node_size: f32) -> Option<SkilltreeNodeId> {
+        self.nodes
+            .iter()
+            .find(|node| {
+                Rect::from_pos_width_height(node.pos, node_size, node_size)
+                    .centered()
+                    .contains_point(pos)
+            })
+            .map(|node| node.id)
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Skilltree Scene
 
 #[derive(Clone)]
-pub struct SceneSkilltree {}
+pub struct SceneSkilltree {
+    skilltree: Skilltree,
+    skillpoints: usize,
+    nodes_owned: HashSet<SkilltreeNodeId>,
+    node_hovered: Option<SkilltreeNodeId>,
+
+    /// Nodes purchased since entering this scene, used for undoing purchases
+    purchase_history: Vec<SkilltreeNodeId>,
+    has_uncommitted_changes: bool,
+}
 
 impl SceneSkilltree {
-    pub fn new() -> SceneSkilltree {
-        SceneSkilltree {}
+    pub fn new(profile: &PlayerProfile) -> SceneSkilltree {
+        let skilltree =
+            Skilltree::load_from_file(SKILLTREE_FILEPATH).unwrap_or_else(|error| panic!("{}", error));
+
+        // NOTE: We silently drop nodes that do not exist (anymore) in our skilltree
+        let mut nodes_owned: HashSet<SkilltreeNodeId> = profile
+            .skilltree_nodes_owned
+            .iter()
+            .filter(|&&node_id| skilltree.get_node(node_id).is_some())
+            .cloned()
+            .collect();
+        nodes_owned.insert(skilltree.root);
+
+        SceneSkilltree {
+            skilltree,
+            skillpoints: profile.skillpoints,
+            nodes_owned,
+            node_hovered: None,
+            purchase_history: Vec::new(),
+            has_uncommitted_changes: false,
+        }
+    }
+
+    pub fn get_nodes_owned(&self) -> Vec<SkilltreeNodeId> {
+        let mut result: Vec<SkilltreeNodeId> = self.nodes_owned.iter().cloned().collect();
+        result.sort_unstable();
+        result
+    }
+
+    pub fn get_skills_owned(&self) -> Vec<Skill> {
+        self.skilltree.collect_skills(&self.nodes_owned)
+    }
+
+    pub fn has_uncommitted_changes(&self) -> bool {
+        self.has_uncommitted_changes
+    }
+
+    pub fn write_to_profile(&mut self, profile: &mut PlayerProfile) {
+        profile.skillpoints = self.skillpoints;
+        profile.skilltree_nodes_owned = self.get_nodes_owned();
+        self.has_uncommitted_changes = false;
+    }
+
+    fn can_afford_node(&self, node_id: SkilltreeNodeId) -> bool {
+        self.skilltree
+            .get_node(node_id)
+            .map(|node| node.cost as usize <= self.skillpoints)
+            .unwrap_or(false)
+    }
+
+    fn purchase_node(&mut self, node_id: SkilltreeNodeId) -> bool {
+        if !self.skilltree.can_purchase_node(node_id, &self.nodes_owned)
+            || !self.can_afford_node(node_id)
+        {
+            return false;
+        }
+
+        let cost = self.skilltree.get_node(node_id).unwrap().cost as usize;
+        self.skillpoints -= cost;
+        self.nodes_owned.insert(node_id);
+        self.purchase_history.push(node_id);
+        self.has_uncommitted_changes = true;
+        true
+    }
+
+    fn refund_node(&mut self, node_id: SkilltreeNodeId) -> bool {
+        if !self.skilltree.can_refund_node(node_id, &self.nodes_owned) {
+            return false;
+        }
+
+        let cost = self.skilltree.get_node(node_id).unwrap().cost as usize;
+        self.skillpoints += cost;
+        self.nodes_owned.remove(&node_id);
+        self.purchase_history.retain(|&purchased| purchased != node_id);
+        self.has_uncommitted_changes = true;
+        true
+    }
+
+    fn undo_last_purchase(&mut self) -> bool {
+        if let Some(&node_id) = self.purchase_history.last() {
+            self.refund_node(node_id)
+        } else {
+            false
+        }
     }
 }
 
 impl Scene for SceneSkilltree {
     fn update_and_draw(
         &mut self,
-        _draw: &mut Drawstate,
+        draw: &mut Drawstate,
         _audio: &mut Audiostate,
         _assets: &mut GameAssets,
-        _input: &GameInput,
-        _globals: &mut Globals,
-        _out_game_events: &mut Vec<GameEvent>,
+        input: &GameInput,
+        globals: &mut Globals,
+        out_game_events: &mut Vec<GameEvent>,
     ) {
+        draw.set_clear_color_and_depth(COLOR_BACKGROUND, DEPTH_BACKGROUND);
+
+        let tree_center = Vec2::new(globals.canvas_width, globals.canvas_height) / 2.0;
+
+        //------------------------------------------------------------------------------------------
+        // INPUT
+
+        let mouse_pos = globals.cursors.mouse_coords.pos_world;
+        self.node_hovered = self
+            .skilltree
+            .get_node_at_pos(mouse_pos - tree_center, NODE_SIZE);
+
+        if input.mouse.button_left.recently_pressed() {
+            if let Some(node_id) = self.node_hovered {
+                self.purchase_node(node_id);
+            }
+        }
+        if input.mouse.button_right.recently_pressed() {
+            if let Some(node_id) = self.node_hovered {
+                self.refund_node(node_id);
+            }
+        }
+        if input.keyboard.recently_pressed(Scancode::Z) {
+            self.undo_last_purchase();
+        }
+
+        if input.keyboard.recently_pressed(Scancode::Return) {
+            out_game_events.push(GameEvent::SwitchToScene {
+                scene_name: "shipselect".to_string(),
+            });
+        } else if input.keyboard.recently_pressed(Scancode::C) {
+            out_game_events.push(GameEvent::SwitchToScene {
+                scene_name: "console".to_string(),
+            });
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW LINKS
+
+        for &(node_a_id, node_b_id) in &self.skilltree.links {
+            let node_a = self.skilltree.get_node(node_a_id).unwrap();
+            let node_b = self.skilltree.get_node(node_b_id).unwrap();
+
+            let node_a_owned = self.nodes_owned.contains(&node_a_id);
+            let node_b_owned = self.nodes_owned.contains(&node_b_id);
+            let color = if node_a_owned && node_b_owned {
+                COLOR_DEFAULT
+            } else if node_a_owned || node_b_owned {
+                Color::greyscale(0.5)
+            } else {
+                Color::greyscale(0.25)
+            };
+
+            draw.draw_line_with_thickness(
+                tree_center + node_a.pos,
+                tree_center + node_b.pos,
+                1.0,
+                false,
+                DEPTH_LINKS,
+                color,
+                ADDITIVITY_NONE,
+            );
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW NODES
+
+        for node in &self.skilltree.nodes {
+            let is_owned = self.nodes_owned.contains(&node.id);
+            let is_purchasable = self.skilltree.can_purchase_node(node.id, &self.nodes_owned)
+                && self.can_afford_node(node.id);
+            let color = if is_owned {
+                COLOR_SKILL_POINT
+            } else if is_purchasable {
+                COLOR_DEFAULT
+            } else {
+                Color::greyscale(0.4)
+            };
+
+            let rect =
+                Rect::from_pos_width_height(tree_center + node.pos, NODE_SIZE, NODE_SIZE).centered();
+
+            // NOTE: We fill the background so that links do not shine through our nodes
+            draw.draw_rect(
+                rect,
+                true,
+                DEPTH_NODES_BACKGROUND,
+                COLOR_BACKGROUND,
+                ADDITIVITY_NONE,
+            );
+            draw.draw_rect(rect, is_owned, DEPTH_NODES, color, ADDITIVITY_NONE);
+
+            if self.node_hovered == Some(node.id) {
+                draw.draw_rect(
+                    rect.extended_uniformly_by(2.0),
+                    false,
+                    DEPTH_NODES,
+                    color,
+                    ADDITIVITY_NONE,
+                );
+            }
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW TOOLTIP
+
+        if let Some(node_id) = self.node_hovered {
+            let node = self.skilltree.get_node(node_id).unwrap();
+            let is_owned = self.nodes_owned.contains(&node_id);
+            let is_connected = self.skilltree.can_purchase_node(node_id, &self.nodes_owned);
+            let is_refundable = self.skilltree.can_refund_node(node_id, &self.nodes_owned);
+
+            let mut lines: Vec<(String, Color)> = node
+                .skills
+                .iter()
+                .map(|skill| (skill.description(), COLOR_DEFAULT))
+                .collect();
+            if is_owned {
+                if is_refundable {
+                    lines.push((
+                        format!("RIGHT CLICK TO REFUND {} SP", node.cost),
+                        COLOR_SKILL_POINT,
+                    ));
+                } else {
+                    lines.push(("OWNED".to_string(), COLOR_SKILL_POINT));
+                }
+            } else if !is_connected {
+                lines.push(("NOT CONNECTED".to_string(), Color::greyscale(0.5)));
+            } else if self.can_afford_node(node_id) {
+                lines.push((
+                    format!("CLICK TO PURCHASE FOR {} SP", node.cost),
+                    COLOR_SKILL_POINT,
+                ));
+            } else {
+                lines.push((format!("NEEDS {} SP", node.cost), COLOR_HP));
+            }
+
+            let tooltip_pos = mouse_pos + Vec2::new(8.0, 8.0);
+            let mut tooltip_width: f32 = 0.0;
+            for (line_index, (text, color)) in lines.iter().enumerate() {
+                let text_end = draw.draw_text(
+                    text,
+                    &globals.font_default,
+                    1.0,
+                    tooltip_pos + Vec2::filled_y(line_index as f32 * TEXT_LINE_HEIGHT),
+                    Vec2::zero(),
+                    None,
+                    None,
+                    DEPTH_GUI,
+                    *color,
+                    ADDITIVITY_NONE,
+                );
+                tooltip_width = f32::max(tooltip_width, text_end.x);
+            }
+            draw.draw_rect(
+                Rect::from_pos_width_height(
+                    tooltip_pos,
+                    tooltip_width,
+                    lines.len() as f32 * TEXT_LINE_HEIGHT,
+                )
+                .extended_uniformly_by(2.0),
+                true,
+                DEPTH_TOOLTIP_BACKGROUND,
+                COLOR_BACKGROUND,
+                ADDITIVITY_NONE,
+            );
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW GUI
+
+        draw.draw_text(
+            &format!("{} SP", self.skillpoints),
+            &globals.font_default,
+            1.0,
+            Vec2::new(20.0, 10.0),
+            Vec2::zero(),
+            Some(TextAlignment {
+                x: AlignmentHorizontal::Left,
+                y: AlignmentVertical::Top,
+                origin_is_baseline: false,
+                ignore_whitespace: false,
+            }),
+            None,
+            DEPTH_GUI,
+            COLOR_SKILL_POINT,
+            ADDITIVITY_NONE,
+        );
+        if !self.purchase_history.is_empty() {
+            draw.draw_text(
+                "PRESS Z TO UNDO",
+                &globals.font_default,
+                1.0,
+                Vec2::new(globals.canvas_width - 20.0, 10.0),
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Right,
+                    y: AlignmentVertical::Top,
+                    origin_is_baseline: false,
+                    ignore_whitespace: false,
+                }),
+                None,
+                DEPTH_GUI,
+                COLOR_DEFAULT,
+                ADDITIVITY_NONE,
+            );
+        }
+        draw.draw_text(
+            "PRESS ENTER TO START - PRESS C FOR THE CONSOLE",
+            &globals.font_default,
+            1.0,
+            Vec2::new(globals.canvas_width / 2.0, globals.canvas_height - 16.0),
+            Vec2::zero(),
+            Some(TextAlignment {
+                x: AlignmentHorizontal::Center,
+                y: AlignmentVertical::Center,
+                origin_is_baseline: false,
+                ignore_whitespace: true,
+            }),
+            None,
+            DEPTH_GUI,
+            COLOR_DEFAULT,
+            ADDITIVITY_NONE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skilltree_from_json(json: &str) -> Result<Skilltree, String> {
+        let definition: SkilltreeDefinition =
+            serde_json::from_str(json).expect("Test skilltree is not valid json");
+        Skilltree::from_definition(definition)
+    }
+
+    fn assert_rejected(json: &str, error_expected: &str) {
+        let error = skilltree_from_json(json).unwrap_err();
+        assert!(
+            error.contains(error_expected),
+            "Expected error '{}' but got:\n{}",
+            error_expected,
+            error
+        );
+    }
+
+    #[test]
+    fn skilltree_accepts_valid_definition() {
+        let skilltree = skilltree_from_json(
+            r#"{
+                "root": 0,
+                "nodes": [
+                    {"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]},
+                    {"id": 1, "x": 24, "y": 0, "cost": 1, "skills": [{"AddProjectilePierce": 1}]},
+                    {"id": 2, "x": 48, "y": 0, "cost": 1, "skills": [
+                        {"Proc": {"trigger": "Kill", "action": "RegainHp", "chance": 100}}
+                    ]}
+                ],
+                "links": [[0, 1], [1, 2]]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(skilltree.get_neighbors(1), &[0, 2]);
+        let nodes_owned: HashSet<SkilltreeNodeId> = [0].iter().cloned().collect();
+        assert!(skilltree.can_purchase_node(1, &nodes_owned));
+        assert!(!skilltree.can_purchase_node(2, &nodes_owned));
+    }
+
+    #[test]
+    fn skilltree_shipped_with_the_game_is_valid() {
+        let filepath = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets_copy/skilltree.json");
+        Skilltree::load_from_file(filepath).unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    #[test]
+    fn skilltree_rejects_duplicate_node_ids() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [
+                    {"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]},
+                    {"id": 0, "x": 24, "y": 0, "cost": 1, "skills": [{"AddHp": 10}]}
+                ],
+                "links": []
+            }"#,
+            "Node 0: id is used by more than one node",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_missing_root() {
+        assert_rejected(
+            r#"{
+                "root": 7,
+                "nodes": [{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]}],
+                "links": []
+            }"#,
+            "Root node 7 does not exist",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_node_without_skills() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": []}],
+                "links": []
+            }"#,
+            "Node 0: has no skills",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_link_to_missing_node() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]}],
+                "links": [[0, 3]]
+            }"#,
+            "Link 0 (0 - 3): node 3 does not exist",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_self_link() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]}],
+                "links": [[0, 0]]
+            }"#,
+            "Link 0 (0 - 0): links a node to itself",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_duplicate_link_in_either_direction() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [
+                    {"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]},
+                    {"id": 1, "x": 24, "y": 0, "cost": 1, "skills": [{"AddHp": 10}]}
+                ],
+                "links": [[0, 1], [1, 0]]
+            }"#,
+            "Link 1 (1 - 0): duplicates link 0",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_unreachable_node() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [
+                    {"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [{"AddHp": 10}]},
+                    {"id": 1, "x": 24, "y": 0, "cost": 1, "skills": [{"AddHp": 10}]},
+                    {"id": 2, "x": 48, "y": 0, "cost": 1, "skills": [{"AddHp": 10}]}
+                ],
+                "links": [[1, 2]]
+            }"#,
+            "Node 2: is not reachable from root node 0",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_proc_spawning_neutral_attack() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [
+                    {"Proc": {"trigger": "Kill", "action": {"SpawnAttack": "Neutral"}, "chance": 5}}
+                ]}],
+                "links": []
+            }"#,
+            "Node 0: proc can not spawn the neutral attack",
+        );
+    }
+
+    #[test]
+    fn skilltree_rejects_proc_chance_out_of_range() {
+        for &chance in &[0, 101] {
+            assert_rejected(
+                &format!(
+                    r#"{{
+                        "root": 0,
+                        "nodes": [{{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [
+                            {{"Proc": {{"trigger": "Hit", "action": "Barrage", "chance": {}}}}}
+                        ]}}],
+                        "links": []
+                    }}"#,
+                    chance
+                ),
+                &format!("Node 0: proc chance {} is not in range [1, 100]", chance),
+            );
+        }
+    }
+
+    #[test]
+    fn skilltree_rejects_negative_skill_value() {
+        assert_rejected(
+            r#"{
+                "root": 0,
+                "nodes": [{"id": 0, "x": 0, "y": 0, "cost": 0, "skills": [
+                    {"AddProjectileSplit": -1}
+                ]}],
+                "links": []
+            }"#,
+            "Node 0: Split value -1 is negative",
+        );
+    }
+
+    #[test]
+    fn skilltree_reports_all_errors_at_once() {
+        let error = skilltree_from_json(
+            r#"{
+                "root": 0,
+                "nodes": [
+                    {"id": 0, "x": 0, "y": 0, "cost": 0, "skills": []},
+                    {"id": 1, "x": 24, "y": 0, "cost": 1, "skills": [{"AddHp": -5}]}
+                ],
+                "links": [[1, 1]]
+            }"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.lines().count(), 4, "{}", error);
     }
 }
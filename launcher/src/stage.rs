@@ -1,13 +1,23 @@
-// Stopped at "Haste Area" in https://github.com/adnzzzzZ/blog/issues/25
-
 use ct_lib::audio::*;
 use ct_lib::draw::*;
 use ct_lib::game::*;
 use ct_lib::math::*;
 use ct_lib::random::*;
 
+use crate::broadphase::{find_collision_pairs, BroadphaseBody, BROADPHASE_CELL_SIZE_DEFAULT};
+use crate::console::{enum_variant_names, ArgType, CommandRegistry, Terminal, TerminalAction};
+use crate::director::{
+    load_director_config_from_file, Director, DirectorConfig, EnemyType, ResourceType,
+};
+use crate::physics::{
+    collision_positional_correction, collision_resolve, sweep_circle_circle, Body, Shape,
+};
+use crate::profile::ProfileSettings;
+use crate::replay::{Checksum, Replay, ReplayFrame, ReplayKey, REPLAY_CHECKSUM_INTERVAL};
+
 use ct_lib::dformat;
 use lazy_static::*;
+use serde_derive::{Deserialize, Serialize};
 
 use hecs::*;
 use strum::IntoEnumIterator;
@@ -15,6 +25,7 @@ use strum_macros::EnumIter;
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Mutex;
 
 const DEBUG_DRAW_ENABLE: bool = false;
 
@@ -26,14 +37,16 @@ const DEPTH_EFFECTS: Depth = 30.0;
 const DEPTH_INFOTEXT: Depth = 35.0;
 const DEPTH_SCREENFLASH: Depth = 60.0;
 const DEPTH_GUI: Depth = 55.0;
+const DEPTH_DEBUG_TERMINAL_BACKGROUND: Depth = 65.0;
+const DEPTH_DEBUG_TERMINAL: Depth = 66.0;
 
 // TODO: When f32 gets const functions we can just use from_rgb_bytes instead of this monstrosity
-const COLOR_BACKGROUND: Color = Color::from_rgb(16.0 / 255.0, 16.0 / 255.0, 16.0 / 255.0);
-const COLOR_DEFAULT: Color = Color::from_rgb(222.0 / 255.0, 222.0 / 255.0, 222.0 / 255.0);
-const COLOR_AMMO: Color = Color::from_rgb(123.0 / 255.0, 200.0 / 255.0, 164.0 / 255.0);
-const COLOR_BOOST: Color = Color::from_rgb(76.0 / 255.0, 195.0 / 255.0, 217.0 / 255.0);
-const COLOR_HP: Color = Color::from_rgb(241.0 / 255.0, 103.0 / 255.0, 69.0 / 255.0);
-const COLOR_SKILL_POINT: Color = Color::from_rgb(255.0 / 255.0, 198.0 / 255.0, 93.0 / 255.0);
+pub const COLOR_BACKGROUND: Color = Color::from_rgb(16.0 / 255.0, 16.0 / 255.0, 16.0 / 255.0);
+pub const COLOR_DEFAULT: Color = Color::from_rgb(222.0 / 255.0, 222.0 / 255.0, 222.0 / 255.0);
+pub const COLOR_AMMO: Color = Color::from_rgb(123.0 / 255.0, 200.0 / 255.0, 164.0 / 255.0);
+pub const COLOR_BOOST: Color = Color::from_rgb(76.0 / 255.0, 195.0 / 255.0, 217.0 / 255.0);
+pub const COLOR_HP: Color = Color::from_rgb(241.0 / 255.0, 103.0 / 255.0, 69.0 / 255.0);
+pub const COLOR_SKILL_POINT: Color = Color::from_rgb(255.0 / 255.0, 198.0 / 255.0, 93.0 / 255.0);
 
 const COLOR_NEGATIVE_DEFAULT: Color = Color::from_rgb(
     1.0 - 222.0 / 255.0,
@@ -85,6 +98,10 @@ const COLLISION_LAYER_PLAYER: u64 = 1 << 1;
 const COLLISION_LAYER_ENEMY_PROJECTILE: u64 = 1 << 2;
 const COLLISION_LAYER_PLAYER_PROJECTILE: u64 = 1 << 3;
 const COLLISION_LAYER_COLLECTIBLES: u64 = 1 << 4;
+const COLLISION_LAYER_HAZARD: u64 = 1 << 5;
+
+/// Rigid bodies only bounce off other rigid bodies so they get their own layers
+const RIGIDBODY_LAYER_ROCKS: u64 = 1 << 0;
 
 const PLAYER_BASE_HP: f32 = 100.0;
 const PLAYER_BASE_BOOST: f32 = 100.0;
@@ -94,10 +111,42 @@ const PLAYER_BASE_GAIN_HP: f32 = 25.0;
 const PLAYER_BASE_GAIN_AMMO: f32 = 5.0;
 const PLAYER_BASE_GAIN_BOOST: f32 = 25.0;
 
+const PLAYER_BASE_CYCLE_COOLDOWN: f32 = 5.0;
+const PLAYER_BASE_INVULNERABILITY_TIME: f32 = 2.0;
+
+/// Skills can lower the multipliers of the player but never below this
+const PLAYER_MULTIPLIER_MIN: f32 = 0.1;
+
+const HASTE_AREA_RADIUS: f32 = 24.0;
+const HASTE_AREA_LIFETIME: f32 = 4.0;
+
+const BARRAGE_PROJECTILE_COUNT: usize = 8;
+const BARRAGE_SPREAD_ANGLE: f32 = 22.5;
+
+const ENEMY_CONTACT_DAMAGE: f32 = 30.0;
+const BIG_ROCK_SPLIT_COUNT: usize = 4;
+const ORBITTER_SATELLITE_COUNT: usize = 3;
+/// Ammo and boost per second that a Sapper takes from the player while touching
+const SAPPER_STEAL_RATE: f32 = 40.0;
+const MINE_LIFETIME: f32 = 8.0;
+const TRAIL_SEGMENT_LIFETIME: f32 = 2.5;
+const TRAIL_SEGMENT_INTERVAL: f32 = 0.1;
+const TRAIL_SEGMENT_RADIUS: f32 = 3.0;
+const ROCK_DENSITY: f32 = 1.0;
+const ROCK_BOUNCYNESS: f32 = 0.8;
+
+const BOSS_HP: f32 = 4000.0;
+const BOSS_RADIUS: f32 = 16.0;
+/// The boss enters its next phase when its hp percentage drops below each of these
+const BOSS_PHASE_HP_THRESHOLDS: [f32; 2] = [0.66, 0.33];
+const BOSS_ARMOR_ANGLES: [f32; 4] = [0.0, 60.0, 180.0, 240.0];
+const BOSS_ARMOR_DISTANCE: f32 = 28.0;
+const BOSS_SKILLPOINT_REWARD: usize = 10;
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Attacks
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, EnumIter)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum AttackType {
     Neutral,
     Double,
@@ -107,112 +156,371 @@ pub enum AttackType {
     Back,
     Side,
     Homing,
+    Blast,
+    Spin,
+    Flame,
+    Bounce,
+    Split2,
+    Split4,
+    Lightning,
+    Explode,
+    Laser,
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Names for our palette colors so that data files can refer to them
+#[derive(Debug, Copy, Clone, Deserialize)]
+enum PaletteColor {
+    Default,
+    Ammo,
+    Boost,
+    Hp,
+    SkillPoint,
+}
+
+impl PaletteColor {
+    fn to_color(self) -> Color {
+        match self {
+            PaletteColor::Default => COLOR_DEFAULT,
+            PaletteColor::Ammo => COLOR_AMMO,
+            PaletteColor::Boost => COLOR_BOOST,
+            PaletteColor::Hp => COLOR_HP,
+            PaletteColor::SkillPoint => COLOR_SKILL_POINT,
+        }
+    }
+}
+
+/// Explosions deal this fraction of their damage to enemies at the edge of the explosion radius
+const EXPLOSION_DAMAGE_PERCENTAGE_AT_EDGE: f32 = 0.25;
+
+/// Optional behavior of player projectiles. Attacks define a base set of modifiers which skills
+/// can add to.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ProjectileModifiers {
+    /// How many enemies a projectile passes through before it vanishes
+    #[serde(default)]
+    pierce: usize,
+    /// How often a projectile reflects off the canvas edges before it vanishes
+    #[serde(default)]
+    bounces: usize,
+    /// Angles of the child projectiles that get spawned when hitting an enemy, relative to the
+    /// flight direction
+    #[serde(default)]
+    split_angles: Vec<f32>,
+    /// Additionally damages every enemy in this radius when hitting an enemy. The damage falls off
+    /// towards the edge of the radius.
+    #[serde(default)]
+    explosion_radius: f32,
+}
+
+impl ProjectileModifiers {
+    fn combined_with(&self, other: &ProjectileModifiers) -> ProjectileModifiers {
+        ProjectileModifiers {
+            pierce: self.pierce + other.pierce,
+            bounces: self.bounces + other.bounces,
+            split_angles: self
+                .split_angles
+                .iter()
+                .chain(other.split_angles.iter())
+                .cloned()
+                .collect(),
+            explosion_radius: self.explosion_radius + other.explosion_radius,
+        }
+    }
+}
+
+/// Where projectiles of a shot start and where they fly to. Both angles are in degrees relative
+/// to the ships forward direction.
+#[derive(Debug, Copy, Clone, Deserialize)]
+struct ProjectileSpawn {
+    /// How many projectiles to fire from here. Only makes sense together with `angle_jitter`.
+    count: usize,
+    /// Rotates the flight direction
+    angle_offset: f32,
+    /// Rotates the spawn position around the ship, i.e. 180 fires from the back of the ship
+    spawn_angle_offset: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ProjectilePattern {
+    projectiles: Vec<ProjectileSpawn>,
+    /// Rotates each projectile by a random angle in [-angle_jitter, angle_jitter]
+    angle_jitter: f32,
+    /// Picks a random palette color for each shot instead of the attacks color
+    random_color: bool,
+    speed: f32,
+    size: f32,
+    damage: f32,
+    homing: bool,
+    trail: bool,
+
+    // NOTE: The following are optional as most attacks don't need them
+    //
+    /// Changes the speed of each projectile by a random value in [-speed_jitter, speed_jitter]
+    #[serde(default)]
+    speed_jitter: f32,
+    /// Projectiles vanish after the given time in seconds
+    #[serde(default)]
+    lifetime: Option<f32>,
+    /// Rotates the flight direction by this many degrees per second in a random direction so
+    /// that projectiles fly in circles
+    #[serde(default)]
+    spin_speed: f32,
+    #[serde(flatten)]
+    modifiers: ProjectileModifiers,
+}
+
+/// Instantly hits the closest enemy in range and then jumps to further enemies close by
+#[derive(Debug, Clone, Deserialize)]
+struct LightningPattern {
+    range: f32,
+    chain_range: f32,
+    chain_count: usize,
+    damage: f32,
+}
+
+/// Instantly hits every enemy on a straight line in front of the ship
+#[derive(Debug, Clone, Deserialize)]
+struct LaserPattern {
+    length: f32,
+    width: f32,
+    damage: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+enum AttackPattern {
+    Projectiles(ProjectilePattern),
+    Lightning(LightningPattern),
+    Laser(LaserPattern),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AttackDefinition {
+    typename: AttackType,
+    name: String,
+    name_abbreviation: String,
+    reload_time: f32,
+    ammo_consumption_on_shot: f32,
+    color: PaletteColor,
+    pattern: AttackPattern,
+}
+
+#[derive(Debug, Clone)]
 struct Attack {
     pub typename: AttackType,
-    pub name: &'static str,
-    pub name_abbreviation: &'static str,
+    pub name: String,
+    pub name_abbreviation: String,
     pub reload_time: f32,
     pub ammo_consumption_on_shot: f32,
     pub color: Color,
+    pub pattern: AttackPattern,
 }
 
+/// The launcher finds its data files in the `resources` folder of its working directory
+#[cfg(not(test))]
+const RESOURCES_DIRPATH_DEFAULT: &str = "resources";
+/// Tests run from the crate directory so they read the data files right from the assets
+#[cfg(test)]
+const RESOURCES_DIRPATH_DEFAULT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets_copy");
+
+const ATTACKS_FILENAME: &str = "attacks.json";
+const DIRECTOR_CONFIG_FILENAME: &str = "director.json";
+
 lazy_static! {
-    static ref ATTACKS: HashMap<AttackType, Attack> = {
-        let mut attacks = HashMap::new();
-        attacks.insert(
-            AttackType::Neutral,
-            Attack {
-                typename: AttackType::Neutral,
-                name: "Neutral",
-                name_abbreviation: "N",
-                reload_time: 0.24,
-                ammo_consumption_on_shot: 0.0,
-                color: COLOR_DEFAULT,
-            },
-        );
-        attacks.insert(
-            AttackType::Double,
-            Attack {
-                typename: AttackType::Double,
-                name: "Double",
-                name_abbreviation: "2",
-                reload_time: 0.32,
-                ammo_consumption_on_shot: 2.0,
-                color: COLOR_AMMO,
-            },
-        );
-        attacks.insert(
-            AttackType::Triple,
-            Attack {
-                typename: AttackType::Triple,
-                name: "Triple",
-                name_abbreviation: "3",
-                reload_time: 0.32,
-                ammo_consumption_on_shot: 3.0,
-                color: COLOR_BOOST,
-            },
-        );
-        attacks.insert(
-            AttackType::Rapid,
-            Attack {
-                typename: AttackType::Rapid,
-                name: "Rapid",
-                name_abbreviation: "R",
-                reload_time: 0.12,
-                ammo_consumption_on_shot: 1.0,
-                color: COLOR_DEFAULT,
-            },
-        );
-        attacks.insert(
-            AttackType::Spread,
-            Attack {
-                typename: AttackType::Spread,
-                name: "Spread",
-                name_abbreviation: "RS",
-                reload_time: 0.16,
-                ammo_consumption_on_shot: 1.0,
-                color: COLOR_DEFAULT,
-            },
-        );
-        attacks.insert(
-            AttackType::Back,
-            Attack {
-                typename: AttackType::Back,
-                name: "Back",
-                name_abbreviation: "Ba",
-                reload_time: 0.32,
-                ammo_consumption_on_shot: 2.0,
-                color: COLOR_SKILL_POINT,
-            },
-        );
-        attacks.insert(
-            AttackType::Side,
-            Attack {
-                typename: AttackType::Side,
-                name: "Side",
-                name_abbreviation: "Si",
-                reload_time: 0.32,
-                ammo_consumption_on_shot: 3.0,
-                color: COLOR_BOOST,
-            },
-        );
+    static ref RESOURCES_DIRPATH: Mutex<String> = Mutex::new(RESOURCES_DIRPATH_DEFAULT.to_owned());
+}
+
+/// Changes where the attacks and the director config are loaded from. Needs to be called before
+/// the first stage is created as both are only loaded once.
+pub fn set_resources_dirpath(dirpath: &str) {
+    *RESOURCES_DIRPATH.lock().unwrap() = dirpath.to_owned();
+}
+
+fn get_resource_filepath(filename: &str) -> String {
+    format!("{}/{}", RESOURCES_DIRPATH.lock().unwrap(), filename)
+}
+
+lazy_static! {
+    static ref DIRECTOR_CONFIG: DirectorConfig =
+        load_director_config_from_file(&get_resource_filepath(DIRECTOR_CONFIG_FILENAME))
+            .unwrap_or_else(|error| panic!("{}", error));
+}
+
+lazy_static! {
+    static ref ATTACKS: HashMap<AttackType, Attack> =
+        load_attacks_from_file(&get_resource_filepath(ATTACKS_FILENAME))
+            .unwrap_or_else(|error| panic!("{}", error));
+}
+
+fn load_attacks_from_file(filepath: &str) -> Result<HashMap<AttackType, Attack>, String> {
+    let content = std::fs::read_to_string(filepath)
+        .map_err(|error| format!("Could not read attacks '{}': {}", filepath, error))?;
+    let definitions: Vec<AttackDefinition> = serde_json::from_str(&content)
+        .map_err(|error| format!("Could not parse attacks '{}': {}", filepath, error))?;
+    create_attacks(definitions)
+        .map_err(|error| format!("Attacks '{}' are invalid:\n{}", filepath, error))
+}
+
+/// Returns all validation errors at once so that they can be fixed in one go
+fn create_attacks(
+    definitions: Vec<AttackDefinition>,
+) -> Result<HashMap<AttackType, Attack>, String> {
+    let mut errors = Vec::new();
+    let mut attacks = HashMap::new();
+
+    for definition in definitions {
+        let attacktype = definition.typename;
+        if attacks.contains_key(&attacktype) {
+            errors.push(format!("{:?}: is defined more than once", attacktype));
+        }
+        if definition.reload_time <= 0.0 {
+            errors.push(format!(
+                "{:?}: reload time {} must be positive",
+                attacktype, definition.reload_time
+            ));
+        }
+        if definition.ammo_consumption_on_shot < 0.0 {
+            errors.push(format!(
+                "{:?}: ammo consumption {} must not be negative",
+                attacktype, definition.ammo_consumption_on_shot
+            ));
+        }
+        match &definition.pattern {
+            AttackPattern::Projectiles(pattern) => {
+                if pattern.projectiles.iter().all(|spawn| spawn.count == 0) {
+                    errors.push(format!("{:?}: pattern has no projectiles", attacktype));
+                }
+                if pattern.speed - pattern.speed_jitter <= 0.0 || pattern.size <= 0.0 {
+                    errors.push(format!(
+                        "{:?}: projectile speed and size must be positive",
+                        attacktype
+                    ));
+                }
+                if pattern.lifetime.map_or(false, |lifetime| lifetime <= 0.0) {
+                    errors.push(format!(
+                        "{:?}: projectile lifetime must be positive",
+                        attacktype
+                    ));
+                }
+                if pattern.modifiers.explosion_radius < 0.0 {
+                    errors.push(format!(
+                        "{:?}: explosion radius must not be negative",
+                        attacktype
+                    ));
+                }
+            }
+            AttackPattern::Lightning(pattern) => {
+                if pattern.range <= 0.0 || pattern.chain_range <= 0.0 {
+                    errors.push(format!(
+                        "{:?}: lightning ranges must be positive",
+                        attacktype
+                    ));
+                }
+            }
+            AttackPattern::Laser(pattern) => {
+                if pattern.length <= 0.0 || pattern.width <= 0.0 {
+                    errors.push(format!(
+                        "{:?}: laser length and width must be positive",
+                        attacktype
+                    ));
+                }
+            }
+        }
+
         attacks.insert(
-            AttackType::Homing,
+            attacktype,
             Attack {
-                typename: AttackType::Homing,
-                name: "Homing",
-                name_abbreviation: "H",
-                reload_time: 0.56,
-                ammo_consumption_on_shot: 4.0,
-                color: COLOR_SKILL_POINT,
+                typename: attacktype,
+                name: definition.name,
+                name_abbreviation: definition.name_abbreviation,
+                reload_time: definition.reload_time,
+                ammo_consumption_on_shot: definition.ammo_consumption_on_shot,
+                color: definition.color.to_color(),
+                pattern: definition.pattern,
             },
         );
+    }
 
-        attacks
-    };
+    for attacktype in AttackType::iter() {
+        if !attacks.contains_key(&attacktype) {
+            errors.push(format!("{:?}: is missing", attacktype));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(attacks)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Returns the enemies a lightning bolt jumps to in the order they get hit
+fn find_lightning_targets(
+    world: &World,
+    start: Vec2,
+    pattern: &LightningPattern,
+) -> Vec<(Entity, Vec2)> {
+    let enemies: Vec<(Entity, Vec2)> = world
+        .query::<&Transform>()
+        .with::<Enemy>()
+        .iter()
+        .map(|(entity, xform)| (entity, xform.pos))
+        .collect();
+
+    let mut targets: Vec<(Entity, Vec2)> = Vec::new();
+    let mut pos = start;
+    let mut range = pattern.range;
+    while targets.len() <= pattern.chain_count {
+        let closest = enemies
+            .iter()
+            .filter(|(entity, _)| !targets.iter().any(|(target, _)| target == entity))
+            .map(|&(entity, enemy_pos)| (entity, enemy_pos, Vec2::distance_squared(pos, enemy_pos)))
+            .filter(|&(_, _, distance_squared)| distance_squared < squared(range))
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+
+        if let Some((entity, enemy_pos, _)) = closest {
+            targets.push((entity, enemy_pos));
+            pos = enemy_pos;
+            range = pattern.chain_range;
+        } else {
+            break;
+        }
+    }
+    targets
+}
+
+fn find_enemies_on_line(world: &World, start: Vec2, end: Vec2, width: f32) -> Vec<Entity> {
+    let line = end - start;
+    let line_length_squared = Vec2::dot(line, line);
+    world
+        .query::<(&Transform, &Enemy)>()
+        .iter()
+        .filter(|(_entity, (xform, enemy))| {
+            let percentage = clampf(
+                Vec2::dot(xform.pos - start, line) / line_length_squared,
+                0.0,
+                1.0,
+            );
+            let closest_point = start + percentage * line;
+            Vec2::distance_squared(xform.pos, closest_point) < squared(enemy.radius + width / 2.0)
+        })
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+/// Returns the enemies that overlap the given circle together with the distance of their edge to
+/// its center
+fn find_enemies_in_radius(world: &World, pos: Vec2, radius: f32) -> Vec<(Entity, f32)> {
+    world
+        .query::<(&Transform, &Enemy)>()
+        .iter()
+        .filter(|(_entity, (xform, enemy))| {
+            Vec2::distance_squared(xform.pos, pos) < squared(enemy.radius + radius)
+        })
+        .map(|(entity, (xform, enemy))| {
+            let distance = (xform.pos - pos).magnitude() - enemy.radius;
+            (entity, f32::max(distance, 0.0))
+        })
+        .collect()
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -220,14 +528,169 @@ lazy_static! {
 
 type Blinker = TimerStateSwitchBinary;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone)]
 struct Collider {
     radius: f32,
 
     layers_own: CollisionMask,
     layers_affects: CollisionMask,
+}
+
+/// Makes a `Collider` check its whole path of the last frame instead of only its end position so
+/// that fast and small entities can not tunnel through others
+#[derive(Debug, Copy, Clone)]
+struct ContinuousCollision {
+    pos_previous: Vec2,
+}
+
+/// The longest frame that swept collision needs to handle
+const CONTINUOUS_COLLISION_DELTATIME_MAX: f32 = 1.0 / 30.0;
+
+/// Gives the entity a `ContinuousCollision` if it can move further than its own radius within one
+/// frame. Slower entities can not tunnel and skip the more expensive swept test.
+fn add_continuous_collision_if_fast(
+    commands: &mut WorldCommandBuffer,
+    entity: Entity,
+    pos: Vec2,
+    speed: f32,
+    radius: f32,
+) {
+    if speed * CONTINUOUS_COLLISION_DELTATIME_MAX > radius {
+        commands.add_component(entity, ContinuousCollision { pos_previous: pos });
+    }
+}
+
+/// What a body is in a collision, given by its `layers_own`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum CollisionRole {
+    Enemy,
+    Player,
+    EnemyProjectile,
+    PlayerProjectile,
+    Collectible,
+    Hazard,
+}
+impl CollisionRole {
+    fn from_layers(layers_own: CollisionMask) -> Option<CollisionRole> {
+        match layers_own {
+            COLLISION_LAYER_ENEMY => Some(CollisionRole::Enemy),
+            COLLISION_LAYER_PLAYER => Some(CollisionRole::Player),
+            COLLISION_LAYER_ENEMY_PROJECTILE => Some(CollisionRole::EnemyProjectile),
+            COLLISION_LAYER_PLAYER_PROJECTILE => Some(CollisionRole::PlayerProjectile),
+            COLLISION_LAYER_COLLECTIBLES => Some(CollisionRole::Collectible),
+            COLLISION_LAYER_HAZARD => Some(CollisionRole::Hazard),
+            _ => None,
+        }
+    }
+}
+
+/// Gets emitted once per colliding pair of bodies
+///
+/// NOTE: Bodies on the enemy layer are not necessarily enemies (i.e. boss armor)
+#[derive(Debug, Copy, Clone)]
+enum CollisionEvent {
+    PlayerProjectileEnemyProjectile {
+        projectile: Entity,
+        enemy_projectile: Entity,
+    },
+    PlayerProjectileEnemy {
+        projectile: Entity,
+        enemy: Entity,
+    },
+    PlayerEnemyProjectile {
+        player: Entity,
+        projectile: Entity,
+    },
+    PlayerEnemy {
+        player: Entity,
+        enemy: Entity,
+    },
+    PlayerHazard {
+        player: Entity,
+        hazard: Entity,
+    },
+    PlayerCollectible {
+        player: Entity,
+        collectible: Entity,
+    },
+}
+impl CollisionEvent {
+    fn from_pair(
+        body_a: (Entity, CollisionRole),
+        body_b: (Entity, CollisionRole),
+    ) -> Option<CollisionEvent> {
+        // We sort the bodies by role so that we only need to handle one order per pair
+        let ((entity_a, role_a), (entity_b, role_b)) = if body_a.1 <= body_b.1 {
+            (body_a, body_b)
+        } else {
+            (body_b, body_a)
+        };
+        match (role_a, role_b) {
+            (CollisionRole::EnemyProjectile, CollisionRole::PlayerProjectile) => {
+                Some(CollisionEvent::PlayerProjectileEnemyProjectile {
+                    projectile: entity_b,
+                    enemy_projectile: entity_a,
+                })
+            }
+            (CollisionRole::Enemy, CollisionRole::PlayerProjectile) => {
+                Some(CollisionEvent::PlayerProjectileEnemy {
+                    projectile: entity_b,
+                    enemy: entity_a,
+                })
+            }
+            (CollisionRole::Player, CollisionRole::EnemyProjectile) => {
+                Some(CollisionEvent::PlayerEnemyProjectile {
+                    player: entity_a,
+                    projectile: entity_b,
+                })
+            }
+            (CollisionRole::Enemy, CollisionRole::Player) => Some(CollisionEvent::PlayerEnemy {
+                player: entity_b,
+                enemy: entity_a,
+            }),
+            (CollisionRole::Player, CollisionRole::Hazard) => Some(CollisionEvent::PlayerHazard {
+                player: entity_a,
+                hazard: entity_b,
+            }),
+            (CollisionRole::Player, CollisionRole::Collectible) => {
+                Some(CollisionEvent::PlayerCollectible {
+                    player: entity_a,
+                    collectible: entity_b,
+                })
+            }
+            _ => None,
+        }
+    }
 
-    collisions: Vec<Entity>,
+    /// Events get consumed in ascending order. Bullets that cancel each other out go first so
+    /// that a shot down enemy projectile can not hurt the player anymore.
+    fn priority(&self) -> usize {
+        match self {
+            CollisionEvent::PlayerProjectileEnemyProjectile { .. } => 0,
+            CollisionEvent::PlayerProjectileEnemy { .. } => 1,
+            CollisionEvent::PlayerEnemyProjectile { .. } => 2,
+            CollisionEvent::PlayerEnemy { .. } => 3,
+            CollisionEvent::PlayerHazard { .. } => 4,
+            CollisionEvent::PlayerCollectible { .. } => 5,
+        }
+    }
+
+    fn get_entities(&self) -> (Entity, Entity) {
+        match *self {
+            CollisionEvent::PlayerProjectileEnemyProjectile {
+                projectile,
+                enemy_projectile,
+            } => (projectile, enemy_projectile),
+            CollisionEvent::PlayerProjectileEnemy { projectile, enemy } => (projectile, enemy),
+            CollisionEvent::PlayerEnemyProjectile { player, projectile } => (player, projectile),
+            CollisionEvent::PlayerEnemy { player, enemy } => (player, enemy),
+            CollisionEvent::PlayerHazard { player, hazard } => (player, hazard),
+            CollisionEvent::PlayerCollectible {
+                player,
+                collectible,
+            } => (player, collectible),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -322,6 +785,22 @@ struct MoveTowardsTarget {
     pub follow_precision_percent: f32,
 }
 
+/// Wiggles the velocity sideways around `vel_base`
+#[derive(Debug, Copy, Clone)]
+struct SineMotion {
+    pub vel_base: Vec2,
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub time: f32,
+}
+
+/// Rotates the offset of a `SnapToParent` around its parent
+#[derive(Debug, Copy, Clone)]
+struct Orbit {
+    /// Given in degrees [-360, 360] counterclockwise
+    pub angle_vel: f32,
+}
+
 #[derive(Debug, Copy, Clone)]
 struct AutoremoveTimer {
     pub timer: TimerSimple,
@@ -359,33 +838,77 @@ impl AutoremoveTimerFrames {
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Primary Components
 
-#[derive(Debug, Copy, Clone)]
-pub struct PickupAction {
-    collectible_type: CollectibleType,
-    action: OneTimeAction,
-    chance: i32,
+/// Things that happen to the player which can trigger a `Proc`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcTrigger {
+    Pickup(CollectibleType),
+    /// Triggers on picking up any attack, regardless of its type
+    AttackPickup,
+    Kill,
+    CycleTick,
+    Hit,
+    BoostStart,
+}
+impl ProcTrigger {
+    fn to_string(self) -> String {
+        match self {
+            ProcTrigger::Pickup(collectible_type) => {
+                format!("on {} pickup", collectible_type.to_string())
+            }
+            ProcTrigger::AttackPickup => "on attack pickup".to_string(),
+            ProcTrigger::Kill => "on kill".to_string(),
+            ProcTrigger::CycleTick => "on cycle".to_string(),
+            ProcTrigger::Hit => "when hit".to_string(),
+            ProcTrigger::BoostStart => "on boost start".to_string(),
+        }
+    }
 }
-impl PickupAction {
+
+/// A percentage chance to perform an action whenever the trigger happens
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Proc {
+    pub trigger: ProcTrigger,
+    pub action: OneTimeAction,
+    pub chance: i32,
+}
+impl Proc {
     fn to_string(self) -> String {
         format!(
-            "{} percent chance to {} on {} pickup",
+            "{} percent chance to {} {}",
             self.chance,
             self.action.to_string(),
-            self.collectible_type.to_string()
+            self.trigger.to_string()
         )
     }
 }
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OneTimeAction {
     RegainHp,
     LaunchHomingProjectile,
+    SpawnHasteArea,
+    AttackSpeedBoost,
+    MovementSpeedBoost,
+    DamageBoost,
+    Invulnerability,
+    Barrage,
+    /// Spawns a collectible of the given attack. Must not be `AttackType::Neutral`.
+    SpawnAttack(AttackType),
 }
 impl OneTimeAction {
     fn to_string(self) -> String {
         match self {
             OneTimeAction::RegainHp => "Regain Hp".to_string(),
             OneTimeAction::LaunchHomingProjectile => "Launch Homing Projectile".to_string(),
+            OneTimeAction::SpawnHasteArea => "Spawn Haste Area".to_string(),
+            OneTimeAction::AttackSpeedBoost => "Gain Attack Speed Boost".to_string(),
+            OneTimeAction::MovementSpeedBoost => "Gain Movement Speed Boost".to_string(),
+            OneTimeAction::DamageBoost => "Gain Damage Boost".to_string(),
+            OneTimeAction::Invulnerability => "Become Invulnerable".to_string(),
+            OneTimeAction::Barrage => "Launch Barrage".to_string(),
+            OneTimeAction::SpawnAttack(attacktype) => {
+                format!("Spawn {} Attack", ATTACKS[&attacktype].name)
+            }
         }
     }
 
@@ -393,6 +916,13 @@ impl OneTimeAction {
         match self {
             OneTimeAction::RegainHp => "Hp Regain!".to_string(),
             OneTimeAction::LaunchHomingProjectile => "Homing Projectile!".to_string(),
+            OneTimeAction::SpawnHasteArea => "Haste Area!".to_string(),
+            OneTimeAction::AttackSpeedBoost => "Attack Speed Boost!".to_string(),
+            OneTimeAction::MovementSpeedBoost => "Movement Speed Boost!".to_string(),
+            OneTimeAction::DamageBoost => "Damage Boost!".to_string(),
+            OneTimeAction::Invulnerability => "Invulnerable!".to_string(),
+            OneTimeAction::Barrage => "Barrage!".to_string(),
+            OneTimeAction::SpawnAttack(attacktype) => format!("{}!", ATTACKS[&attacktype].name),
         }
     }
 
@@ -400,11 +930,18 @@ impl OneTimeAction {
         match self {
             OneTimeAction::RegainHp => COLOR_HP,
             OneTimeAction::LaunchHomingProjectile => COLOR_DEFAULT,
+            OneTimeAction::SpawnHasteArea => COLOR_AMMO,
+            OneTimeAction::AttackSpeedBoost => COLOR_AMMO,
+            OneTimeAction::MovementSpeedBoost => COLOR_BOOST,
+            OneTimeAction::DamageBoost => COLOR_HP,
+            OneTimeAction::Invulnerability => COLOR_DEFAULT,
+            OneTimeAction::Barrage => COLOR_DEFAULT,
+            OneTimeAction::SpawnAttack(attacktype) => ATTACKS[&attacktype].color,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum Skill {
     AddPercentageHP(i32),
     AddPercentageAmmo(i32),
@@ -415,7 +952,18 @@ pub enum Skill {
     AddHpGain(i32),
     AddAmmoGain(i32),
     AddBoostGain(i32),
-    PickupAction(PickupAction),
+    AddProjectilePierce(i32),
+    AddProjectileBounces(i32),
+    AddProjectileSplit(i32),
+    AddExplosionRadius(i32),
+    AddPercentageAttackSpeed(i32),
+    AddPercentageMovementSpeed(i32),
+    AddPercentageCycleSpeed(i32),
+    AddPercentageProjectileSpeed(i32),
+    AddPercentageProjectileSize(i32),
+    AddPercentageProjectileDuration(i32),
+    AddPercentageInvulnerabilityTime(i32),
+    Proc(Proc),
 }
 
 impl Skill {
@@ -430,11 +978,20 @@ impl Skill {
             Skill::AddHpGain(_) => "HP Gain".to_string(),
             Skill::AddAmmoGain(_) => "Ammo Gain".to_string(),
             Skill::AddBoostGain(_) => "Boost Gain".to_string(),
-            Skill::PickupAction(action) => format!(
-                "{} on {} pickup",
-                action.action.to_string(),
-                action.collectible_type.to_string()
-            ),
+            Skill::AddProjectilePierce(_) => "Pierce".to_string(),
+            Skill::AddProjectileBounces(_) => "Bounce".to_string(),
+            Skill::AddProjectileSplit(_) => "Split".to_string(),
+            Skill::AddExplosionRadius(_) => "Explosion".to_string(),
+            Skill::AddPercentageAttackSpeed(_) => "Attack Speed".to_string(),
+            Skill::AddPercentageMovementSpeed(_) => "Movement Speed".to_string(),
+            Skill::AddPercentageCycleSpeed(_) => "Cycle Speed".to_string(),
+            Skill::AddPercentageProjectileSpeed(_) => "Projectile Speed".to_string(),
+            Skill::AddPercentageProjectileSize(_) => "Projectile Size".to_string(),
+            Skill::AddPercentageProjectileDuration(_) => "Projectile Duration".to_string(),
+            Skill::AddPercentageInvulnerabilityTime(_) => "Invulnerability Time".to_string(),
+            Skill::Proc(proc) => {
+                format!("{} {}", proc.action.to_string(), proc.trigger.to_string())
+            }
         }
     }
     pub fn description(&self) -> String {
@@ -448,11 +1005,49 @@ impl Skill {
             Skill::AddHpGain(value) => format!("+{} HP Gain", value),
             Skill::AddAmmoGain(value) => format!("+{} Ammo Gain", value),
             Skill::AddBoostGain(value) => format!("+{} Boost Gain", value),
-            Skill::PickupAction(action) => format!(
-                "{} on {} pickup",
-                action.action.to_string(),
-                action.collectible_type.to_string()
-            ),
+            Skill::AddProjectilePierce(value) => format!("Projectiles pierce +{} enemies", value),
+            Skill::AddProjectileBounces(value) => format!("Projectiles bounce +{} times", value),
+            Skill::AddProjectileSplit(value) => format!("Projectiles split into +{} on hit", value),
+            Skill::AddExplosionRadius(value) => format!("+{} Explosion radius on hit", value),
+            Skill::AddPercentageAttackSpeed(value) => format!("+{}% Attack Speed", value),
+            Skill::AddPercentageMovementSpeed(value) => format!("+{}% Movement Speed", value),
+            Skill::AddPercentageCycleSpeed(value) => format!("+{}% Cycle Speed", value),
+            Skill::AddPercentageProjectileSpeed(value) => format!("+{}% Projectile Speed", value),
+            Skill::AddPercentageProjectileSize(value) => format!("+{}% Projectile Size", value),
+            Skill::AddPercentageProjectileDuration(value) => {
+                format!("+{}% Projectile Duration", value)
+            }
+            Skill::AddPercentageInvulnerabilityTime(value) => {
+                format!("+{}% Invulnerability Time", value)
+            }
+            Skill::Proc(proc) => proc.to_string(),
+        }
+    }
+
+    /// The amount that the skill adds. Procs have a chance instead.
+    pub fn value(&self) -> Option<i32> {
+        match *self {
+            Skill::AddPercentageHP(value)
+            | Skill::AddPercentageAmmo(value)
+            | Skill::AddPercentageBoost(value)
+            | Skill::AddHp(value)
+            | Skill::AddAmmo(value)
+            | Skill::AddBoost(value)
+            | Skill::AddHpGain(value)
+            | Skill::AddAmmoGain(value)
+            | Skill::AddBoostGain(value)
+            | Skill::AddProjectilePierce(value)
+            | Skill::AddProjectileBounces(value)
+            | Skill::AddProjectileSplit(value)
+            | Skill::AddExplosionRadius(value)
+            | Skill::AddPercentageAttackSpeed(value)
+            | Skill::AddPercentageMovementSpeed(value)
+            | Skill::AddPercentageCycleSpeed(value)
+            | Skill::AddPercentageProjectileSpeed(value)
+            | Skill::AddPercentageProjectileSize(value)
+            | Skill::AddPercentageProjectileDuration(value)
+            | Skill::AddPercentageInvulnerabilityTime(value) => Some(value),
+            Skill::Proc(_) => None,
         }
     }
 }
@@ -496,33 +1091,40 @@ struct Player {
     pub cycle_cooldown: f32,
     pub cycle_timer: TimerSimple,
 
-    action_on_pickup_chances: Vec<PickupAction>,
+    pub attack_speed_multiplier: f32,
+    pub movement_speed_multiplier: f32,
+    pub projectile_speed_multiplier: f32,
+    pub projectile_size_multiplier: f32,
+    pub projectile_duration_multiplier: f32,
+
+    /// Needed for detecting the start of a boost
+    pub boost_was_active: bool,
+
+    procs: Vec<Proc>,
+
+    /// Added on top of the modifiers of the current attack
+    projectile_modifiers: ProjectileModifiers,
 }
 
 impl Player {
     fn new(size: f32, ship_type: ShipType, skills: &[Skill]) -> Player {
-        let action_on_pickup_chances = {
-            let mut pairs_percentages = HashMap::new();
+        let ship_stats = get_stats_for_ship(ship_type);
+
+        // NOTE: Procs roll for their chance in this order so it must not depend on anything other
+        //       than the order of the skills. Otherwise replays would desync.
+        let procs = {
+            let mut result: Vec<Proc> = Vec::new();
             for skill in skills {
-                match skill {
-                    Skill::PickupAction(action) => {
-                        let entry = pairs_percentages
-                            .entry((action.collectible_type, action.action))
-                            .or_insert(0);
-                        *entry += action.chance;
+                if let Skill::Proc(proc) = skill {
+                    let existing = result.iter_mut().find(|existing| {
+                        existing.trigger == proc.trigger && existing.action == proc.action
+                    });
+                    match existing {
+                        Some(existing) => existing.chance += proc.chance,
+                        None => result.push(*proc),
                     }
-                    _ => {}
                 }
             }
-
-            let mut result = Vec::new();
-            for ((collectible_type, action), chance) in pairs_percentages {
-                result.push(PickupAction {
-                    collectible_type,
-                    action,
-                    chance,
-                });
-            }
             result
         };
 
@@ -570,37 +1172,123 @@ impl Player {
             }
 
             (
-                flat_hp * multiplier_hp,
+                flat_hp * multiplier_hp * ship_stats.hp_multiplier,
                 gain_hp,
-                flat_ammo * multiplier_ammo,
+                flat_ammo * multiplier_ammo * ship_stats.ammo_multiplier,
                 gain_ammo,
-                flat_boost * multiplier_boost,
+                flat_boost * multiplier_boost * ship_stats.boost_multiplier,
                 gain_boost,
             )
         };
 
-        let attack = ATTACKS[&AttackType::Neutral];
+        let projectile_modifiers = {
+            let mut split_count = 0;
+            let mut result = ProjectileModifiers::default();
+            for skill in skills {
+                match skill {
+                    Skill::AddProjectilePierce(value) => {
+                        result.pierce += *value as usize;
+                    }
+                    Skill::AddProjectileBounces(value) => {
+                        result.bounces += *value as usize;
+                    }
+                    Skill::AddProjectileSplit(value) => {
+                        split_count += *value as usize;
+                    }
+                    Skill::AddExplosionRadius(value) => {
+                        result.explosion_radius += *value as f32;
+                    }
+                    _ => {}
+                }
+            }
+
+            // Spread the split projectiles evenly over the forward facing half circle
+            result.split_angles = (0..split_count)
+                .map(|index| -90.0 + 180.0 * (index as f32 + 0.5) / split_count as f32)
+                .collect();
+            result
+        };
+
+        let (
+            attack_speed_multiplier,
+            movement_speed_multiplier,
+            cycle_speed_multiplier,
+            projectile_speed_multiplier,
+            projectile_size_multiplier,
+            projectile_duration_multiplier,
+            invulnerability_time_multiplier,
+        ) = {
+            let mut attack_speed = ship_stats.attack_speed_multiplier;
+            let mut movement_speed = 1.0;
+            let mut cycle_speed = ship_stats.cycle_speed_multiplier;
+            let mut projectile_speed = 1.0;
+            let mut projectile_size = 1.0;
+            let mut projectile_duration = 1.0;
+            let mut invulnerability_time = 1.0;
+            for skill in skills {
+                match skill {
+                    Skill::AddPercentageAttackSpeed(value) => {
+                        attack_speed += (*value as f32) / 100.0;
+                    }
+                    Skill::AddPercentageMovementSpeed(value) => {
+                        movement_speed += (*value as f32) / 100.0;
+                    }
+                    Skill::AddPercentageCycleSpeed(value) => {
+                        cycle_speed += (*value as f32) / 100.0;
+                    }
+                    Skill::AddPercentageProjectileSpeed(value) => {
+                        projectile_speed += (*value as f32) / 100.0;
+                    }
+                    Skill::AddPercentageProjectileSize(value) => {
+                        projectile_size += (*value as f32) / 100.0;
+                    }
+                    Skill::AddPercentageProjectileDuration(value) => {
+                        projectile_duration += (*value as f32) / 100.0;
+                    }
+                    Skill::AddPercentageInvulnerabilityTime(value) => {
+                        invulnerability_time += (*value as f32) / 100.0;
+                    }
+                    _ => {}
+                }
+            }
+
+            (
+                f32::max(attack_speed, PLAYER_MULTIPLIER_MIN),
+                f32::max(movement_speed, PLAYER_MULTIPLIER_MIN),
+                f32::max(cycle_speed, PLAYER_MULTIPLIER_MIN),
+                f32::max(projectile_speed, PLAYER_MULTIPLIER_MIN),
+                f32::max(projectile_size, PLAYER_MULTIPLIER_MIN),
+                f32::max(projectile_duration, PLAYER_MULTIPLIER_MIN),
+                f32::max(invulnerability_time, PLAYER_MULTIPLIER_MIN),
+            )
+        };
+        let cycle_cooldown = PLAYER_BASE_CYCLE_COOLDOWN / cycle_speed_multiplier;
+
+        let attack = ATTACKS[&AttackType::Neutral].clone();
+        let reload_timer = TriggerRepeating::new(attack.reload_time);
         Player {
             attack,
             timer_trail_particles: TriggerRepeating::new(0.01),
             ship_type,
             speed: 0.0,
 
-            speed_max: 100.0,
+            speed_max: ship_stats.speed,
 
-            speed_base_max: 100.0,
-            acc: 100.0,
-            turn_speed: 1.66 * 180.0,
+            speed_base_max: ship_stats.speed,
+            acc: ship_stats.acc,
+            turn_speed: ship_stats.turn_speed,
 
             size,
 
-            reload_timer: TriggerRepeating::new(attack.reload_time),
+            reload_timer,
 
             hp,
             hp_max: hp,
             hp_gain,
 
-            invincible_timer: TimerSimple::new_stopped(2.0),
+            invincible_timer: TimerSimple::new_stopped(
+                PLAYER_BASE_INVULNERABILITY_TIME * invulnerability_time_multiplier,
+            ),
 
             ammo,
             ammo_max: ammo,
@@ -613,10 +1301,20 @@ impl Player {
             boost_allowed: true,
             boost_cooldown_time: 2.0,
             boost_cooldown_timer: TimerSimple::new_stopped(1.0),
-            cycle_cooldown: 5.0,
-            cycle_timer: TimerSimple::new_started(5.0),
+            cycle_cooldown,
+            cycle_timer: TimerSimple::new_started(cycle_cooldown),
+
+            attack_speed_multiplier,
+            movement_speed_multiplier,
+            projectile_speed_multiplier,
+            projectile_size_multiplier,
+            projectile_duration_multiplier,
+
+            boost_was_active: false,
 
-            action_on_pickup_chances,
+            procs,
+
+            projectile_modifiers,
         }
     }
 
@@ -637,21 +1335,213 @@ impl Player {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-struct Enemy {
-    hp: f32,
-    hp_max: f32,
-    hitflash_timer: TimerSimple,
-    radius: f32,
-    can_shoot: bool,
-    is_charging: bool,
-    timer_shoot: TimerSimple,
-    timer_charge: TriggerRepeating,
-    score: usize,
+/// Additive bonuses on top of the multipliers of the player, i.e. 0.5 means +50%
+#[derive(Debug, Copy, Clone, Default)]
+struct StatModifiers {
+    pub attack_speed: f32,
+    pub movement_speed: f32,
+    pub damage: f32,
+    pub projectile_speed: f32,
+    pub boost_drain: f32,
+}
+
+/// What happens when a buff gets applied while it is still active
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BuffStacking {
+    /// Restarts the duration
+    Refresh,
+    /// Adds a stack and restarts the duration. Every stack applies the modifiers once more.
+    Stack { stacks_max: usize },
+    /// Keeps the active buff as it is
+    Ignore,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BuffType {
+    HasteArea,
+    AttackSpeedBoost,
+    MovementSpeedBoost,
+    DamageBoost,
+}
+
+struct BuffDefinition {
+    duration: f32,
+    stacking: BuffStacking,
+    modifiers_per_stack: StatModifiers,
+    name_abbreviation: &'static str,
+    color: Color,
+}
+
+impl BuffType {
+    fn get_definition(self) -> BuffDefinition {
+        match self {
+            // NOTE: The haste area refreshes this every frame while the player stands inside so it
+            //       runs out shortly after leaving
+            BuffType::HasteArea => BuffDefinition {
+                duration: 0.1,
+                stacking: BuffStacking::Refresh,
+                modifiers_per_stack: StatModifiers {
+                    attack_speed: 1.0,
+                    movement_speed: 0.5,
+                    ..StatModifiers::default()
+                },
+                name_abbreviation: "H",
+                color: COLOR_AMMO,
+            },
+            BuffType::AttackSpeedBoost => BuffDefinition {
+                duration: 4.0,
+                stacking: BuffStacking::Stack { stacks_max: 3 },
+                modifiers_per_stack: StatModifiers {
+                    attack_speed: 0.5,
+                    ..StatModifiers::default()
+                },
+                name_abbreviation: "AS",
+                color: COLOR_AMMO,
+            },
+            BuffType::MovementSpeedBoost => BuffDefinition {
+                duration: 4.0,
+                stacking: BuffStacking::Refresh,
+                modifiers_per_stack: StatModifiers {
+                    movement_speed: 0.5,
+                    boost_drain: -0.5,
+                    ..StatModifiers::default()
+                },
+                name_abbreviation: "MS",
+                color: COLOR_BOOST,
+            },
+            BuffType::DamageBoost => BuffDefinition {
+                duration: 6.0,
+                stacking: BuffStacking::Ignore,
+                modifiers_per_stack: StatModifiers {
+                    damage: 0.5,
+                    projectile_speed: 0.25,
+                    ..StatModifiers::default()
+                },
+                name_abbreviation: "D",
+                color: COLOR_HP,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Buff {
+    pub bufftype: BuffType,
+    pub stacks: usize,
+    pub timer: TimerSimple,
+}
+
+/// Temporary stat changes. Permanent ones are folded into the `Player` on creation.
+#[derive(Debug, Clone)]
+struct Buffs {
+    pub active: Vec<Buff>,
+}
+
+impl Buffs {
+    fn new() -> Buffs {
+        Buffs { active: Vec::new() }
+    }
+
+    fn add(&mut self, bufftype: BuffType) {
+        let definition = bufftype.get_definition();
+        if let Some(buff) = self
+            .active
+            .iter_mut()
+            .find(|buff| buff.bufftype == bufftype)
+        {
+            match definition.stacking {
+                BuffStacking::Refresh => buff.timer.restart(),
+                BuffStacking::Stack { stacks_max } => {
+                    buff.stacks = usize::min(buff.stacks + 1, stacks_max);
+                    buff.timer.restart();
+                }
+                BuffStacking::Ignore => {}
+            }
+        } else {
+            self.active.push(Buff {
+                bufftype,
+                stacks: 1,
+                timer: TimerSimple::new_started(definition.duration),
+            });
+        }
+    }
+
+    fn update(&mut self, deltatime: f32) {
+        for buff in &mut self.active {
+            buff.timer.update(deltatime);
+        }
+        self.active.retain(|buff| buff.timer.is_running());
+    }
+
+    fn get_stat_modifiers(&self) -> StatModifiers {
+        let mut result = StatModifiers::default();
+        for buff in &self.active {
+            let modifiers = buff.bufftype.get_definition().modifiers_per_stack;
+            let stacks = buff.stacks as f32;
+            result.attack_speed += stacks * modifiers.attack_speed;
+            result.movement_speed += stacks * modifiers.movement_speed;
+            result.damage += stacks * modifiers.damage;
+            result.projectile_speed += stacks * modifiers.projectile_speed;
+            result.boost_drain += stacks * modifiers.boost_drain;
+        }
+        result
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Enemy {
+    hp: f32,
+    hp_max: f32,
+    hitflash_timer: TimerSimple,
+    radius: f32,
+    can_shoot: bool,
+    is_charging: bool,
+    timer_shoot: TimerSimple,
+    timer_charge: TriggerRepeating,
+    score: usize,
+    enemytype: EnemyType,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum BossAttack {
+    AimedBurst,
+    Ring,
+    WavesAndMinion,
+}
+
+/// NOTE: `phase` starts at 1 and increases as the boss loses hp
+#[derive(Debug, Copy, Clone)]
+struct Boss {
+    pub phase: usize,
+    pub timer_attack: TriggerRepeating,
+    pub attack_count: usize,
+    pub time: f32,
+}
+
+/// Blocks player projectiles for the boss it is snapped to
+#[derive(Debug, Copy, Clone)]
+struct BossArmor;
+
+#[derive(Debug, Copy, Clone)]
+struct TrailEmitter {
+    pub timer_segment: TriggerRepeating,
+}
+
+/// Damages the player on contact
+#[derive(Debug, Copy, Clone)]
+struct Hazard {
+    pub damage: f32,
+}
+
+/// Bounces off other rigid bodies. Position, velocity and angle get synced with the `Transform`
+/// and `Motion` of the entity every frame.
+#[derive(Debug, Copy, Clone)]
+struct RigidBody {
+    pub body: Body,
 }
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
-enum CollectibleType {
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CollectibleType {
     Boost,
     Ammo,
     Hp,
@@ -675,7 +1565,7 @@ impl CollectibleType {
             CollectibleType::Ammo => "+AMMO",
             CollectibleType::Hp => "+HP",
             CollectibleType::Skillpoint => "+1 SP",
-            CollectibleType::Attack(attacktype) => ATTACKS[attacktype].name,
+            CollectibleType::Attack(attacktype) => &ATTACKS[attacktype].name,
         }
     }
 }
@@ -687,6 +1577,11 @@ struct Collectible {
     size: f32,
 }
 
+#[derive(Debug, Copy, Clone)]
+struct HasteArea {
+    pub radius: f32,
+}
+
 #[derive(Debug, Copy, Clone)]
 struct TickEffect {
     pub timer_tween: TimerSimple,
@@ -694,13 +1589,19 @@ struct TickEffect {
     pub height: f32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 struct Projectile {
     pub size: f32,
     pub color: Color,
     pub damage: f32,
 
     pub timer_trail_particles: TriggerRepeating,
+
+    pub spin_speed: f32,
+    /// NOTE: `pierce` and `bounces` count down while the projectile is alive
+    pub modifiers: ProjectileModifiers,
+    /// Every enemy gets damaged at most once by the same projectile and its split children
+    pub enemies_hit: Vec<Entity>,
 }
 impl Projectile {
     fn new(size: f32, color: Color, damage: f32, has_trail: bool) -> Projectile {
@@ -714,6 +1615,10 @@ impl Projectile {
             } else {
                 TriggerRepeating::new(std::f32::MAX)
             },
+
+            spin_speed: 0.0,
+            modifiers: ProjectileModifiers::default(),
+            enemies_hit: Vec::new(),
         }
     }
 }
@@ -786,7 +1691,7 @@ struct DrawableMulti {
 fn draw_drawable(
     fonts: &HashMap<String, SpriteFont>,
     draw: &mut Drawstate,
-    globals: &mut Globals,
+    random: &mut Random,
     xform: &Transform,
     drawable: &Drawable,
 ) {
@@ -863,7 +1768,7 @@ fn draw_drawable(
         MeshType::Linestrips(linestrips) => {
             for linestrip_raw in linestrips {
                 let jitter = if drawable.add_jitter {
-                    Some(&mut globals.random)
+                    Some(&mut *random)
                 } else {
                     None
                 };
@@ -880,7 +1785,7 @@ fn draw_drawable(
         }
         MeshType::Linestrip(linestrip_raw) => {
             let jitter = if drawable.add_jitter {
-                Some(&mut globals.random)
+                Some(&mut *random)
             } else {
                 None
             };
@@ -1018,6 +1923,7 @@ struct InfoText {
     pub pos: Vec2,
     pub timer: TimerSimple,
     pub blinker: Blinker,
+    pub visible: bool,
     pub char_switcher: TriggerRepeating,
     pub color: Color,
     pub text: Vec<char>,
@@ -1035,6 +1941,7 @@ impl InfoText {
             pos,
             timer: TimerSimple::new_started(1.1),
             blinker: Blinker::new(true, 0.7, 0.05),
+            visible: true,
             char_switcher: TriggerRepeating::new_with_distinct_triggertimes(0.7, 0.035),
             color,
             text,
@@ -1043,17 +1950,9 @@ impl InfoText {
         }
     }
 
-    fn update_and_check_if_finished(
-        &mut self,
-        draw: &mut Drawstate,
-        random: &mut Random,
-        gui_font: &SpriteFont,
-        deltatime: f32,
-    ) -> bool {
+    fn update_and_check_if_finished(&mut self, random: &mut Random, deltatime: f32) -> bool {
         self.timer.update(deltatime);
 
-        let mut text_offset = Vec2::zero();
-
         // Change text characters and colors randomly
         if self.char_switcher.update_and_check(deltatime) {
             let random_ascii_chars = " 0123456789!@#$%&*()-=+[]^~/;?><.,|abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWYXZ".as_bytes();
@@ -1078,9 +1977,14 @@ impl InfoText {
             }
         }
 
-        // Draw text
-        let visible = self.blinker.update_and_check(deltatime);
-        if visible {
+        self.visible = self.blinker.update_and_check(deltatime);
+
+        self.timer.is_finished()
+    }
+
+    fn draw(&self, draw: &mut Drawstate, gui_font: &SpriteFont) {
+        let mut text_offset = Vec2::zero();
+        if self.visible {
             for (index, &character) in self.text.iter().enumerate() {
                 text_offset = draw.draw_text(
                     &character.to_string(),
@@ -1096,8 +2000,6 @@ impl InfoText {
                 )
             }
         }
-
-        self.timer.is_finished()
     }
 }
 
@@ -1111,7 +2013,7 @@ impl Archetypes {
         pos: Vec2,
         ship_type: ShipType,
         skills: &[Skill],
-    ) -> (Transform, Motion, Drawable, Player, Collider) {
+    ) -> (Transform, Motion, Drawable, Player, Buffs, Collider) {
         let player_size = 12.0;
         (
             Transform {
@@ -1136,12 +2038,11 @@ impl Archetypes {
                 visible: true,
             },
             Player::new(player_size, ship_type, skills),
+            Buffs::new(),
             Collider {
                 radius: player_size,
                 layers_own: COLLISION_LAYER_PLAYER,
                 layers_affects: COLLISION_LAYER_COLLECTIBLES,
-
-                collisions: Vec::with_capacity(32),
             },
         )
     }
@@ -1258,10 +2159,10 @@ impl Archetypes {
         pos: Vec2,
         dir: Vec2,
         speed: f32,
-        length: f32,
-        color: Color,
-        damage: f32,
+        projectile: Projectile,
     ) -> (Transform, Motion, Projectile, Collider, DrawableMulti) {
+        let length = projectile.size;
+        let color = projectile.color;
         (
             Transform {
                 pos,
@@ -1273,12 +2174,11 @@ impl Archetypes {
                 dir_angle_vel: 0.0,
                 dir_angle_acc: 0.0,
             },
-            Projectile::new(length, color, damage, false),
+            projectile,
             Collider {
                 radius: length,
                 layers_own: COLLISION_LAYER_PLAYER_PROJECTILE,
                 layers_affects: COLLISION_LAYER_ENEMY | COLLISION_LAYER_ENEMY_PROJECTILE,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![
@@ -1323,9 +2223,7 @@ impl Archetypes {
         pos: Vec2,
         dir: Vec2,
         speed: f32,
-        size: f32,
-        color: Color,
-        damage: f32,
+        projectile: Projectile,
     ) -> (
         Transform,
         Motion,
@@ -1334,6 +2232,8 @@ impl Archetypes {
         Collider,
         DrawableMulti,
     ) {
+        let size = projectile.size;
+        let color = projectile.color;
         (
             Transform {
                 pos,
@@ -1349,12 +2249,11 @@ impl Archetypes {
                 target: Entity::from_bits(std::u64::MAX),
                 follow_precision_percent: 0.1,
             },
-            Projectile::new(size, color, damage, true),
+            projectile,
             Collider {
                 radius: size,
                 layers_own: COLLISION_LAYER_PLAYER_PROJECTILE,
                 layers_affects: COLLISION_LAYER_ENEMY | COLLISION_LAYER_ENEMY_PROJECTILE,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![
@@ -1416,7 +2315,6 @@ impl Archetypes {
                 radius: length,
                 layers_own: COLLISION_LAYER_ENEMY_PROJECTILE,
                 layers_affects: COLLISION_LAYER_PLAYER | COLLISION_LAYER_PLAYER_PROJECTILE,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![Drawable {
@@ -1475,7 +2373,6 @@ impl Archetypes {
                 radius: size,
                 layers_own: COLLISION_LAYER_COLLECTIBLES,
                 layers_affects: 0,
-                collisions: Vec::with_capacity(32),
             },
             Drawable {
                 mesh: MeshType::RectangleTransformed {
@@ -1520,7 +2417,6 @@ impl Archetypes {
                 radius: size,
                 layers_own: COLLISION_LAYER_COLLECTIBLES,
                 layers_affects: 0,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![
@@ -1585,7 +2481,6 @@ impl Archetypes {
                 radius: size,
                 layers_own: COLLISION_LAYER_COLLECTIBLES,
                 layers_affects: 0,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![
@@ -1632,7 +2527,7 @@ impl Archetypes {
         attacktype: AttackType,
     ) -> (Transform, Motion, Collectible, Collider, DrawableMulti) {
         assert!(attacktype != AttackType::Neutral);
-        let attack = ATTACKS[&attacktype];
+        let attack = &ATTACKS[&attacktype];
 
         let font_name = "gui_font".to_owned();
         let label = attack.name_abbreviation.to_owned();
@@ -1658,7 +2553,6 @@ impl Archetypes {
                 radius: size,
                 layers_own: COLLISION_LAYER_COLLECTIBLES,
                 layers_affects: 0,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![
@@ -1746,7 +2640,6 @@ impl Archetypes {
                 radius: size,
                 layers_own: COLLISION_LAYER_COLLECTIBLES,
                 layers_affects: 0,
-                collisions: Vec::with_capacity(32),
             },
             DrawableMulti {
                 drawables: vec![
@@ -1877,6 +2770,101 @@ impl Archetypes {
         )
     }
 
+    fn new_haste_area(pos: Vec2) -> (Transform, AutoremoveTimer, HasteArea, TweenScale, Drawable) {
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            AutoremoveTimer::new(HASTE_AREA_LIFETIME),
+            HasteArea {
+                radius: HASTE_AREA_RADIUS,
+            },
+            TweenScale::new(0.0, 1.0, 0.15, EasingType::CubicInOut),
+            Drawable {
+                mesh: MeshType::Circle {
+                    radius: HASTE_AREA_RADIUS,
+                    filled: false,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::zero(),
+                color: COLOR_AMMO,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_EFFECTS,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_lightning_effect(
+        start: Vec2,
+        end: Vec2,
+        color: Color,
+    ) -> (Transform, AutoremoveTimer, TweenColor, Drawable) {
+        let lifetime = 0.15;
+
+        // NOTE: More vertices make the jitter look more like a lightning bolt
+        let segment_count = usize::max(1, ((end - start).magnitude() / 8.0) as usize);
+        let linestrip: Vec<Vec2> = (0..=segment_count)
+            .map(|index| (end - start) * (index as f32 / segment_count as f32))
+            .collect();
+        (
+            Transform {
+                pos: start,
+                dir_angle: 0.0,
+            },
+            AutoremoveTimer::new(lifetime),
+            TweenColor::new(COLOR_DEFAULT, color, 0.05, EasingType::StepEnd),
+            Drawable {
+                mesh: MeshType::Linestrip(linestrip),
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_DEFAULT,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_EFFECTS,
+                add_jitter: true,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_laser_effect(
+        pos: Vec2,
+        dir: Vec2,
+        length: f32,
+        width: f32,
+        color: Color,
+    ) -> (Transform, AutoremoveTimer, TweenColor, Drawable) {
+        let lifetime = 0.2;
+        (
+            Transform {
+                pos,
+                dir_angle: rad_to_deg(dir.to_angle_flipped_y()),
+            },
+            AutoremoveTimer::new(lifetime),
+            TweenColor::new(COLOR_DEFAULT, color, 0.05, EasingType::StepEnd),
+            Drawable {
+                mesh: MeshType::LineWithThickness {
+                    length,
+                    thickness: width,
+                    smooth_edges: false,
+                    centered: false,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_DEFAULT,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_EFFECTS,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
     fn new_explode_particle(
         pos: Vec2,
         dir_angle: f32,
@@ -1983,7 +2971,7 @@ impl Archetypes {
         pos: Vec2,
         vel: Vec2,
         radius: f32,
-    ) -> (Transform, Motion, Collider, Enemy, Drawable) {
+    ) -> (Transform, Motion, Collider, Enemy, RigidBody, Drawable) {
         fn create_irregular_polygon(
             random: &mut Random,
             vertex_count: usize,
@@ -2012,7 +3000,9 @@ impl Archetypes {
 
         let dir_angle = random.f32_in_range_closed(0.0, 360.0);
         let dir_angle_vel = random.f32_in_range_closed(-360.0, 360.0);
-        let linestrip = create_irregular_polygon(random, 8, 10.0);
+        let linestrip = create_irregular_polygon(random, 8, 1.25 * radius);
+        let shape = Shape::Disk { radius };
+        let mass_data = shape.compute_mass(ROCK_DENSITY);
         (
             Transform { pos, dir_angle },
             Motion {
@@ -2025,7 +3015,6 @@ impl Archetypes {
                 radius,
                 layers_own: COLLISION_LAYER_ENEMY,
                 layers_affects: COLLISION_LAYER_PLAYER,
-                collisions: Vec::with_capacity(32),
             },
             Enemy {
                 hp: 100.0,
@@ -2037,6 +3026,18 @@ impl Archetypes {
                 timer_shoot: TimerSimple::new_stopped(1.0),
                 timer_charge: TriggerRepeating::new(1.0),
                 score: 100,
+                enemytype: EnemyType::Rock,
+            },
+            RigidBody {
+                body: Body::new(
+                    RIGIDBODY_LAYER_ROCKS,
+                    RIGIDBODY_LAYER_ROCKS,
+                    pos,
+                    dir_angle,
+                    shape,
+                    mass_data,
+                    ROCK_BOUNCYNESS,
+                ),
             },
             Drawable {
                 mesh: MeshType::Linestrip(linestrip),
@@ -2076,7 +3077,6 @@ impl Archetypes {
                 radius,
                 layers_own: COLLISION_LAYER_ENEMY,
                 layers_affects: COLLISION_LAYER_PLAYER,
-                collisions: Vec::with_capacity(32),
             },
             Enemy {
                 hp: 100.0,
@@ -2088,6 +3088,7 @@ impl Archetypes {
                 timer_shoot: TimerSimple::new_started(5.0),
                 timer_charge: TriggerRepeating::new_with_distinct_triggertimes(4.0, 0.02),
                 score: 150,
+                enemytype: EnemyType::Shooter,
             },
             Drawable {
                 mesh: MeshType::Linestrip(linestrip),
@@ -2102,40 +3103,620 @@ impl Archetypes {
             },
         )
     }
-}
 
-////////////////////////////////////////////////////////////////////////////////////////////////////
-// World command buffer
-
-struct WorldCommandBuffer {
-    commands: Vec<Box<dyn FnOnce(&mut World) + Send + Sync>>,
-}
-
-impl WorldCommandBuffer {
-    fn new() -> WorldCommandBuffer {
-        WorldCommandBuffer {
-            commands: Vec::new(),
-        }
+    fn new_enemy_big_rock(
+        random: &mut Random,
+        pos: Vec2,
+        vel: Vec2,
+    ) -> (Transform, Motion, Collider, Enemy, RigidBody, Drawable) {
+        let (xform, motion, collider, mut enemy, rigidbody, drawable) =
+            Archetypes::new_enemy_rock(random, pos, 0.5 * vel, 16.0);
+        enemy.hp = 300.0;
+        enemy.hp_max = 300.0;
+        enemy.score = 300;
+        enemy.enemytype = EnemyType::BigRock;
+        (xform, motion, collider, enemy, rigidbody, drawable)
     }
 
-    fn add_entity<ComponentsBundleType>(&mut self, components: ComponentsBundleType)
-    where
-        ComponentsBundleType: hecs::DynamicBundle + Send + Sync + 'static,
-    {
-        self.commands.push(Box::new(move |world| {
-            world.spawn(components);
-        }));
+    fn new_enemy_waver(
+        pos: Vec2,
+        vel: Vec2,
+    ) -> (Transform, Motion, Collider, Enemy, SineMotion, Drawable) {
+        let width = 10.0;
+        let height = 8.0;
+        let radius = f32::max(width, height);
+        let dir_angle = if vel.x >= 0.0 { 0.0 } else { -180.0 };
+        let linestrip = vec![
+            Vec2::new(width, 0.0),
+            Vec2::new(0.0, height),
+            Vec2::new(-width, 0.0),
+            Vec2::new(0.0, -height),
+            Vec2::new(width, 0.0),
+        ];
+        (
+            Transform { pos, dir_angle },
+            Motion {
+                vel,
+                acc: Vec2::zero(),
+                dir_angle_vel: 0.0,
+                dir_angle_acc: 0.0,
+            },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: 70.0,
+                hp_max: 70.0,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: true,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_started(4.0),
+                timer_charge: TriggerRepeating::new_with_distinct_triggertimes(3.0, 0.02),
+                score: 200,
+                enemytype: EnemyType::Waver,
+            },
+            SineMotion {
+                vel_base: vel,
+                amplitude: 40.0,
+                frequency: 0.5,
+                time: 0.0,
+            },
+            Drawable {
+                mesh: MeshType::Linestrip(linestrip),
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
     }
 
-    fn add_component<ComponentType>(&mut self, entity: Entity, component: ComponentType)
-    where
-        ComponentType: Send + Sync + 'static,
-    {
-        self.commands.push(Box::new(move |world| {
-            world
-                .insert_one(entity, component)
-                .expect("Could not add component to entity");
-        }));
+    fn new_enemy_seeker(
+        pos: Vec2,
+        vel: Vec2,
+        player_entity: Entity,
+    ) -> (
+        Transform,
+        Motion,
+        Collider,
+        Enemy,
+        MoveTowardsTarget,
+        Drawable,
+    ) {
+        let radius = 8.0;
+        let linestrip = vec![
+            Vec2::new(radius, 0.0),
+            Vec2::new(-radius / 2.0, radius),
+            Vec2::new(-radius / 2.0, -radius),
+            Vec2::new(radius, 0.0),
+        ];
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            Motion {
+                vel,
+                acc: Vec2::zero(),
+                dir_angle_vel: 180.0,
+                dir_angle_acc: 0.0,
+            },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: 100.0,
+                hp_max: 100.0,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: true,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_started(3.0),
+                timer_charge: TriggerRepeating::new(std::f32::MAX),
+                score: 250,
+                enemytype: EnemyType::Seeker,
+            },
+            MoveTowardsTarget {
+                target: player_entity,
+                follow_precision_percent: 0.02,
+            },
+            Drawable {
+                mesh: MeshType::Linestrip(linestrip),
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_enemy_mine(
+        pos: Vec2,
+    ) -> (
+        Transform,
+        Motion,
+        Projectile,
+        Collider,
+        AutoremoveTimer,
+        Drawable,
+    ) {
+        let radius = 4.0;
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            Motion {
+                vel: Vec2::zero(),
+                acc: Vec2::zero(),
+                dir_angle_vel: 0.0,
+                dir_angle_acc: 0.0,
+            },
+            Projectile::new(radius, COLOR_HP, ENEMY_CONTACT_DAMAGE, false),
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY_PROJECTILE,
+                layers_affects: COLLISION_LAYER_PLAYER | COLLISION_LAYER_PLAYER_PROJECTILE,
+            },
+            AutoremoveTimer::new(MINE_LIFETIME),
+            Drawable {
+                mesh: MeshType::Circle {
+                    radius,
+                    filled: false,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_PROJECTILE,
+                add_jitter: true,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_enemy_orbitter(pos: Vec2, vel: Vec2) -> (Transform, Motion, Collider, Enemy, Drawable) {
+        let size = 10.0;
+        let radius = size;
+        let linestrip = vec![
+            Vec2::new(size, size),
+            Vec2::new(-size, size),
+            Vec2::new(-size, -size),
+            Vec2::new(size, -size),
+            Vec2::new(size, size),
+        ];
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            Motion {
+                vel: 0.5 * vel,
+                acc: Vec2::zero(),
+                dir_angle_vel: 45.0,
+                dir_angle_acc: 0.0,
+            },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: 200.0,
+                hp_max: 200.0,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: false,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_stopped(1.0),
+                timer_charge: TriggerRepeating::new(1.0),
+                score: 400,
+                enemytype: EnemyType::Orbitter,
+            },
+            Drawable {
+                mesh: MeshType::Linestrip(linestrip),
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    /// NOTE: Satellites keep their Orbitter from taking damage as long as they live
+    fn new_enemy_satellite(
+        parent: Entity,
+        parent_pos: Vec2,
+        angle: f32,
+    ) -> (
+        Transform,
+        Motion,
+        SnapToParent,
+        Orbit,
+        Collider,
+        Enemy,
+        Drawable,
+    ) {
+        let radius = 4.0;
+        let pos_offset = Vec2::from_angle_flipped_y(deg_to_rad(angle)) * 24.0;
+        (
+            Transform {
+                pos: parent_pos + pos_offset,
+                dir_angle: 0.0,
+            },
+            Motion {
+                vel: Vec2::zero(),
+                acc: Vec2::zero(),
+                dir_angle_vel: 0.0,
+                dir_angle_acc: 0.0,
+            },
+            SnapToParent {
+                parent,
+                remove_entity_if_lost_parent: true,
+                pos_snap: true,
+                pos_offset,
+                dir_angle_snap: false,
+                dir_angle_offset: 0.0,
+            },
+            Orbit { angle_vel: 120.0 },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: 50.0,
+                hp_max: 50.0,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: false,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_stopped(1.0),
+                timer_charge: TriggerRepeating::new(1.0),
+                score: 50,
+                enemytype: EnemyType::Orbitter,
+            },
+            Drawable {
+                mesh: MeshType::Circle {
+                    radius,
+                    filled: true,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_enemy_sapper(
+        pos: Vec2,
+        vel: Vec2,
+        player_entity: Entity,
+    ) -> (
+        Transform,
+        Motion,
+        Collider,
+        Enemy,
+        MoveTowardsTarget,
+        Drawable,
+    ) {
+        let radius = 7.0;
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            Motion {
+                vel: 0.75 * vel,
+                acc: Vec2::zero(),
+                dir_angle_vel: 0.0,
+                dir_angle_acc: 0.0,
+            },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: 80.0,
+                hp_max: 80.0,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: false,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_stopped(1.0),
+                timer_charge: TriggerRepeating::new(1.0),
+                score: 200,
+                enemytype: EnemyType::Sapper,
+            },
+            MoveTowardsTarget {
+                target: player_entity,
+                follow_precision_percent: 0.01,
+            },
+            Drawable {
+                mesh: MeshType::Circle {
+                    radius,
+                    filled: false,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: true,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_enemy_trailer(
+        pos: Vec2,
+        vel: Vec2,
+    ) -> (Transform, Motion, Collider, Enemy, TrailEmitter, Drawable) {
+        let width = 12.0;
+        let height = 4.0;
+        let radius = width;
+        let dir_angle = if vel.x >= 0.0 { 0.0 } else { -180.0 };
+        let linestrip = vec![
+            Vec2::new(width, 0.0),
+            Vec2::new(-width, height),
+            Vec2::new(-width / 2.0, 0.0),
+            Vec2::new(-width, -height),
+            Vec2::new(width, 0.0),
+        ];
+        (
+            Transform { pos, dir_angle },
+            Motion {
+                vel: 1.5 * vel,
+                acc: Vec2::zero(),
+                dir_angle_vel: 0.0,
+                dir_angle_acc: 0.0,
+            },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: 100.0,
+                hp_max: 100.0,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: false,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_stopped(1.0),
+                timer_charge: TriggerRepeating::new(1.0),
+                score: 250,
+                enemytype: EnemyType::Trailer,
+            },
+            TrailEmitter {
+                timer_segment: TriggerRepeating::new(TRAIL_SEGMENT_INTERVAL),
+            },
+            Drawable {
+                mesh: MeshType::Linestrip(linestrip),
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_trail_segment(
+        pos: Vec2,
+    ) -> (
+        Transform,
+        AutoremoveTimer,
+        Hazard,
+        Collider,
+        TweenScale,
+        Drawable,
+    ) {
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            AutoremoveTimer::new(TRAIL_SEGMENT_LIFETIME),
+            Hazard {
+                damage: ENEMY_CONTACT_DAMAGE,
+            },
+            Collider {
+                radius: TRAIL_SEGMENT_RADIUS,
+                layers_own: COLLISION_LAYER_HAZARD,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            TweenScale::new(1.0, 0.0, TRAIL_SEGMENT_LIFETIME, EasingType::Linear),
+            Drawable {
+                mesh: MeshType::Circle {
+                    radius: TRAIL_SEGMENT_RADIUS,
+                    filled: true,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_PROJECTILE - 1.0,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_boss(pos: Vec2, vel: Vec2) -> (Transform, Motion, Collider, Enemy, Boss, Drawable) {
+        let radius = BOSS_RADIUS;
+        let vertex_count = 8;
+        let linestrip: Vec<Vec2> = (0..=vertex_count)
+            .map(|index| {
+                let angle = deg_to_rad(index as f32 * 360.0 / vertex_count as f32);
+                radius * Vec2::from_angle_flipped_y(angle)
+            })
+            .collect();
+        (
+            Transform {
+                pos,
+                dir_angle: 0.0,
+            },
+            Motion {
+                vel,
+                acc: Vec2::zero(),
+                dir_angle_vel: 30.0,
+                dir_angle_acc: 0.0,
+            },
+            Collider {
+                radius,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Enemy {
+                hp: BOSS_HP,
+                hp_max: BOSS_HP,
+                hitflash_timer: TimerSimple::new_stopped(0.1),
+                radius,
+                can_shoot: false,
+                is_charging: false,
+                timer_shoot: TimerSimple::new_stopped(1.0),
+                timer_charge: TriggerRepeating::new(1.0),
+                score: 5000,
+                enemytype: EnemyType::Boss,
+            },
+            Boss {
+                phase: 1,
+                timer_attack: TriggerRepeating::new(2.0),
+                attack_count: 0,
+                time: 0.0,
+            },
+            Drawable {
+                mesh: MeshType::Linestrip(linestrip),
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_HP,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+
+    fn new_boss_armor(
+        boss: Entity,
+        boss_pos: Vec2,
+        angle: f32,
+    ) -> (
+        Transform,
+        SnapToParent,
+        Collider,
+        Hazard,
+        BossArmor,
+        Drawable,
+    ) {
+        let pos_offset = BOSS_ARMOR_DISTANCE * Vec2::from_angle_flipped_y(deg_to_rad(angle));
+        let length = 20.0;
+        (
+            Transform {
+                pos: boss_pos + pos_offset,
+                dir_angle: angle + 90.0,
+            },
+            SnapToParent {
+                parent: boss,
+                remove_entity_if_lost_parent: true,
+                pos_snap: true,
+                pos_offset,
+                dir_angle_snap: true,
+                dir_angle_offset: angle + 90.0,
+            },
+            Collider {
+                radius: length / 2.0,
+                layers_own: COLLISION_LAYER_ENEMY,
+                layers_affects: COLLISION_LAYER_PLAYER,
+            },
+            Hazard {
+                damage: ENEMY_CONTACT_DAMAGE,
+            },
+            BossArmor,
+            Drawable {
+                mesh: MeshType::LineWithThickness {
+                    length,
+                    thickness: 4.0,
+                    smooth_edges: false,
+                    centered: true,
+                },
+                pos_offset: Vec2::zero(),
+                dir_angle_offset: 0.0,
+                scale: Vec2::ones(),
+                color: COLOR_DEFAULT,
+                additivity: ADDITIVITY_NONE,
+                depth: DEPTH_COLLECTIBLES,
+                add_jitter: false,
+                visible: true,
+            },
+        )
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// World command buffer
+
+struct WorldCommandBuffer {
+    commands: Vec<Box<dyn FnOnce(&mut World) + Send + Sync>>,
+}
+
+impl WorldCommandBuffer {
+    fn new() -> WorldCommandBuffer {
+        WorldCommandBuffer {
+            commands: Vec::new(),
+        }
+    }
+
+    fn add_entity<ComponentsBundleType>(&mut self, components: ComponentsBundleType)
+    where
+        ComponentsBundleType: hecs::DynamicBundle + Send + Sync + 'static,
+    {
+        self.commands.push(Box::new(move |world| {
+            world.spawn(components);
+        }));
+    }
+
+    fn add_component<ComponentType>(&mut self, entity: Entity, component: ComponentType)
+    where
+        ComponentType: Send + Sync + 'static,
+    {
+        self.commands.push(Box::new(move |world| {
+            world
+                .insert_one(entity, component)
+                .expect("Could not add component to entity");
+        }));
     }
 
     fn add_component_bundle<ComponentsBundleType>(
@@ -2165,17 +3746,231 @@ impl WorldCommandBuffer {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// World snapshot
+
+/// Lists every component type that can live in the stage world. Components of types that are
+/// missing here get lost when restoring a snapshot.
+macro_rules! snapshot_components {
+    ($($component:ident),* $(,)?) => {
+        #[derive(Clone)]
+        enum ComponentSnapshot {
+            $($component($component),)*
+        }
+
+        impl ComponentSnapshot {
+            fn collect_from_entity(entity_ref: &EntityRef) -> Vec<ComponentSnapshot> {
+                let mut components = Vec::new();
+                $(
+                    if let Some(component) = entity_ref.get::<$component>() {
+                        components.push(ComponentSnapshot::$component((*component).clone()));
+                    }
+                )*
+                components
+            }
+
+            fn add_to_builder(&self, builder: &mut EntityBuilder) {
+                match self {
+                    $(
+                        ComponentSnapshot::$component(component) => {
+                            builder.add(component.clone());
+                        }
+                    )*
+                }
+            }
+        }
+    };
+}
+
+impl ComponentSnapshot {
+    /// Points all entity handles stored in the component to the entities of a restored world.
+    /// Handles of entities that did not exist when taking the snapshot become dead handles.
+    fn remap_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        let remap = |entity: Entity| {
+            entity_map
+                .get(&entity)
+                .cloned()
+                .unwrap_or_else(|| Entity::from_bits(std::u64::MAX))
+        };
+        match self {
+            ComponentSnapshot::SnapToParent(snap) => snap.parent = remap(snap.parent),
+            ComponentSnapshot::MoveTowardsTarget(move_towards) => {
+                move_towards.target = remap(move_towards.target)
+            }
+            ComponentSnapshot::EnemyChargeParticle(particle) => {
+                particle.muzzle_entity = remap(particle.muzzle_entity)
+            }
+            ComponentSnapshot::Projectile(projectile) => {
+                projectile.enemies_hit = projectile
+                    .enemies_hit
+                    .iter()
+                    .filter_map(|enemy| entity_map.get(enemy).cloned())
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+}
+
+snapshot_components!(
+    AutoremoveTimer,
+    AutoremoveTimerFrames,
+    Blinker,
+    Boss,
+    BossArmor,
+    Buffs,
+    Collectible,
+    Collider,
+    ContinuousCollision,
+    Drawable,
+    DrawableMulti,
+    Enemy,
+    EnemyChargeParticle,
+    ExplodeParticle,
+    HasteArea,
+    Hazard,
+    Motion,
+    MoveTowardsTarget,
+    Orbit,
+    Player,
+    Projectile,
+    RigidBody,
+    SineMotion,
+    SnapToParent,
+    TickEffect,
+    TrailEmitter,
+    Transform,
+    TweenColor,
+    TweenScale,
+);
+
+#[derive(Clone)]
+struct WorldSnapshot {
+    entities: Vec<(Entity, Vec<ComponentSnapshot>)>,
+}
+
+impl WorldSnapshot {
+    fn new(world: &World) -> WorldSnapshot {
+        WorldSnapshot {
+            entities: world
+                .iter()
+                .map(|(entity, entity_ref)| {
+                    (entity, ComponentSnapshot::collect_from_entity(&entity_ref))
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the restored world together with the new handle of the given entity.
+    ///
+    /// NOTE: Restored entities get new handles. All handles stored in components are remapped
+    ///       accordingly, so that a handle of an entity that was already despawned when taking the
+    ///       snapshot can not end up pointing to some unrelated entity.
+    fn restore(&self, entity: Entity) -> (World, Entity) {
+        let mut world = World::new();
+        let entity_map: HashMap<Entity, Entity> = self
+            .entities
+            .iter()
+            .map(|(old_entity, _components)| (*old_entity, world.reserve_entity()))
+            .collect();
+
+        let mut builder = EntityBuilder::new();
+        for (old_entity, components) in &self.entities {
+            for component in components {
+                let mut component = component.clone();
+                component.remap_entities(&entity_map);
+                component.add_to_builder(&mut builder);
+            }
+            world
+                .insert(entity_map[old_entity], builder.build())
+                .expect("Could not restore entity");
+        }
+
+        let entity = entity_map
+            .get(&entity)
+            .cloned()
+            .unwrap_or_else(|| Entity::from_bits(std::u64::MAX));
+        (world, entity)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Ship types
 
-#[derive(Debug, Copy, Clone)]
-enum ShipType {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum ShipType {
     Fighter,
     Sorcerer,
     Rogue,
+    Striker,
+    Crusader,
+    BitHunter,
+    Sentinel,
+    Swordsman,
+    Nuclear,
+    Cycler,
+    Wisp,
 }
 
-fn get_draw_lines_for_ship(ship_type: ShipType) -> Vec<Vec<(i32, i32)>> {
+impl ShipType {
+    pub fn to_string(self) -> String {
+        match self {
+            ShipType::Fighter => "Fighter".to_string(),
+            ShipType::Sorcerer => "Sorcerer".to_string(),
+            ShipType::Rogue => "Rogue".to_string(),
+            ShipType::Striker => "Striker".to_string(),
+            ShipType::Crusader => "Crusader".to_string(),
+            ShipType::BitHunter => "Bit Hunter".to_string(),
+            ShipType::Sentinel => "Sentinel".to_string(),
+            ShipType::Swordsman => "Swordsman".to_string(),
+            ShipType::Nuclear => "Nuclear".to_string(),
+            ShipType::Cycler => "Cycler".to_string(),
+            ShipType::Wisp => "Wisp".to_string(),
+        }
+    }
+}
+
+/// Base values of a ship before skills are applied
+#[derive(Debug, Copy, Clone)]
+pub struct ShipStats {
+    pub speed: f32,
+    pub acc: f32,
+    pub turn_speed: f32,
+    pub hp_multiplier: f32,
+    pub ammo_multiplier: f32,
+    pub boost_multiplier: f32,
+    pub attack_speed_multiplier: f32,
+    pub cycle_speed_multiplier: f32,
+}
+
+pub fn get_stats_for_ship(ship_type: ShipType) -> ShipStats {
+    #[rustfmt::skip]
+    let (speed, acc, turn_speed, hp, ammo, boost, attack_speed, cycle_speed) = match ship_type {
+        ShipType::Fighter =>   (100.0, 100.0, 300.0, 1.0, 1.0, 1.0, 1.0, 1.0),
+        ShipType::Sorcerer =>  ( 90.0, 100.0, 300.0, 0.8, 1.3, 1.0, 1.0, 1.0),
+        ShipType::Rogue =>     (120.0, 120.0, 330.0, 0.7, 1.0, 1.2, 1.1, 1.0),
+        ShipType::Striker =>   (100.0, 100.0, 300.0, 0.9, 1.0, 1.0, 1.2, 1.0),
+        ShipType::Crusader =>  ( 80.0,  80.0, 250.0, 1.5, 1.0, 0.8, 0.9, 1.0),
+        ShipType::BitHunter => (110.0, 110.0, 300.0, 0.9, 1.1, 1.1, 1.0, 1.0),
+        ShipType::Sentinel =>  ( 90.0,  90.0, 280.0, 1.3, 0.9, 1.0, 0.9, 1.0),
+        ShipType::Swordsman => (110.0, 120.0, 330.0, 1.1, 0.8, 1.0, 0.9, 1.0),
+        ShipType::Nuclear =>   ( 90.0,  90.0, 280.0, 1.2, 1.2, 0.8, 0.8, 1.0),
+        ShipType::Cycler =>    (100.0, 100.0, 300.0, 1.0, 1.0, 1.0, 1.0, 1.5),
+        ShipType::Wisp =>      (130.0, 140.0, 360.0, 0.6, 1.0, 1.3, 1.1, 1.0),
+    };
+    ShipStats {
+        speed,
+        acc,
+        turn_speed,
+        hp_multiplier: hp,
+        ammo_multiplier: ammo,
+        boost_multiplier: boost,
+        attack_speed_multiplier: attack_speed,
+        cycle_speed_multiplier: cycle_speed,
+    }
+}
+
+pub fn get_draw_lines_for_ship(ship_type: ShipType) -> Vec<Vec<(i32, i32)>> {
     match ship_type {
         ShipType::Fighter => {
             let hull = vec![
@@ -2205,183 +4000,93 @@ fn get_draw_lines_for_ship(ship_type: ShipType) -> Vec<Vec<(i32, i32)>> {
             let wing_right = vec![(2, 2), (5, 7), (-1, 4), (-4, 0)];
             vec![hull, wing_left, wing_right]
         }
-    }
-}
-
-fn get_shoot_points_for_ship(ship_type: ShipType) -> Vec<(i32, i32)> {
-    match ship_type {
-        ShipType::Fighter => vec![(4, 0)],
-        ShipType::Rogue => vec![(4, 0)],
-        ShipType::Sorcerer => vec![(5, 0)],
-    }
-}
-
-fn get_exhaust_points_for_ship(ship_type: ShipType) -> Vec<(i32, i32)> {
-    match ship_type {
-        ShipType::Fighter => vec![(-3, -1), (-3, 1)],
-        ShipType::Rogue => vec![(-3, -2), (-3, 2)],
-        ShipType::Sorcerer => vec![(-4, 0)],
-    }
-}
-
-////////////////////////////////////////////////////////////////////////////////////////////////////
-// Director
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, EnumIter)]
-pub enum ResourceType {
-    Boost,
-    Skillpoint,
-    Health,
-}
-
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-pub enum EnemyType {
-    Rock,
-    Shooter,
-}
-
-pub struct Director {
-    pub difficulty: usize,
-    pub timer_spawn_attack: TriggerRepeating,
-    pub timer_spawn_resource: TriggerRepeating,
-    pub timer_round: TimerSimple,
-    pub round_duration: f32,
-    pub round_enemies_and_spawntimes: std::collections::VecDeque<(EnemyType, f32)>,
-}
-impl Director {
-    pub fn new(random: &mut Random) -> Director {
-        let difficulty = 1;
-        let round_duration = 22.0;
-        Director {
-            difficulty,
-            timer_spawn_attack: TriggerRepeating::new(30.0),
-            timer_spawn_resource: TriggerRepeating::new(16.0),
-            timer_round: TimerSimple::new_started(round_duration),
-            round_duration,
-            round_enemies_and_spawntimes: Director::create_enemies_and_spawntimes(
-                random,
-                difficulty,
-                round_duration,
-            ),
+        ShipType::Striker => {
+            let hull = vec![(4, 0), (2, -1), (-2, -1), (-4, 0), (-2, 1), (2, 1), (4, 0)];
+            let wing_left = vec![(1, -1), (-1, -5), (-3, -5), (-2, -1)];
+            let wing_right = vec![(1, 1), (-1, 5), (-3, 5), (-2, 1)];
+            vec![hull, wing_left, wing_right]
         }
-    }
-
-    pub fn update_and_spawn_enemies_and_collectibles(
-        &mut self,
-        random: &mut Random,
-        deltatime: f32,
-    ) -> (Option<EnemyType>, Option<ResourceType>, Option<AttackType>) {
-        self.timer_round.update(deltatime);
-
-        let attack_to_spawn = if self.timer_spawn_attack.update_and_check(deltatime) {
-            let attacktypes: Vec<AttackType> = AttackType::iter().skip(1).collect();
-            let attacktype = random.pick_from_slice(&attacktypes);
-            Some(attacktype)
-        } else {
-            None
-        };
-
-        let resource_to_spawn = if self.timer_spawn_resource.update_and_check(deltatime) {
-            let mut shufflebag = Shufflebag::new_with_counts(&[
-                (ResourceType::Boost, 28),
-                (ResourceType::Health, 14),
-                (ResourceType::Skillpoint, 58),
-            ]);
-            let resourcetype = shufflebag.get_next(random);
-            Some(resourcetype)
-        } else {
-            None
-        };
-
-        let enemy_to_spawn = {
-            if let Some((enemy, spawn_time)) = self.round_enemies_and_spawntimes.front().cloned() {
-                if spawn_time <= self.timer_round.time_cur {
-                    self.round_enemies_and_spawntimes.pop_front();
-                    Some(enemy)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        };
-
-        // Update difficulty
-        if self.timer_round.is_finished() {
-            self.timer_round.restart();
-            self.difficulty += 1;
-            self.round_enemies_and_spawntimes = Director::create_enemies_and_spawntimes(
-                random,
-                self.difficulty,
-                self.round_duration,
-            );
+        ShipType::Crusader => {
+            let hull = vec![(4, 0), (2, -2), (-3, -2), (-3, 2), (2, 2), (4, 0)];
+            let wing_left = vec![(0, -2), (0, -5), (-4, -5), (-3, -2)];
+            let wing_right = vec![(0, 2), (0, 5), (-4, 5), (-3, 2)];
+            vec![hull, wing_left, wing_right]
+        }
+        ShipType::BitHunter => {
+            let hull = vec![(4, 0), (0, -3), (-4, -1), (-4, 1), (0, 3), (4, 0)];
+            let fin_left = vec![(0, -3), (-2, -5), (-2, -2)];
+            let fin_right = vec![(0, 3), (-2, 5), (-2, 2)];
+            vec![hull, fin_left, fin_right]
+        }
+        ShipType::Sentinel => {
+            let hull = vec![
+                (4, 0),
+                (3, -3),
+                (0, -4),
+                (-3, -3),
+                (-4, 0),
+                (-3, 3),
+                (0, 4),
+                (3, 3),
+                (4, 0),
+            ];
+            let core = vec![(2, 0), (-2, -2), (-2, 2), (2, 0)];
+            vec![hull, core]
         }
-
-        (enemy_to_spawn, resource_to_spawn, attack_to_spawn)
-    }
-
-    fn create_enemies_and_spawntimes(
-        random: &mut Random,
-        difficulty: usize,
-        round_duration: f32,
-    ) -> std::collections::VecDeque<(EnemyType, f32)> {
-        let enemy_spawn_chances = Director::get_enemy_spawn_chance(random, difficulty);
-
-        let mut points = Director::get_spending_points_for_difficulty(difficulty);
-        let mut enemybag = Shufflebag::new_with_counts(&enemy_spawn_chances);
-        let mut enemies = Vec::new();
-
-        while points > 0 {
-            let enemy = enemybag.get_next(random);
-            points -= Director::get_spending_cost_for_enemy(enemy);
-            enemies.push(enemy);
+        ShipType::Swordsman => {
+            let blade = vec![(6, 0), (0, -1), (-4, 0), (0, 1), (6, 0)];
+            let guard = vec![(-1, -4), (0, -1), (0, 1), (-1, 4)];
+            vec![blade, guard]
         }
-
-        let mut spawn_times = Vec::new();
-        for _ in 0..enemies.len() {
-            spawn_times.push(random.f32_in_range_open(0.0, round_duration))
+        ShipType::Nuclear => {
+            let hull = vec![(4, 0), (2, -3), (-2, -3), (-4, 0), (-2, 3), (2, 3), (4, 0)];
+            let core = vec![(2, 0), (-1, -2), (-1, 2), (2, 0)];
+            vec![hull, core]
         }
-        spawn_times.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-
-        enemies.into_iter().zip(spawn_times.into_iter()).collect()
-    }
-
-    fn get_spending_points_for_difficulty(difficulty: usize) -> i32 {
-        let mut points = 16;
-        for n in 1..difficulty {
-            points = if (n - 1) % 4 == 0 {
-                points + 8
-            } else if (n - 1) % 4 == 1 {
-                points
-            } else if (n - 1) % 4 == 2 {
-                (points as f32 / 1.5) as i32
-            } else {
-                points * 2
-            };
+        ShipType::Cycler => {
+            let hull = vec![(4, 0), (0, -4), (-4, 0), (0, 4), (4, 0)];
+            let spoke_horizontal = vec![(4, 0), (-4, 0)];
+            let spoke_vertical = vec![(0, -4), (0, 4)];
+            vec![hull, spoke_horizontal, spoke_vertical]
+        }
+        ShipType::Wisp => {
+            let hull = vec![(2, 0), (0, -2), (-2, 0), (0, 2), (2, 0)];
+            let tail_left = vec![(-2, 0), (-5, -2)];
+            let tail_right = vec![(-2, 0), (-5, 2)];
+            vec![hull, tail_left, tail_right]
         }
-
-        points
     }
+}
 
-    fn get_enemy_spawn_chance(random: &mut Random, difficulty: usize) -> Vec<(EnemyType, usize)> {
-        match difficulty {
-            0 => unimplemented!("There is no difficulty 0"),
-            1 => vec![(EnemyType::Rock, 1)],
-            2 => vec![(EnemyType::Rock, 8), (EnemyType::Shooter, 4)],
-            3 => vec![(EnemyType::Rock, 8), (EnemyType::Shooter, 8)],
-            4 => vec![(EnemyType::Rock, 4), (EnemyType::Shooter, 8)],
-            _ => vec![
-                (EnemyType::Rock, random.gen_range(2, 12)),
-                (EnemyType::Shooter, random.gen_range(2, 12)),
-            ],
-        }
+fn get_shoot_points_for_ship(ship_type: ShipType) -> Vec<(i32, i32)> {
+    match ship_type {
+        ShipType::Fighter => vec![(4, 0)],
+        ShipType::Rogue => vec![(4, 0)],
+        ShipType::Sorcerer => vec![(5, 0)],
+        ShipType::Striker => vec![(4, 0)],
+        ShipType::Crusader => vec![(4, 0)],
+        ShipType::BitHunter => vec![(4, 0)],
+        ShipType::Sentinel => vec![(4, 0)],
+        ShipType::Swordsman => vec![(6, 0)],
+        ShipType::Nuclear => vec![(4, 0)],
+        ShipType::Cycler => vec![(4, 0)],
+        ShipType::Wisp => vec![(2, 0)],
     }
+}
 
-    fn get_spending_cost_for_enemy(enemytype: EnemyType) -> i32 {
-        match enemytype {
-            EnemyType::Rock => 1,
-            EnemyType::Shooter => 2,
-        }
+fn get_exhaust_points_for_ship(ship_type: ShipType) -> Vec<(i32, i32)> {
+    match ship_type {
+        ShipType::Fighter => vec![(-3, -1), (-3, 1)],
+        ShipType::Rogue => vec![(-3, -2), (-3, 2)],
+        ShipType::Sorcerer => vec![(-4, 0)],
+        ShipType::Striker => vec![(-4, 0)],
+        ShipType::Crusader => vec![(-3, -1), (-3, 1)],
+        ShipType::BitHunter => vec![(-4, -1), (-4, 1)],
+        ShipType::Sentinel => vec![(-4, 0)],
+        ShipType::Swordsman => vec![(-4, 0)],
+        ShipType::Nuclear => vec![(-3, -1), (-3, 1)],
+        ShipType::Cycler => vec![(-4, 0)],
+        ShipType::Wisp => vec![(-5, -2), (-5, 2)],
     }
 }
 
@@ -2409,15 +4114,257 @@ impl SlowmotionModulator {
     #[must_use]
     fn update_and_get_new_deltatime(&mut self, deltatime: f32) -> f32 {
         self.timer.update(deltatime);
+        self.get_factor() * deltatime
+    }
+
+    fn get_factor(&self) -> f32 {
         let percentage = self.timer.completion_ratio();
-        let factor = lerp(self.factor, 1.0, percentage);
-        factor * deltatime
+        lerp(self.factor, 1.0, percentage)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Debug commands
+
+#[derive(Debug, Copy, Clone, EnumIter)]
+enum PlayerStat {
+    Hp,
+    Ammo,
+    Boost,
+}
+
+/// Debug commands that need access to the `StageContext` are executed by the stage on its next
+/// update
+#[derive(Debug, Copy, Clone)]
+enum DebugRequest {
+    SpawnEnemy { enemytype: EnemyType, pos: Vec2 },
+    SetDifficulty(usize),
+}
+
+lazy_static! {
+    static ref DEBUG_COMMANDS: CommandRegistry<SceneStage> = {
+        let mut commands = CommandRegistry::new();
+        commands.register(
+            "give",
+            "Replaces the current attack of the player",
+            &[(
+                "attack",
+                ArgType::Enum("AttackType", enum_variant_names::<AttackType>),
+            )],
+            |stage, args, output| {
+                let mut player = stage.get_player_mut()?;
+                player.ammo = player.ammo_max;
+                player.attack = ATTACKS[&args[0].as_enum::<AttackType>()].clone();
+                player.reload_timer = TriggerRepeating::new(player.attack.reload_time);
+                output.print(&format!("Gave attack '{}'", player.attack.name));
+                Ok(())
+            },
+        );
+        commands.register(
+            "spawn",
+            "Spawns an enemy at the given position",
+            &[
+                (
+                    "enemy",
+                    ArgType::Enum("EnemyType", enum_variant_names::<EnemyType>),
+                ),
+                ("x", ArgType::Float),
+                ("y", ArgType::Float),
+            ],
+            |stage, args, _output| {
+                stage.debug_requests.push(DebugRequest::SpawnEnemy {
+                    enemytype: args[0].as_enum(),
+                    pos: Vec2::new(args[1].as_float(), args[2].as_float()),
+                });
+                Ok(())
+            },
+        );
+        commands.register(
+            "set",
+            "Sets a player stat and raises its maximum if necessary",
+            &[
+                (
+                    "stat",
+                    ArgType::Enum("PlayerStat", enum_variant_names::<PlayerStat>),
+                ),
+                ("value", ArgType::Float),
+            ],
+            |stage, args, output| {
+                let value = args[1].as_float();
+                if value < 0.0 {
+                    return Err(format!("Value must not be negative but was {}", value));
+                }
+
+                let mut player_ref = stage.get_player_mut()?;
+                let player = &mut *player_ref;
+                let stat = args[0].as_enum::<PlayerStat>();
+                let (current, max) = match stat {
+                    PlayerStat::Hp => (&mut player.hp, &mut player.hp_max),
+                    PlayerStat::Ammo => (&mut player.ammo, &mut player.ammo_max),
+                    PlayerStat::Boost => (&mut player.boost, &mut player.boost_max),
+                };
+                *current = value;
+                *max = f32::max(*max, value);
+                output.print(&format!("Set {:?} to {}", stat, value));
+                Ok(())
+            },
+        );
+        commands.register(
+            "difficulty",
+            "Restarts the current round with the given director difficulty",
+            &[("difficulty", ArgType::Int)],
+            |stage, args, _output| {
+                let difficulty = args[0].as_int();
+                if difficulty < 1 {
+                    return Err(format!(
+                        "Difficulty must be at least 1 but was {}",
+                        difficulty
+                    ));
+                }
+                stage
+                    .debug_requests
+                    .push(DebugRequest::SetDifficulty(difficulty as usize));
+                Ok(())
+            },
+        );
+        commands.register(
+            "invincible",
+            "Toggles whether the player can take damage",
+            &[],
+            |stage, _args, output| {
+                stage.debug_player_invincible = !stage.debug_player_invincible;
+                output.print(&format!(
+                    "Invincibility {}",
+                    on_off(stage.debug_player_invincible)
+                ));
+                Ok(())
+            },
+        );
+        commands.register(
+            "colliders",
+            "Toggles drawing of colliders",
+            &[],
+            |stage, _args, output| {
+                stage.debug_draw_enabled = !stage.debug_draw_enabled;
+                output.print(&format!(
+                    "Collider drawing {}",
+                    on_off(stage.debug_draw_enabled)
+                ));
+                Ok(())
+            },
+        );
+        commands.register(
+            "quicksave",
+            "Remembers the current state of the stage",
+            &[],
+            |stage, _args, output| {
+                stage.debug_quicksave = Some(stage.create_snapshot());
+                output.print("Saved stage");
+                Ok(())
+            },
+        );
+        commands.register(
+            "quickload",
+            "Puts the stage back into the state of the last quicksave",
+            &[],
+            |stage, _args, output| {
+                let snapshot = stage
+                    .debug_quicksave
+                    .clone()
+                    .ok_or_else(|| "Nothing was saved yet".to_owned())?;
+                stage.restore_snapshot(&snapshot);
+                output.print("Loaded stage");
+                Ok(())
+            },
+        );
+        commands.register(
+            "clear",
+            "Clears the screen",
+            &[],
+            |_stage, _args, output| {
+                output.clear_terminal = true;
+                Ok(())
+            },
+        );
+        commands
+    };
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[derive(Clone)]
+enum ReplayMode {
+    Recording(Replay),
+    Playback {
+        replay: Replay,
+        frame_index: usize,
+        desync_frame: Option<usize>,
+    },
+}
+
+fn create_replay_frame(input: &GameInput, deltatime: f32) -> ReplayFrame {
+    let mut frame = ReplayFrame::new(deltatime);
+    for key in ReplayKey::iter() {
+        let scancode = match key {
+            ReplayKey::Up => Scancode::Up,
+            ReplayKey::Down => Scancode::Down,
+            ReplayKey::Left => Scancode::Left,
+            ReplayKey::Right => Scancode::Right,
+            ReplayKey::A => Scancode::A,
+            ReplayKey::S => Scancode::S,
+            ReplayKey::T => Scancode::T,
+            ReplayKey::C => Scancode::C,
+        };
+        frame.set_key(
+            key,
+            input.keyboard.is_down(scancode),
+            input.keyboard.recently_pressed(scancode),
+        );
+    }
+    // NOTE: Toggling the terminal does not count as a key press
+    frame.any_key_pressed =
+        input.keyboard.has_press_event && !input.keyboard.recently_pressed(Scancode::Grave);
+    frame
+}
+
+/// The parts of `Globals` that the stage simulation touches. Everything else like fonts and cursors
+/// only exists when we have a window.
+///
+/// NOTE: The stage brings its own random generator because it needs to be part of snapshots
+pub struct StageContext<'a> {
+    pub camera: &'a mut GameCamera,
+    pub deltatime: f32,
+    pub canvas_width: f32,
+    pub canvas_height: f32,
+}
+
+impl<'a> StageContext<'a> {
+    pub fn from_globals(globals: &'a mut Globals) -> StageContext<'a> {
+        StageContext {
+            camera: &mut globals.camera,
+            deltatime: globals.deltatime,
+            canvas_width: globals.canvas_width,
+            canvas_height: globals.canvas_height,
+        }
     }
 }
 
+fn load_fonts(draw: &mut Drawstate) -> HashMap<String, SpriteFont> {
+    let mut fonts = HashMap::new();
+    fonts.insert("gui_font".to_owned(), draw.get_font("default_tiny").clone());
+    fonts
+}
+
 pub struct SceneStage {
     score: usize,
     skillpoint_count: usize,
+    screenshake_enabled: bool,
 
     slowmotion: SlowmotionModulator,
 
@@ -2426,11 +4373,76 @@ pub struct SceneStage {
     world: World,
     commands: WorldCommandBuffer,
     player: Entity,
+
+    random: Random,
+    /// Effects that only change how things look use their own random generator. This way the
+    /// simulation does the same with and without drawing.
+    random_visual: Random,
+    /// Infotexts only show what happened. They live outside of the world so that drawing them never
+    /// changes the entities of the simulation.
+    infotexts: Vec<InfoText>,
+    infotexts_to_create: Vec<InfoText>,
+
+    debug_terminal: Terminal,
+    debug_terminal_open: bool,
+    debug_requests: Vec<DebugRequest>,
+    debug_player_invincible: bool,
+    debug_draw_enabled: bool,
+    debug_quicksave: Option<StageSnapshot>,
+    debug_colliding_entities: HashSet<Entity>,
+
+    replay_mode: ReplayMode,
+}
+
+/// Everything that changes while playing a stage. Restoring it puts the stage back into the exact
+/// same state, which is what rewinding and quick-saving need.
+///
+/// NOTE: Snapshots are only taken between updates. Pending world commands are not part of them as
+///       the stage executes all of its commands at the end of every update.
+#[derive(Clone)]
+struct StageSnapshot {
+    score: usize,
+    skillpoint_count: usize,
+    slowmotion: SlowmotionModulator,
+    director: Director,
+    world: WorldSnapshot,
+    player: Entity,
+    random: Random,
+    random_visual: Random,
 }
 
 impl Clone for SceneStage {
     fn clone(&self) -> Self {
-        todo!()
+        let snapshot = self.create_snapshot();
+        let (world, player) = snapshot.world.restore(snapshot.player);
+        SceneStage {
+            score: snapshot.score,
+            skillpoint_count: snapshot.skillpoint_count,
+            screenshake_enabled: self.screenshake_enabled,
+
+            slowmotion: snapshot.slowmotion,
+
+            director: snapshot.director,
+            fonts: self.fonts.clone(),
+            world,
+            commands: WorldCommandBuffer::new(),
+            player,
+
+            random: snapshot.random,
+            random_visual: snapshot.random_visual,
+            infotexts: self.infotexts.clone(),
+            infotexts_to_create: self.infotexts_to_create.clone(),
+
+            debug_terminal: self.debug_terminal.clone(),
+            debug_terminal_open: self.debug_terminal_open,
+            debug_requests: self.debug_requests.clone(),
+            debug_player_invincible: self.debug_player_invincible,
+            debug_draw_enabled: self.debug_draw_enabled,
+            debug_quicksave: self.debug_quicksave.clone(),
+            debug_colliding_entities: self.debug_colliding_entities.clone(),
+
+            replay_mode: self.replay_mode.clone(),
+        }
     }
 }
 
@@ -2441,309 +4453,299 @@ impl SceneStage {
         _assets: &mut GameAssets,
         _input: &GameInput,
         globals: &mut Globals,
+        ship_type: ShipType,
+        skills: &[Skill],
+        settings: &ProfileSettings,
     ) -> SceneStage {
-        let mut world = World::new();
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        let replay = Replay::new(seed, ship_type, skills);
+        SceneStage::new_with_replay_mode(
+            &mut StageContext::from_globals(globals),
+            load_fonts(draw),
+            ship_type,
+            skills,
+            settings,
+            ReplayMode::Recording(replay),
+        )
+    }
 
-        let skills = vec![
-            Skill::AddHp(15),
-            Skill::AddPercentageBoost(50),
-            Skill::PickupAction(PickupAction {
-                collectible_type: CollectibleType::Ammo,
-                action: OneTimeAction::LaunchHomingProjectile,
-                chance: 5,
-            }),
-            Skill::PickupAction(PickupAction {
-                collectible_type: CollectibleType::Ammo,
-                action: OneTimeAction::RegainHp,
-                chance: 5,
-            }),
-        ];
-        let player_pos = Vec2::new(globals.canvas_width, globals.canvas_height) / 2.0;
-        let player = world.spawn(Archetypes::new_player(
-            player_pos,
-            ShipType::Sorcerer,
+    /// Plays back the given replay instead of reading the players input
+    pub fn new_playback(
+        draw: &mut Drawstate,
+        _audio: &mut Audiostate,
+        _assets: &mut GameAssets,
+        _input: &GameInput,
+        globals: &mut Globals,
+        replay: Replay,
+        settings: &ProfileSettings,
+    ) -> SceneStage {
+        SceneStage::new_playback_with_fonts(
+            &mut StageContext::from_globals(globals),
+            load_fonts(draw),
+            replay,
+            settings,
+        )
+    }
+
+    /// Plays back the given replay without a window. Such a stage can only be updated but never
+    /// drawn.
+    pub fn new_headless(
+        context: &mut StageContext,
+        replay: Replay,
+        settings: &ProfileSettings,
+    ) -> SceneStage {
+        SceneStage::new_playback_with_fonts(context, HashMap::new(), replay, settings)
+    }
+
+    fn new_playback_with_fonts(
+        context: &mut StageContext,
+        fonts: HashMap<String, SpriteFont>,
+        replay: Replay,
+        settings: &ProfileSettings,
+    ) -> SceneStage {
+        let ship_type = replay.ship_type;
+        let skills = replay.skills.clone();
+        SceneStage::new_with_replay_mode(
+            context,
+            fonts,
+            ship_type,
             &skills,
-        ));
+            settings,
+            ReplayMode::Playback {
+                replay,
+                frame_index: 0,
+                desync_frame: None,
+            },
+        )
+    }
+
+    fn new_with_replay_mode(
+        context: &mut StageContext,
+        fonts: HashMap<String, SpriteFont>,
+        ship_type: ShipType,
+        skills: &[Skill],
+        settings: &ProfileSettings,
+        replay_mode: ReplayMode,
+    ) -> SceneStage {
+        // NOTE: Everything random in a run must come from this seed so that replays work
+        let seed = match &replay_mode {
+            ReplayMode::Recording(replay) => replay.seed,
+            ReplayMode::Playback { replay, .. } => replay.seed,
+        };
+        let mut random = Random::new_from_seed(seed);
+
+        let mut world = World::new();
 
-        let mut fonts = HashMap::new();
-        fonts.insert("gui_font".to_owned(), draw.get_font("default_tiny").clone());
+        let player_pos = Vec2::new(context.canvas_width, context.canvas_height) / 2.0;
+        let player = world.spawn(Archetypes::new_player(player_pos, ship_type, skills));
+
+        let mut debug_terminal = Terminal::new();
+        debug_terminal.print(
+            "Debug commands - type 'help' for a list, press ` to close",
+            COLOR_SKILL_POINT,
+        );
 
         SceneStage {
             score: 0,
             slowmotion: SlowmotionModulator::new(),
 
-            director: Director::new(&mut globals.random),
+            director: Director::new(&mut random, DIRECTOR_CONFIG.clone()),
             skillpoint_count: 0,
+            screenshake_enabled: settings.screenshake_enabled,
             fonts,
             world,
             player: player,
             commands: WorldCommandBuffer::new(),
-        }
-    }
-}
-
-impl Scene for SceneStage {
-    fn update_and_draw(
-        &mut self,
-        draw: &mut Drawstate,
-        _audio: &mut Audiostate,
-        _assets: &mut GameAssets,
-        input: &GameInput,
-        globals: &mut Globals,
-        out_game_events: &mut Vec<GameEvent>,
-    ) {
-        draw.set_clear_color_and_depth(COLOR_BACKGROUND, DEPTH_BACKGROUND);
-
-        if input.keyboard.recently_pressed(Scancode::S) {
-            let screen_shake = ModulatorScreenShake::new(&mut globals.random, 4.0, 1.0, 60.0);
-            globals.camera.add_shake(screen_shake);
-        }
 
-        let deltatime = self
-            .slowmotion
-            .update_and_get_new_deltatime(globals.deltatime);
+            random,
+            random_visual: Random::new_from_seed(seed),
+            infotexts: Vec::new(),
+            infotexts_to_create: Vec::new(),
 
-        let mut infotext_create_buffer: Vec<InfoText> = Vec::new();
+            debug_terminal,
+            debug_terminal_open: false,
+            debug_requests: Vec::new(),
+            debug_player_invincible: false,
+            debug_draw_enabled: DEBUG_DRAW_ENABLE,
+            debug_quicksave: None,
+            debug_colliding_entities: HashSet::new(),
 
-        //------------------------------------------------------------------------------------------
-        // DRAW GUI
+            replay_mode,
+        }
+    }
 
-        // Score
-        draw.draw_text(
-            &self.score.to_string(),
-            &self.fonts["gui_font"],
-            1.0,
-            Vec2::new(globals.canvas_width - 20.0, 10.0),
-            Vec2::zero(),
-            Some(TextAlignment {
-                x: AlignmentHorizontal::Right,
-                y: AlignmentVertical::Top,
-                origin_is_baseline: false,
-                ignore_whitespace: false,
-            }),
-            None,
-            DEPTH_GUI,
-            COLOR_DEFAULT,
-            ADDITIVITY_NONE,
+    fn create_snapshot(&self) -> StageSnapshot {
+        assert!(
+            self.commands.commands.is_empty(),
+            "Can not take a snapshot while world commands are pending"
         );
+        StageSnapshot {
+            score: self.score,
+            skillpoint_count: self.skillpoint_count,
+            slowmotion: self.slowmotion.clone(),
+            director: self.director.clone(),
+            world: WorldSnapshot::new(&self.world),
+            player: self.player,
+            random: self.random.clone(),
+            random_visual: self.random_visual.clone(),
+        }
+    }
 
-        // Skillpoints
-        draw.draw_text(
-            &format!("{} SP", self.skillpoint_count),
-            &self.fonts["gui_font"],
-            1.0,
-            Vec2::new(20.0, 10.0),
-            Vec2::zero(),
-            Some(TextAlignment {
-                x: AlignmentHorizontal::Left,
-                y: AlignmentVertical::Top,
-                origin_is_baseline: false,
-                ignore_whitespace: false,
-            }),
-            None,
-            DEPTH_GUI,
-            COLOR_SKILL_POINT,
-            ADDITIVITY_NONE,
-        );
+    fn restore_snapshot(&mut self, snapshot: &StageSnapshot) {
+        self.score = snapshot.score;
+        self.skillpoint_count = snapshot.skillpoint_count;
+        self.slowmotion = snapshot.slowmotion.clone();
+        self.director = snapshot.director.clone();
+        let (world, player) = snapshot.world.restore(snapshot.player);
+        self.world = world;
+        self.commands = WorldCommandBuffer::new();
+        self.player = player;
+        self.random = snapshot.random.clone();
+        self.random_visual = snapshot.random_visual.clone();
+    }
 
-        // Bars
-        fn draw_bar(
-            draw: &mut Drawstate,
-            font: &SpriteFont,
-            text_title: &str,
-            text_value: &str,
-            bar_color: Color,
-            bar_center: Vec2,
-            bar_width: f32,
-            bar_height: f32,
-            bar_filled_percentage: f32,
-            swap_title_and_value_text_pos: bool,
-        ) {
-            let bar_left_top = bar_center - Vec2::new(bar_width, bar_height) / 2.0;
-            draw.draw_rect(
-                Rect::from_pos_width_height(
-                    bar_left_top,
-                    bar_width * bar_filled_percentage,
-                    bar_height,
-                ),
-                true,
-                DEPTH_GUI,
-                bar_color,
-                ADDITIVITY_NONE,
-            );
-            draw.draw_rect(
-                Rect::from_pos_width_height(bar_left_top, bar_width, bar_height),
-                false,
-                DEPTH_GUI,
-                Color::new(
-                    bar_color.r - (32.0 / 255.0),
-                    bar_color.g - (32.0 / 255.0),
-                    bar_color.b - (32.0 / 255.0),
-                    1.0,
-                ),
-                ADDITIVITY_NONE,
-            );
+    pub fn get_skillpoints_earned(&self) -> usize {
+        self.skillpoint_count
+    }
+
+    pub fn get_score(&self) -> usize {
+        self.score
+    }
 
-            let mut pos_title = bar_center - Vec2::filled_y(8.0);
-            let mut pos_value = bar_center + Vec2::filled_y(8.0);
-            if swap_title_and_value_text_pos {
-                std::mem::swap(&mut pos_title, &mut pos_value);
-            }
+    pub fn get_difficulty(&self) -> usize {
+        self.director.difficulty
+    }
 
-            draw.draw_text(
-                text_title,
-                font,
-                1.0,
-                pos_title,
-                Vec2::zero(),
-                Some(TextAlignment {
-                    x: AlignmentHorizontal::Center,
-                    y: AlignmentVertical::Center,
-                    origin_is_baseline: false,
-                    ignore_whitespace: true,
-                }),
-                None,
-                DEPTH_GUI,
-                bar_color,
-                ADDITIVITY_NONE,
-            );
-            draw.draw_text(
-                text_value,
-                font,
-                1.0,
-                pos_value,
-                Vec2::zero(),
-                Some(TextAlignment {
-                    x: AlignmentHorizontal::Center,
-                    y: AlignmentVertical::Center,
-                    origin_is_baseline: false,
-                    ignore_whitespace: true,
-                }),
-                None,
-                DEPTH_GUI,
-                bar_color,
-                ADDITIVITY_NONE,
-            );
+    pub fn is_player_alive(&self) -> bool {
+        self.world.get::<Player>(self.player).is_ok()
+    }
+
+    pub fn is_debug_terminal_open(&self) -> bool {
+        self.debug_terminal_open
+    }
+
+    pub fn is_replay_playback(&self) -> bool {
+        match self.replay_mode {
+            ReplayMode::Recording(_) => false,
+            ReplayMode::Playback { .. } => true,
         }
+    }
 
-        let (
-            player_hp,
-            player_hp_max,
-            player_boost,
-            player_boost_max,
-            player_ammo,
-            player_ammo_max,
-            player_cycle_percentage,
-        ) = if let Some(player) = self.world.get::<Player>(self.player).ok() {
-            (
-                player.hp,
-                player.hp_max,
-                player.boost,
-                player.boost_max,
-                player.ammo,
-                player.ammo_max,
-                player.cycle_timer.completion_ratio(),
-            )
-        } else {
-            (0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+    /// Whether a playback has run out of recorded frames. Always false while recording.
+    pub fn is_replay_finished(&self) -> bool {
+        match &self.replay_mode {
+            ReplayMode::Recording(_) => false,
+            ReplayMode::Playback {
+                replay,
+                frame_index,
+                ..
+            } => *frame_index >= replay.frames.len(),
+        }
+    }
+
+    /// The first frame at which a playback diverged from the recorded run
+    pub fn get_replay_desync_frame(&self) -> Option<usize> {
+        match self.replay_mode {
+            ReplayMode::Recording(_) => None,
+            ReplayMode::Playback { desync_frame, .. } => desync_frame,
+        }
+    }
+
+    /// Returns the replay of the run so far unless we are playing one back
+    pub fn get_replay_recorded(&self) -> Option<&Replay> {
+        match &self.replay_mode {
+            ReplayMode::Recording(replay) => Some(replay),
+            ReplayMode::Playback { .. } => None,
+        }
+    }
+
+    /// Covers everything that decides how a run goes so that replays notice when they desync
+    fn compute_checksum(&self) -> u64 {
+        let mut checksum = Checksum::default();
+        checksum.add_u64(self.score as u64);
+        checksum.add_u64(self.skillpoint_count as u64);
+        checksum.add_u64(self.director.difficulty as u64);
+        if let (Ok(xform), Ok(player)) = (
+            self.world.get::<Transform>(self.player),
+            self.world.get::<Player>(self.player),
+        ) {
+            checksum.add_vec2(xform.pos);
+            checksum.add_f32(xform.dir_angle);
+            checksum.add_f32(player.hp);
+            checksum.add_f32(player.ammo);
+            checksum.add_f32(player.boost);
+        }
+        for (_entity, (xform, enemy)) in &mut self.world.query::<(&Transform, &Enemy)>() {
+            checksum.add_vec2(xform.pos);
+            checksum.add_f32(enemy.hp);
+        }
+        checksum.finish()
+    }
+
+    /// Advances the stage by one frame without drawing anything. `input_frame` is what the player
+    /// pressed this frame and gets recorded. During playback it is ignored in favour of the replay.
+    pub fn update(
+        &mut self,
+        input_frame: ReplayFrame,
+        context: &mut StageContext,
+        out_game_events: &mut Vec<GameEvent>,
+    ) {
+        //------------------------------------------------------------------------------------------
+        // REPLAY
+
+        // NOTE: Gameplay reads its input only from this frame so that a playback does exactly what
+        //       the recorded run did. Debug commands are not recorded and desync a replay.
+        let replay_frame = match &mut self.replay_mode {
+            ReplayMode::Recording(replay) => {
+                replay.frames.push(input_frame);
+                input_frame
+            }
+            ReplayMode::Playback {
+                replay,
+                frame_index,
+                ..
+            } => {
+                // After the last frame the stage keeps running without any input
+                let frame = replay
+                    .frames
+                    .get(*frame_index)
+                    .cloned()
+                    .unwrap_or_else(|| ReplayFrame::new(context.deltatime));
+                *frame_index += 1;
+                context.deltatime = frame.deltatime;
+                frame
+            }
         };
 
-        let bar_width = 48.0;
-        let bar_height = 4.0;
+        if replay_frame.recently_pressed(ReplayKey::S) {
+            let screen_shake = ModulatorScreenShake::new(&mut self.random_visual, 4.0, 1.0, 60.0);
+            context.camera.add_shake(screen_shake);
+        }
 
-        draw_bar(
-            draw,
-            &self.fonts["gui_font"],
-            "AMMO",
-            &format!("{}/{}", roundi(player_ammo), roundi(player_ammo_max)),
-            COLOR_AMMO,
-            Vec2::new(globals.canvas_width / 2.0 - (bar_width / 2.0 + 4.0), 16.0),
-            bar_width,
-            bar_height,
-            player_ammo / player_ammo_max,
-            true,
-        );
-        draw_bar(
-            draw,
-            &self.fonts["gui_font"],
-            "BOOST",
-            &format!("{}/{}", roundi(player_boost), roundi(player_boost_max)),
-            COLOR_BOOST,
-            Vec2::new(globals.canvas_width / 2.0 + (bar_width / 2.0 + 4.0), 16.0),
-            bar_width,
-            bar_height,
-            player_boost / player_boost_max,
-            true,
-        );
-        draw_bar(
-            draw,
-            &self.fonts["gui_font"],
-            "HP",
-            &format!("{}/{}", roundi(player_hp), roundi(player_hp_max)),
-            COLOR_HP,
-            Vec2::new(
-                globals.canvas_width / 2.0 - (bar_width / 2.0 + 4.0),
-                globals.canvas_height - 16.0,
-            ),
-            bar_width,
-            bar_height,
-            player_hp / player_hp_max,
-            false,
-        );
-        draw_bar(
-            draw,
-            &self.fonts["gui_font"],
-            "CYCLE",
-            "",
-            COLOR_DEFAULT,
-            Vec2::new(
-                globals.canvas_width / 2.0 + (bar_width / 2.0 + 4.0),
-                globals.canvas_height - 16.0,
-            ),
-            bar_width,
-            bar_height,
-            player_cycle_percentage,
-            false,
-        );
+        let deltatime = self
+            .slowmotion
+            .update_and_get_new_deltatime(context.deltatime);
 
-        let round_percentage = self.director.timer_round.completion_ratio();
-        draw_bar(
-            draw,
-            &self.fonts["gui_font"],
-            "DIFFICULTY",
-            &self.director.difficulty.to_string(),
-            COLOR_NEGATIVE_AMMO,
-            Vec2::new(bar_width, globals.canvas_height - 16.0),
-            bar_width,
-            bar_height,
-            round_percentage,
-            false,
-        );
+        let mut infotext_create_buffer: Vec<InfoText> = Vec::new();
+        let mut enemy_damage_buffer: Vec<(Entity, f32)> = Vec::new();
+        let mut proc_trigger_buffer: Vec<ProcTrigger> = Vec::new();
 
         //------------------------------------------------------------------------------------------
         // RESTART GAME
 
         if self.world.get::<Player>(self.player).is_err() {
-            let canvas_center =
-                Rect::from_width_height(globals.canvas_width, globals.canvas_height).center();
-            draw.draw_text(
-                "PRESS ANY KEY TO RESTART",
-                &self.fonts["gui_font"],
-                1.0,
-                canvas_center,
-                Vec2::zero(),
-                Some(TextAlignment {
-                    x: AlignmentHorizontal::Center,
-                    y: AlignmentVertical::Center,
-                    origin_is_baseline: false,
-                    ignore_whitespace: true,
-                }),
-                None,
-                DEPTH_GUI,
-                COLOR_DEFAULT,
-                ADDITIVITY_NONE,
-            );
-            if input.keyboard.has_press_event {
+            if replay_frame.recently_pressed(ReplayKey::T) {
+                out_game_events.push(GameEvent::SwitchToScene {
+                    scene_name: "skilltree".to_string(),
+                })
+            } else if replay_frame.recently_pressed(ReplayKey::C) {
+                out_game_events.push(GameEvent::SwitchToScene {
+                    scene_name: "console".to_string(),
+                })
+            } else if replay_frame.any_key_pressed {
                 out_game_events.push(GameEvent::SwitchToScene {
                     scene_name: "stage".to_string(),
                 })
@@ -2753,9 +4755,10 @@ impl Scene for SceneStage {
         //------------------------------------------------------------------------------------------
         // UPDATE DIRECTOR
 
-        let (enemy_to_spawn, resource_to_spawn, attack_to_spawn) = self
+        let boss_alive = self.world.query::<&Boss>().iter().next().is_some();
+        let (enemy_to_spawn, resource_to_spawn, spawn_attack) = self
             .director
-            .update_and_spawn_enemies_and_collectibles(&mut globals.random, deltatime);
+            .update_and_spawn_enemies_and_collectibles(&mut self.random, deltatime, boss_alive);
 
         fn create_spawn_pos_vel(
             random: &mut Random,
@@ -2774,44 +4777,89 @@ impl Scene for SceneStage {
             (pos, vel)
         }
 
+        fn spawn_enemy(
+            world: &mut World,
+            random: &mut Random,
+            enemytype: EnemyType,
+            pos: Vec2,
+            vel: Vec2,
+            player_entity: Entity,
+        ) {
+            match enemytype {
+                EnemyType::Rock => {
+                    world.spawn(Archetypes::new_enemy_rock(random, pos, vel, 8.0));
+                }
+                EnemyType::Shooter => {
+                    world.spawn(Archetypes::new_enemy_shooter(pos, vel));
+                }
+                EnemyType::BigRock => {
+                    world.spawn(Archetypes::new_enemy_big_rock(random, pos, vel));
+                }
+                EnemyType::Waver => {
+                    world.spawn(Archetypes::new_enemy_waver(pos, vel));
+                }
+                EnemyType::Seeker => {
+                    world.spawn(Archetypes::new_enemy_seeker(pos, vel, player_entity));
+                }
+                EnemyType::Orbitter => {
+                    let orbitter = world.spawn(Archetypes::new_enemy_orbitter(pos, vel));
+                    for index in 0..ORBITTER_SATELLITE_COUNT {
+                        let angle = index as f32 * 360.0 / ORBITTER_SATELLITE_COUNT as f32;
+                        world.spawn(Archetypes::new_enemy_satellite(orbitter, pos, angle));
+                    }
+                }
+                EnemyType::Sapper => {
+                    world.spawn(Archetypes::new_enemy_sapper(pos, vel, player_entity));
+                }
+                EnemyType::Trailer => {
+                    world.spawn(Archetypes::new_enemy_trailer(pos, vel));
+                }
+                EnemyType::Boss => {
+                    let boss = world.spawn(Archetypes::new_boss(pos, vel));
+                    for &angle in &BOSS_ARMOR_ANGLES {
+                        world.spawn(Archetypes::new_boss_armor(boss, pos, angle));
+                    }
+                }
+            }
+        }
+
         // Spawn enemies
         if let Some(enemytype) = enemy_to_spawn {
             let (pos, vel) = create_spawn_pos_vel(
-                &mut globals.random,
-                globals.canvas_width,
-                globals.canvas_height,
+                &mut self.random,
+                context.canvas_width,
+                context.canvas_height,
+            );
+            spawn_enemy(
+                &mut self.world,
+                &mut self.random,
+                enemytype,
+                pos,
+                vel,
+                self.player,
             );
-            match enemytype {
-                EnemyType::Rock => self.world.spawn(Archetypes::new_enemy_rock(
-                    &mut globals.random,
-                    pos,
-                    vel,
-                    8.0,
-                )),
-                EnemyType::Shooter => self.world.spawn(Archetypes::new_enemy_shooter(pos, vel)),
-            };
         }
 
         // Spawn resources
         if let Some(resourcetype) = resource_to_spawn {
             let (pos, vel) = create_spawn_pos_vel(
-                &mut globals.random,
-                globals.canvas_width,
-                globals.canvas_height,
+                &mut self.random,
+                context.canvas_width,
+                context.canvas_height,
             );
             match resourcetype {
                 ResourceType::Boost => self.world.spawn(Archetypes::new_boost_collectible(
                     pos,
                     vel,
-                    globals.random.f32_in_range_closed(0.0, 360.0),
-                    globals.random.f32_in_range_closed(-360.0, 360.0),
+                    self.random.f32_in_range_closed(0.0, 360.0),
+                    self.random.f32_in_range_closed(-360.0, 360.0),
                 )),
                 ResourceType::Skillpoint => {
                     self.world.spawn(Archetypes::new_skillpoints_collectible(
                         pos,
                         vel,
-                        globals.random.f32_in_range_closed(0.0, 360.0),
-                        globals.random.f32_in_range_closed(-360.0, 360.0),
+                        self.random.f32_in_range_closed(0.0, 360.0),
+                        self.random.f32_in_range_closed(-360.0, 360.0),
                     ))
                 }
                 ResourceType::Health => self.world.spawn(Archetypes::new_hp_collectible(pos, vel)),
@@ -2819,28 +4867,57 @@ impl Scene for SceneStage {
         }
 
         // Spawn attacks
-        if let Some(attacktype) = attack_to_spawn {
+        if spawn_attack {
+            let attacktypes: Vec<AttackType> = AttackType::iter().skip(1).collect();
+            let attacktype = self.random.pick_from_slice(&attacktypes);
             let (pos, vel) = create_spawn_pos_vel(
-                &mut globals.random,
-                globals.canvas_width,
-                globals.canvas_height,
+                &mut self.random,
+                context.canvas_width,
+                context.canvas_height,
             );
             self.world
                 .spawn(Archetypes::new_attack_collectible(pos, vel, attacktype));
         }
 
+        // Spawn debug requests
+        for request in self.debug_requests.drain(..) {
+            match request {
+                DebugRequest::SpawnEnemy { enemytype, pos } => {
+                    // Enemies only get removed when they leave the canvas in their direction
+                    // of travel so we send them towards the center
+                    let dir = if pos.x < context.canvas_width / 2.0 {
+                        1.0
+                    } else {
+                        -1.0
+                    };
+                    let vel = Vec2::filled_x(dir * 30.0);
+                    spawn_enemy(
+                        &mut self.world,
+                        &mut self.random,
+                        enemytype,
+                        pos,
+                        vel,
+                        self.player,
+                    );
+                }
+                DebugRequest::SetDifficulty(difficulty) => {
+                    self.director.restart_round(&mut self.random, difficulty);
+                }
+            }
+        }
+
         //------------------------------------------------------------------------------------------
         // SPAWN AMMO
 
-        if input.keyboard.is_down(Scancode::A) {
+        if replay_frame.is_down(ReplayKey::A) {
             self.world.spawn(Archetypes::new_ammo_collectible(
-                globals.random.vec2_in_rect(Rect::from_width_height(
-                    globals.canvas_width,
-                    globals.canvas_height,
+                self.random.vec2_in_rect(Rect::from_width_height(
+                    context.canvas_width,
+                    context.canvas_height,
                 )),
-                globals.random.vec2_in_unit_disk() * globals.random.f32_in_range_closed(10.0, 20.0),
-                globals.random.f32_in_range_closed(0.0, 360.0),
-                globals.random.f32_in_range_closed(-360.0, 360.0),
+                self.random.vec2_in_unit_disk() * self.random.f32_in_range_closed(10.0, 20.0),
+                self.random.f32_in_range_closed(0.0, 360.0),
+                self.random.f32_in_range_closed(-360.0, 360.0),
                 self.player,
             ));
         }
@@ -2865,6 +4942,11 @@ impl Scene for SceneStage {
         // UPDATE POSITIONS
 
         // MOTION
+        for (_entity, (xform, continuous)) in
+            &mut self.world.query::<(&Transform, &mut ContinuousCollision)>()
+        {
+            continuous.pos_previous = xform.pos;
+        }
         for (_entity, (xform, motion)) in &mut self.world.query::<(&mut Transform, &Motion)>() {
             xform.pos += motion.vel * deltatime;
             xform.dir_angle += motion.dir_angle_vel * deltatime;
@@ -2876,6 +4958,13 @@ impl Scene for SceneStage {
             }
         }
 
+        // ORBITING
+        for (_entity, (snap, orbit)) in &mut self.world.query::<(&mut SnapToParent, &Orbit)>() {
+            snap.pos_offset = snap
+                .pos_offset
+                .rotated(deg_to_rad(orbit.angle_vel * deltatime));
+        }
+
         // SNAPPING
         for (entity, (xform, snap)) in &mut self.world.query::<(&mut Transform, &SnapToParent)>() {
             if let Some(parent_xform) = self.world.get::<Transform>(snap.parent).ok() {
@@ -2897,73 +4986,188 @@ impl Scene for SceneStage {
             }
         }
 
-        //------------------------------------------------------------------------------------------
-        // COLLISION
-
-        // Clear collisions from last frame
-        for (_entity, collider) in &mut self.world.query::<&mut Collider>() {
-            collider.collisions.clear();
-        }
-
-        // Collect bodies for indexing
-        let bodies: Vec<(Entity, Transform, Collider)> = self
+        // RIGID BODIES
+        //
+        // NOTE: We resolve the contacts on copies of the bodies and write the results back after
+        let (rigidbody_entities, mut rigidbodies): (Vec<Entity>, Vec<Body>) = self
             .world
-            .query::<(&Transform, &Collider)>()
+            .query::<(&Transform, &Motion, &RigidBody)>()
+            .iter()
+            .map(|(entity, (xform, motion, rigidbody))| {
+                let mut body = rigidbody.body;
+                body.pos = xform.pos;
+                body.vel = motion.vel;
+                body.angle = xform.dir_angle;
+                body.angle_vel = motion.dir_angle_vel;
+                (entity, body)
+            })
+            .unzip();
+        let rigidbody_bounds: Vec<BroadphaseBody> = rigidbodies
             .iter()
-            .map(|(entity, (&xform, collider))| (entity, xform, collider.clone()))
+            .map(|body| BroadphaseBody {
+                pos: body.pos,
+                radius: body.shape.compute_bounding_radius(),
+                layers_own: body.layers,
+                layers_affects: body.layers_affects,
+            })
             .collect();
+        for (index_a, index_b) in
+            find_collision_pairs(&rigidbody_bounds, BROADPHASE_CELL_SIZE_DEFAULT)
+        {
+            let mut body_a = rigidbodies[index_a];
+            let mut body_b = rigidbodies[index_b];
+            if let Some(contact) = Body::collide(&body_a, &body_b) {
+                collision_resolve(&mut body_a, &mut body_b, &contact);
+                collision_positional_correction(&mut body_a, &mut body_b, &contact);
+                rigidbodies[index_a] = body_a;
+                rigidbodies[index_b] = body_b;
+            }
+        }
+        for (&entity, body) in rigidbody_entities.iter().zip(rigidbodies.iter()) {
+            let mut xform = self.world.get_mut::<Transform>(entity).unwrap();
+            xform.pos = body.pos;
+            let mut motion = self.world.get_mut::<Motion>(entity).unwrap();
+            motion.vel = body.vel;
+            motion.dir_angle_vel = body.angle_vel;
+        }
 
-        // Broadphase: Find collisions
-        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
-        for index_a in 0..bodies.len() {
-            for index_b in 0..bodies.len() {
-                if index_a == index_b {
-                    continue;
-                }
+        //------------------------------------------------------------------------------------------
+        // COLLISION
 
-                let body_a = &bodies[index_a];
-                let body_b = &bodies[index_b];
+        // Collect bodies for indexing
+        //
+        // NOTE: Bodies with continuous collision get a bounding circle around their whole path of
+        //       the last frame. All other bodies did not move as far as collision is concerned.
+        let mut body_entities: Vec<Entity> = Vec::new();
+        let mut bodies: Vec<BroadphaseBody> = Vec::new();
+        let mut body_paths: Vec<(Circle, Vec2)> = Vec::new();
+        for (entity, (xform, collider)) in &mut self.world.query::<(&Transform, &Collider)>() {
+            let pos_start = self
+                .world
+                .get::<ContinuousCollision>(entity)
+                .map(|continuous| continuous.pos_previous)
+                .unwrap_or(xform.pos);
+            let movement = xform.pos - pos_start;
+
+            body_entities.push(entity);
+            bodies.push(BroadphaseBody {
+                pos: pos_start + 0.5 * movement,
+                radius: collider.radius + 0.5 * movement.magnitude(),
+                layers_own: collider.layers_own,
+                layers_affects: collider.layers_affects,
+            });
+            body_paths.push((
+                Circle {
+                    center: pos_start,
+                    radius: collider.radius,
+                },
+                movement,
+            ));
+        }
 
-                let body_a_entity = body_a.0;
-                let body_b_entity = body_b.0;
+        // Broadphase: Find collisions
+        let pairs = find_collision_pairs(&bodies, BROADPHASE_CELL_SIZE_DEFAULT);
 
-                let body_a_xform = body_a.1;
-                let body_b_xform = body_b.1;
+        // Narrowphase: Find time and point of impact and create events
+        let mut collision_events_unresolved: Vec<(CollisionEvent, f32, Vec2)> = pairs
+            .iter()
+            .filter_map(|&(index_a, index_b)| {
+                let role_a = CollisionRole::from_layers(bodies[index_a].layers_own)?;
+                let role_b = CollisionRole::from_layers(bodies[index_b].layers_own)?;
+                let event = CollisionEvent::from_pair(
+                    (body_entities[index_a], role_a),
+                    (body_entities[index_b], role_b),
+                )?;
+
+                let (circle_a, movement_a) = body_paths[index_a];
+                let (circle_b, movement_b) = body_paths[index_b];
+                let time_of_impact =
+                    sweep_circle_circle(circle_a, movement_a, circle_b, movement_b)?;
+                let center_a = circle_a.center + time_of_impact * movement_a;
+                let center_b = circle_b.center + time_of_impact * movement_b;
+                let point_of_impact = if center_a == center_b {
+                    center_a
+                } else {
+                    center_a + circle_a.radius * (center_b - center_a).normalized()
+                };
 
-                let body_a_collider = &body_a.2;
-                let body_b_collider = &body_b.2;
+                Some((event, time_of_impact, point_of_impact))
+            })
+            .collect();
+        // NOTE: Within the same kind of event we want the earliest hits first so that a projectile
+        //       gets used up by the first thing it touched on its path. The sort is stable so that
+        //       events with equal time of impact stay in broadphase order.
+        collision_events_unresolved.sort_by(
+            |(event_a, time_of_impact_a, _), (event_b, time_of_impact_b, _)| {
+                event_a.priority().cmp(&event_b.priority()).then(
+                    time_of_impact_a
+                        .partial_cmp(time_of_impact_b)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+            },
+        );
 
-                if body_a_collider.layers_own & body_b_collider.layers_affects == 0
-                    && body_b_collider.layers_own & body_a_collider.layers_affects == 0
-                {
-                    continue;
+        // Resolve events
+        //
+        // NOTE: A projectile gets used up at most once per frame. Events with projectiles that are
+        //       already used up get dropped so that the remaining systems never see them. We also
+        //       remember where the projectile was used up.
+        let mut collision_events: Vec<CollisionEvent> = Vec::new();
+        let mut projectiles_used_up: HashMap<Entity, Vec2> = HashMap::new();
+        for (event, _time_of_impact, point_of_impact) in collision_events_unresolved {
+            let accepted = match event {
+                CollisionEvent::PlayerProjectileEnemyProjectile {
+                    projectile,
+                    enemy_projectile,
+                } => {
+                    if projectiles_used_up.contains_key(&projectile)
+                        || projectiles_used_up.contains_key(&enemy_projectile)
+                    {
+                        false
+                    } else {
+                        projectiles_used_up.insert(projectile, point_of_impact);
+                        projectiles_used_up.insert(enemy_projectile, point_of_impact);
+                        true
+                    }
                 }
-
-                if Vec2::distance_squared(body_a_xform.pos, body_b_xform.pos)
-                    < squared(body_a_collider.radius + body_b_collider.radius)
-                {
-                    // Intersection found
-                    if index_a < index_b {
-                        pairs.insert((index_a, index_b));
+                CollisionEvent::PlayerProjectileEnemy { projectile, enemy } => {
+                    if projectiles_used_up.contains_key(&projectile) {
+                        false
+                    } else if self.world.get::<Enemy>(enemy).is_err() {
+                        // Anything on the enemy layer that is not an enemy stops us
+                        projectiles_used_up.insert(projectile, point_of_impact);
+                        true
                     } else {
-                        pairs.insert((index_b, index_a));
+                        let mut projectile_data =
+                            self.world.get_mut::<Projectile>(projectile).unwrap();
+                        if projectile_data.enemies_hit.contains(&enemy) {
+                            false
+                        } else {
+                            projectile_data.enemies_hit.push(enemy);
+                            enemy_damage_buffer.push((enemy, projectile_data.damage));
+                            if projectile_data.modifiers.pierce > 0 {
+                                projectile_data.modifiers.pierce -= 1;
+                            } else {
+                                projectiles_used_up.insert(projectile, point_of_impact);
+                            }
+                            true
+                        }
                     }
                 }
-            }
-        }
-
-        // Resolve collisions
-        for (index_a, index_b) in pairs {
-            let body_a_entity = bodies[index_a].0;
-            let body_b_entity = bodies[index_b].0;
-
-            {
-                let mut collider_a = self.world.get_mut::<Collider>(body_a_entity).unwrap();
-                collider_a.collisions.push(body_b_entity);
-            }
-            {
-                let mut collider_b = self.world.get_mut::<Collider>(body_b_entity).unwrap();
-                collider_b.collisions.push(body_a_entity);
+                CollisionEvent::PlayerEnemyProjectile { projectile, .. } => {
+                    if projectiles_used_up.contains_key(&projectile) {
+                        false
+                    } else {
+                        projectiles_used_up.insert(projectile, point_of_impact);
+                        true
+                    }
+                }
+                CollisionEvent::PlayerEnemy { .. }
+                | CollisionEvent::PlayerHazard { .. }
+                | CollisionEvent::PlayerCollectible { .. } => true,
+            };
+            if accepted {
+                collision_events.push(event);
             }
         }
 
@@ -2986,6 +5190,14 @@ impl Scene for SceneStage {
             }
         }
 
+        // SINE MOTION
+        for (_entity, (motion, sine)) in &mut self.world.query::<(&mut Motion, &mut SineMotion)>() {
+            sine.time += deltatime;
+            let offset = sine.amplitude * f32::sin(deg_to_rad(360.0 * sine.frequency * sine.time));
+            let sideways = sine.vel_base.normalized().rotated(deg_to_rad(90.0));
+            motion.vel = sine.vel_base + offset * sideways;
+        }
+
         //------------------------------------------------------------------------------------------
         // UPDATE ENEMY
 
@@ -2995,7 +5207,10 @@ impl Scene for SceneStage {
                 .query::<(&Transform, &Motion, &mut Enemy, &Collider, &mut Drawable)>()
         {
             if enemy.can_shoot {
-                if enemy.timer_charge.update_and_check(deltatime) {
+                // NOTE: Seekers drop their mines without charging up first
+                if enemy.enemytype != EnemyType::Seeker
+                    && enemy.timer_charge.update_and_check(deltatime)
+                {
                     // Spawn particle emitter
                     let muzzle_pos = xform.pos + enemy.radius * motion.vel.normalized();
                     let muzzle_pos_offset = enemy.radius * motion.vel.normalized();
@@ -3003,15 +5218,15 @@ impl Scene for SceneStage {
                         muzzle_pos,
                         muzzle_pos_offset,
                         entity,
-                        globals.random.f32_in_range_closed(2.0, 3.0),
-                        globals.random.f32_in_range_closed(0.1, 0.3),
-                        globals.random.vec2_in_disk(muzzle_pos, 20.0),
+                        self.random.f32_in_range_closed(2.0, 3.0),
+                        self.random.f32_in_range_closed(0.1, 0.3),
+                        self.random.vec2_in_disk(muzzle_pos, 20.0),
                     ),));
                 }
 
                 if enemy.timer_shoot.update_and_check_if_triggered(deltatime) {
                     // Schedule next shot
-                    let shoot_time = globals.random.f32_in_range_closed(4.0, 6.0);
+                    let shoot_time = self.random.f32_in_range_closed(4.0, 6.0);
                     enemy.timer_shoot = TimerSimple::new_started(shoot_time);
                     enemy.timer_charge =
                         TriggerRepeating::new_with_distinct_triggertimes(shoot_time - 1.0, 0.02);
@@ -3022,18 +5237,46 @@ impl Scene for SceneStage {
                         if let Some(player_xform) = self.world.get::<Transform>(self.player).ok() {
                             player_xform.pos
                         } else {
-                            globals.random.vec2_in_rect(Rect::from_width_height(
-                                globals.canvas_width,
-                                globals.canvas_height,
+                            self.random.vec2_in_rect(Rect::from_width_height(
+                                context.canvas_width,
+                                context.canvas_height,
                             ))
                         };
-                    self.commands.add_entity(Archetypes::new_enemy_projectile(
-                        muzzle_pos,
-                        (player_pos - xform.pos).normalized(),
-                        globals.random.f32_in_range_closed(80.0, 100.0),
-                        4.0,
-                        10.0,
-                    ));
+                    let dir = (player_pos - xform.pos).normalized();
+                    let speed = self.random.f32_in_range_closed(80.0, 100.0);
+                    match enemy.enemytype {
+                        EnemyType::Waver => {
+                            // Two projectiles that wind around each other
+                            for &phase in &[0.0, 0.5] {
+                                let entity = self.world.reserve_entity();
+                                self.commands.add_component_bundle(
+                                    entity,
+                                    Archetypes::new_enemy_projectile(
+                                        muzzle_pos, dir, speed, 4.0, 10.0,
+                                    ),
+                                );
+                                self.commands.add_component(
+                                    entity,
+                                    SineMotion {
+                                        vel_base: speed * dir,
+                                        amplitude: 60.0,
+                                        frequency: 1.0,
+                                        time: phase,
+                                    },
+                                );
+                            }
+                        }
+                        EnemyType::Seeker => {
+                            let drop_pos = xform.pos - enemy.radius * motion.vel.normalized();
+                            self.commands
+                                .add_entity(Archetypes::new_enemy_mine(drop_pos));
+                        }
+                        _ => {
+                            self.commands.add_entity(Archetypes::new_enemy_projectile(
+                                muzzle_pos, dir, speed, 4.0, 10.0,
+                            ));
+                        }
+                    }
                 }
             }
 
@@ -3044,25 +5287,55 @@ impl Scene for SceneStage {
                 drawable.color = COLOR_HP;
             }
 
-            let mut got_hit = false;
-            for &collision_entity in &collider.collisions {
-                if let Some(projectile) = self.world.get::<Projectile>(collision_entity).ok() {
-                    enemy.hp = clampf(enemy.hp - projectile.damage, 0.0, enemy.hp_max);
-                    got_hit = true;
-                }
-            }
-
+            // NOTE: Damage from projectiles is applied in the DAMAGE ENEMIES step
             if enemy.hp == 0.0 {
                 self.score += enemy.score;
+                proc_trigger_buffer.push(ProcTrigger::Kill);
 
                 self.commands.remove_entity(entity);
 
+                if enemy.enemytype == EnemyType::Boss {
+                    self.skillpoint_count += BOSS_SKILLPOINT_REWARD;
+                    infotext_create_buffer.push(InfoText::new(
+                        xform.pos,
+                        &format!("+{} SP", BOSS_SKILLPOINT_REWARD),
+                        COLOR_SKILL_POINT,
+                    ));
+                    if self.screenshake_enabled {
+                        let screen_shake =
+                            ModulatorScreenShake::new(&mut self.random_visual, 8.0, 0.5, 60.0);
+                        context.camera.add_shake(screen_shake);
+                    }
+                    self.commands.add_entity(Archetypes::new_hit_effect_round(
+                        xform.pos,
+                        4.0 * enemy.radius,
+                        COLOR_DEFAULT,
+                        0.1,
+                        COLOR_SKILL_POINT,
+                        0.3,
+                        true,
+                    ));
+                }
+
+                if enemy.enemytype == EnemyType::BigRock {
+                    for index in 0..BIG_ROCK_SPLIT_COUNT {
+                        let angle = 45.0 + index as f32 * 360.0 / BIG_ROCK_SPLIT_COUNT as f32;
+                        let dir = Vec2::from_angle_flipped_y(deg_to_rad(angle));
+                        let speed = self.random.f32_in_range_closed(20.0, 40.0);
+                        self.commands.add_entity(Archetypes::new_enemy_rock(
+                            &mut self.random,
+                            xform.pos + 0.5 * enemy.radius * dir,
+                            speed * dir,
+                            8.0,
+                        ));
+                    }
+                }
+
                 self.commands.add_entity(Archetypes::new_ammo_collectible(
-                    xform.pos + enemy.radius * globals.random.vec2_in_unit_disk(),
-                    globals.random.vec2_in_unit_disk()
-                        * globals.random.f32_in_range_closed(10.0, 20.0),
-                    globals.random.f32_in_range_closed(0.0, 360.0),
-                    globals.random.f32_in_range_closed(-360.0, 360.0),
+                    xform.pos + enemy.radius * self.random.vec2_in_unit_disk(),
+                    self.random.vec2_in_unit_disk() * self.random.f32_in_range_closed(10.0, 20.0),
+                    self.random.f32_in_range_closed(0.0, 360.0),
+                    self.random.f32_in_range_closed(-360.0, 360.0),
                     self.player,
                 ));
                 self.commands.add_entity(Archetypes::new_hit_effect(
@@ -3076,14 +5349,10 @@ impl Scene for SceneStage {
                     0.15,
                     true,
                 ));
-            } else {
-                if got_hit {
-                    enemy.hitflash_timer.restart();
-                }
             }
 
             // Remove self when leaving screen
-            if motion.vel.x > 0.0 && xform.pos.x >= globals.canvas_width + 2.0 * enemy.radius {
+            if motion.vel.x > 0.0 && xform.pos.x >= context.canvas_width + 2.0 * enemy.radius {
                 self.commands.remove_entity(entity);
             }
             if motion.vel.x < 0.0 && xform.pos.x < -2.0 * enemy.radius {
@@ -3091,16 +5360,191 @@ impl Scene for SceneStage {
             }
         }
 
+        for (_entity, (xform, emitter)) in
+            &mut self.world.query::<(&Transform, &mut TrailEmitter)>()
+        {
+            if emitter.timer_segment.update_and_check(deltatime) {
+                self.commands
+                    .add_entity(Archetypes::new_trail_segment(xform.pos));
+            }
+        }
+
+        //------------------------------------------------------------------------------------------
+        // UPDATE BOSS
+
+        let player_pos = self
+            .world
+            .get::<Transform>(self.player)
+            .ok()
+            .map(|player_xform| player_xform.pos);
+        for (boss_entity, (xform, motion, enemy, boss)) in
+            &mut self
+                .world
+                .query::<(&Transform, &mut Motion, &Enemy, &mut Boss)>()
+        {
+            boss.time += deltatime;
+
+            // Hover around in the upper half of the canvas
+            let hover_pos = Vec2::new(
+                context.canvas_width / 2.0 + 80.0 * f32::sin(deg_to_rad(20.0 * boss.time)),
+                context.canvas_height / 3.0 + 20.0 * f32::sin(deg_to_rad(45.0 * boss.time)),
+            );
+            motion.vel = hover_pos - xform.pos;
+
+            // Phases
+            let hp_percentage = enemy.hp / enemy.hp_max;
+            let phase = 1 + BOSS_PHASE_HP_THRESHOLDS
+                .iter()
+                .filter(|&&threshold| hp_percentage < threshold)
+                .count();
+            if phase != boss.phase {
+                boss.phase = phase;
+                boss.timer_attack = TriggerRepeating::new(match phase {
+                    1 => 2.0,
+                    2 => 1.5,
+                    _ => 1.0,
+                });
+                motion.dir_angle_vel = 30.0 * phase as f32;
+
+                infotext_create_buffer.push(InfoText::new(
+                    xform.pos - Vec2::filled_y(2.0 * BOSS_RADIUS),
+                    &format!("PHASE {}", phase),
+                    COLOR_HP,
+                ));
+                self.commands.add_entity(Archetypes::new_hit_effect_round(
+                    xform.pos,
+                    3.0 * BOSS_RADIUS,
+                    COLOR_DEFAULT,
+                    0.1,
+                    COLOR_HP,
+                    0.2,
+                    false,
+                ));
+
+                // NOTE: The armor breaks off when entering the last phase
+                if phase == BOSS_PHASE_HP_THRESHOLDS.len() + 1 {
+                    for (armor_entity, (armor_xform, snap)) in &mut self
+                        .world
+                        .query::<(&Transform, &SnapToParent)>()
+                        .with::<BossArmor>()
+                    {
+                        if snap.parent != boss_entity {
+                            continue;
+                        }
+                        self.commands.remove_entity(armor_entity);
+                        self.commands.add_entity(Archetypes::new_hit_effect(
+                            armor_xform.pos,
+                            12.0,
+                            12.0,
+                            armor_xform.dir_angle,
+                            COLOR_DEFAULT,
+                            0.1,
+                            COLOR_HP,
+                            0.15,
+                            true,
+                        ));
+                    }
+                }
+            }
+
+            // Attacks
+            if boss.timer_attack.update_and_check(deltatime) {
+                boss.attack_count += 1;
+                let attack = match boss.phase {
+                    1 => BossAttack::AimedBurst,
+                    2 => {
+                        if boss.attack_count % 2 == 0 {
+                            BossAttack::AimedBurst
+                        } else {
+                            BossAttack::Ring
+                        }
+                    }
+                    _ => match boss.attack_count % 3 {
+                        0 => BossAttack::WavesAndMinion,
+                        1 => BossAttack::Ring,
+                        _ => BossAttack::AimedBurst,
+                    },
+                };
+
+                let aim_dir = if let Some(player_pos) = player_pos {
+                    (player_pos - xform.pos).normalized()
+                } else {
+                    Vec2::filled_y(1.0)
+                };
+                let speed = 100.0;
+                match attack {
+                    BossAttack::AimedBurst => {
+                        for &angle in &[-15.0, 0.0, 15.0] {
+                            let dir = aim_dir.rotated(deg_to_rad(angle));
+                            self.commands.add_entity(Archetypes::new_enemy_projectile(
+                                xform.pos + BOSS_RADIUS * dir,
+                                dir,
+                                speed,
+                                4.0,
+                                10.0,
+                            ));
+                        }
+                    }
+                    BossAttack::Ring => {
+                        let projectile_count = 16;
+                        let angle_offset = boss.attack_count as f32 * 11.25;
+                        for index in 0..projectile_count {
+                            let angle =
+                                angle_offset + index as f32 * 360.0 / projectile_count as f32;
+                            let dir = Vec2::from_angle_flipped_y(deg_to_rad(angle));
+                            self.commands.add_entity(Archetypes::new_enemy_projectile(
+                                xform.pos + BOSS_RADIUS * dir,
+                                dir,
+                                0.75 * speed,
+                                4.0,
+                                10.0,
+                            ));
+                        }
+                    }
+                    BossAttack::WavesAndMinion => {
+                        for &phase_offset in &[0.0, 0.5] {
+                            let entity = self.world.reserve_entity();
+                            self.commands.add_component_bundle(
+                                entity,
+                                Archetypes::new_enemy_projectile(
+                                    xform.pos + BOSS_RADIUS * aim_dir,
+                                    aim_dir,
+                                    speed,
+                                    4.0,
+                                    10.0,
+                                ),
+                            );
+                            self.commands.add_component(
+                                entity,
+                                SineMotion {
+                                    vel_base: speed * aim_dir,
+                                    amplitude: 60.0,
+                                    frequency: 1.0,
+                                    time: phase_offset,
+                                },
+                            );
+                        }
+                        self.commands.add_entity(Archetypes::new_enemy_seeker(
+                            xform.pos,
+                            -40.0 * aim_dir,
+                            self.player,
+                        ));
+                    }
+                }
+            }
+        }
+
         //------------------------------------------------------------------------------------------
         // UPDATE PLAYER
 
-        for (player_entity, (player_xform, player_motion, player, collider, drawable)) in
+        for (player_entity, (player_xform, player_motion, player, buffs, drawable, collider)) in
             &mut self.world.query::<(
                 &Transform,
                 &mut Motion,
                 &mut Player,
-                &Collider,
+                &mut Buffs,
                 &mut Drawable,
+                &Collider,
             )>()
         {
             player.invincible_timer.update(deltatime);
@@ -3110,93 +5554,121 @@ impl Scene for SceneStage {
                 drawable.visible = true;
             }
 
+            // BUFFS
+            buffs.update(deltatime);
+            let inside_haste_area = self.world.query::<(&Transform, &HasteArea)>().iter().any(
+                |(_entity, (area_xform, area))| {
+                    Vec2::distance_squared(area_xform.pos, player_xform.pos) < squared(area.radius)
+                },
+            );
+            if inside_haste_area {
+                buffs.add(BuffType::HasteArea);
+            }
+            let modifiers = buffs.get_stat_modifiers();
+            let damage_multiplier = f32::max(1.0 + modifiers.damage, PLAYER_MULTIPLIER_MIN);
+            let projectile_speed_multiplier = f32::max(
+                player.projectile_speed_multiplier + modifiers.projectile_speed,
+                PLAYER_MULTIPLIER_MIN,
+            );
+
             let mut player_damage: f32 = 0.0;
-            let canvas_rect = Rect::from_width_height(globals.canvas_width, globals.canvas_height);
+            let canvas_rect = Rect::from_width_height(context.canvas_width, context.canvas_height);
             if !canvas_rect.contains_point(player_xform.pos) {
                 player_damage += player.hp_max;
             }
 
-            for &collision_entity in &collider.collisions {
-                if let Some(collectible) = self.world.get::<Collectible>(collision_entity).ok() {
-                    match collectible.collectible {
-                        CollectibleType::Boost => {
-                            self.score += 150;
-                            player.add_boost();
-                        }
-                        CollectibleType::Ammo => {
-                            self.score += 50;
-                            player.add_ammo();
-                        }
-                        CollectibleType::Hp => {
-                            self.score += 100;
-                            player.add_hp();
-                        }
-                        CollectibleType::Skillpoint => {
-                            self.score += 250;
-                            self.skillpoint_count += 1;
-                        }
-                        CollectibleType::Attack(attacktype) => {
-                            self.score += 500;
-                            player.ammo = player.ammo_max;
-                            player.attack = ATTACKS[&attacktype];
-                            player.reload_timer = TriggerRepeating::new(player.attack.reload_time);
+            for event in &collision_events {
+                match *event {
+                    CollisionEvent::PlayerCollectible {
+                        player: player_entity_hit,
+                        collectible: collectible_entity,
+                    } if player_entity_hit == player_entity => {
+                        let collectible =
+                            self.world.get::<Collectible>(collectible_entity).unwrap();
+                        match collectible.collectible {
+                            CollectibleType::Boost => {
+                                self.score += 150;
+                                player.add_boost();
+                            }
+                            CollectibleType::Ammo => {
+                                self.score += 50;
+                                player.add_ammo();
+                            }
+                            CollectibleType::Hp => {
+                                self.score += 100;
+                                player.add_hp();
+                            }
+                            CollectibleType::Skillpoint => {
+                                self.score += 250;
+                                self.skillpoint_count += 1;
+                            }
+                            CollectibleType::Attack(attacktype) => {
+                                self.score += 500;
+                                player.ammo = player.ammo_max;
+                                player.attack = ATTACKS[&attacktype].clone();
+                                player.reload_timer =
+                                    TriggerRepeating::new(player.attack.reload_time);
+                                proc_trigger_buffer.push(ProcTrigger::AttackPickup);
+                            }
                         }
+                        proc_trigger_buffer.push(ProcTrigger::Pickup(collectible.collectible));
                     }
-
-                    for pickup_action in &player.action_on_pickup_chances {
-                        if pickup_action.collectible_type == collectible.collectible
-                            && globals.random.gen_bool(pickup_action.chance as f64 / 100.0)
-                        {
-                            match pickup_action.action {
-                                OneTimeAction::RegainHp => {
-                                    // TODO: The borrowchecker does not let us call player.add_hp() here :(
-                                    player.hp =
-                                        clampf(player.hp + player.hp_gain, 0.0, player.hp_max);
-                                }
-                                OneTimeAction::LaunchHomingProjectile => {
-                                    self.commands.add_entity(Archetypes::new_projectile_homing(
-                                        player_xform.pos,
-                                        globals.random.vec2_in_unit_circle(),
-                                        200.0,
-                                        4.0,
-                                        COLOR_SKILL_POINT,
-                                        100.0,
-                                    ))
-                                }
+                    CollisionEvent::PlayerEnemy {
+                        player: player_entity_hit,
+                        enemy: enemy_entity,
+                    } if player_entity_hit == player_entity => {
+                        if let Some(enemy) = self.world.get::<Enemy>(enemy_entity).ok() {
+                            if enemy.enemytype == EnemyType::Sapper {
+                                // NOTE: Sappers do not hurt but drain us as long as we touch them
+                                let stolen = SAPPER_STEAL_RATE * deltatime;
+                                player.ammo = f32::max(player.ammo - stolen, 0.0);
+                                player.boost = f32::max(player.boost - stolen, 0.0);
+                            } else if !player.invincible_timer.is_running() {
+                                player_damage += ENEMY_CONTACT_DAMAGE;
+                            }
+                        } else if let Some(hazard) = self.world.get::<Hazard>(enemy_entity).ok() {
+                            if !player.invincible_timer.is_running() {
+                                player_damage += hazard.damage;
                             }
-                            // Create infotext
-                            let text_pos = globals
-                                .random
-                                .vec2_in_disk(player_xform.pos, collider.radius);
-                            let text = &pickup_action.action.get_infotext_string();
-                            let text_color = pickup_action.action.get_infotext_color();
-                            infotext_create_buffer.push(InfoText::new(text_pos, text, text_color));
                         }
                     }
-                }
-
-                if let Some(_enemy) = self.world.get::<Enemy>(collision_entity).ok() {
-                    if !player.invincible_timer.is_running() {
-                        player_damage += 30.0;
+                    CollisionEvent::PlayerHazard {
+                        player: player_entity_hit,
+                        hazard: hazard_entity,
+                    } if player_entity_hit == player_entity => {
+                        let hazard = self.world.get::<Hazard>(hazard_entity).unwrap();
+                        if !player.invincible_timer.is_running() {
+                            player_damage += hazard.damage;
+                        }
                     }
-                }
-                if let Some(projectile) = self.world.get::<Projectile>(collision_entity).ok() {
-                    if !player.invincible_timer.is_running() {
-                        player_damage += projectile.damage;
+                    CollisionEvent::PlayerEnemyProjectile {
+                        player: player_entity_hit,
+                        projectile: projectile_entity,
+                    } if player_entity_hit == player_entity => {
+                        let projectile = self.world.get::<Projectile>(projectile_entity).unwrap();
+                        if !player.invincible_timer.is_running() {
+                            player_damage += projectile.damage;
+                        }
                     }
+                    _ => {}
                 }
             }
 
             // BOOST
             let mut boost_active = false;
-            player.speed_max = player.speed_base_max;
+            let speed_base_max = player.speed_base_max
+                * f32::max(
+                    player.movement_speed_multiplier + modifiers.movement_speed,
+                    PLAYER_MULTIPLIER_MIN,
+                );
+            player.speed_max = speed_base_max;
             if player.boost_allowed {
-                if input.keyboard.is_down(Scancode::Up) {
-                    player.speed_max = 1.5 * player.speed_base_max;
+                if replay_frame.is_down(ReplayKey::Up) {
+                    player.speed_max = 1.5 * speed_base_max;
                     boost_active = true;
                 }
-                if input.keyboard.is_down(Scancode::Down) {
-                    player.speed_max = 0.5 * player.speed_base_max;
+                if replay_frame.is_down(ReplayKey::Down) {
+                    player.speed_max = 0.5 * speed_base_max;
                     boost_active = true;
                 }
             } else {
@@ -3205,8 +5677,13 @@ impl Scene for SceneStage {
                     player.boost_allowed = true;
                 }
             }
+            if boost_active && !player.boost_was_active {
+                proc_trigger_buffer.push(ProcTrigger::BoostStart);
+            }
+            player.boost_was_active = boost_active;
             if boost_active {
-                player.boost = f32::max(player.boost - 50.0 * deltatime, 0.0);
+                let boost_drain = 50.0 * f32::max(1.0 + modifiers.boost_drain, 0.0);
+                player.boost = f32::max(player.boost - boost_drain * deltatime, 0.0);
             } else {
                 player.boost = f32::min(player.boost + 10.0 * deltatime, player.boost_max);
             }
@@ -3217,10 +5694,10 @@ impl Scene for SceneStage {
 
             // STEERING
             player_motion.dir_angle_vel = 0.0;
-            if input.keyboard.is_down(Scancode::Left) {
+            if replay_frame.is_down(ReplayKey::Left) {
                 player_motion.dir_angle_vel = player.turn_speed;
             }
-            if input.keyboard.is_down(Scancode::Right) {
+            if replay_frame.is_down(ReplayKey::Right) {
                 player_motion.dir_angle_vel = -player.turn_speed;
             }
             player.speed = f32::min(player.speed + player.acc * deltatime, player.speed_max);
@@ -3232,7 +5709,15 @@ impl Scene for SceneStage {
             let player_scale = Vec2::filled(player.size) / 4.0;
 
             // SHOOTING
-            if player.reload_timer.update_and_check(deltatime) {
+            // NOTE: Higher attack speed makes the reload timer run faster
+            let attack_speed = f32::max(
+                player.attack_speed_multiplier + modifiers.attack_speed,
+                PLAYER_MULTIPLIER_MIN,
+            );
+            if player
+                .reload_timer
+                .update_and_check(attack_speed * deltatime)
+            {
                 player.ammo -= player.attack.ammo_consumption_on_shot;
 
                 // Add muzzleflash
@@ -3262,138 +5747,127 @@ impl Scene for SceneStage {
                 );
                 let muzzle_pos_absolute = shoot_points.first().cloned().unwrap();
 
-                match player.attack.typename {
-                    AttackType::Neutral | AttackType::Rapid => {
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir,
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                    }
-                    AttackType::Double => {
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir.rotated(deg_to_rad(15.0)),
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir.rotated(deg_to_rad(-15.0)),
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                    }
-                    AttackType::Triple => {
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir,
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir.rotated(deg_to_rad(15.0)),
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir.rotated(deg_to_rad(-15.0)),
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                    }
-                    AttackType::Spread => {
-                        let dir_angle_offset = globals.random.f32_in_range_closed(-20.0, 20.0);
-                        let color = globals.random.pick_from_slice(&COLORS_ALL);
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir.rotated(deg_to_rad(dir_angle_offset)),
-                            200.0,
-                            4.0,
-                            color,
-                            100.0,
-                        ));
+                match &player.attack.pattern {
+                    AttackPattern::Projectiles(pattern) => {
+                        let color = if pattern.random_color {
+                            self.random.pick_from_slice(&COLORS_ALL)
+                        } else {
+                            player.attack.color
+                        };
+                        let modifiers = pattern
+                            .modifiers
+                            .combined_with(&player.projectile_modifiers);
+                        for spawn in &pattern.projectiles {
+                            let pos = player_pos
+                                + (muzzle_pos_absolute - player_pos)
+                                    .rotated(deg_to_rad(spawn.spawn_angle_offset));
+                            for _ in 0..spawn.count {
+                                let angle_jitter = self.random.f32_in_range_closed(
+                                    -pattern.angle_jitter,
+                                    pattern.angle_jitter,
+                                );
+                                let dir = player_dir
+                                    .rotated(deg_to_rad(spawn.angle_offset + angle_jitter));
+                                let speed = projectile_speed_multiplier
+                                    * (pattern.speed
+                                        + self.random.f32_in_range_closed(
+                                            -pattern.speed_jitter,
+                                            pattern.speed_jitter,
+                                        ));
+
+                                let mut projectile = Projectile::new(
+                                    player.projectile_size_multiplier * pattern.size,
+                                    color,
+                                    damage_multiplier * pattern.damage,
+                                    pattern.trail,
+                                );
+                                projectile.spin_speed =
+                                    pattern.spin_speed * self.random.pick_from_slice(&[-1.0, 1.0]);
+                                projectile.modifiers = modifiers.clone();
+                                let radius = projectile.size;
+
+                                let entity = self.world.reserve_entity();
+                                if pattern.homing {
+                                    self.commands.add_component_bundle(
+                                        entity,
+                                        Archetypes::new_projectile_homing(
+                                            pos, dir, speed, projectile,
+                                        ),
+                                    );
+                                } else {
+                                    self.commands.add_component_bundle(
+                                        entity,
+                                        Archetypes::new_projectile(pos, dir, speed, projectile),
+                                    );
+                                }
+                                add_continuous_collision_if_fast(
+                                    &mut self.commands,
+                                    entity,
+                                    pos,
+                                    speed,
+                                    radius,
+                                );
+                                if let Some(lifetime) = pattern.lifetime {
+                                    self.commands.add_component(
+                                        entity,
+                                        AutoremoveTimer::new(
+                                            player.projectile_duration_multiplier * lifetime,
+                                        ),
+                                    );
+                                }
+                            }
+                        }
                     }
-                    AttackType::Back => {
-                        let muzzle_pos_absolute_back =
-                            player_pos + (player_pos - muzzle_pos_absolute);
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute,
-                            player_dir,
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute_back,
-                            -player_dir,
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
+                    AttackPattern::Lightning(pattern) => {
+                        let targets =
+                            find_lightning_targets(&self.world, muzzle_pos_absolute, pattern);
+                        let mut bolt_start = muzzle_pos_absolute;
+                        for &(target_entity, target_pos) in &targets {
+                            enemy_damage_buffer
+                                .push((target_entity, damage_multiplier * pattern.damage));
+                            self.commands.add_entity(Archetypes::new_lightning_effect(
+                                bolt_start,
+                                target_pos,
+                                player.attack.color,
+                            ));
+                            self.commands.add_entity(Archetypes::new_hit_effect(
+                                target_pos,
+                                7.0,
+                                7.0,
+                                0.0,
+                                COLOR_DEFAULT,
+                                0.1,
+                                player.attack.color,
+                                0.15,
+                                true,
+                            ));
+                            bolt_start = target_pos;
+                        }
                     }
-                    AttackType::Side => {
-                        let muzzle_pos_absolute_left = player_pos
-                            + (muzzle_pos_absolute - player_pos).rotated(deg_to_rad(90.0));
-                        let muzzle_pos_absolute_right = player_pos
-                            + (muzzle_pos_absolute - player_pos).rotated(deg_to_rad(-90.0));
-                        self.commands.add_entity(Archetypes::new_projectile(
+                    AttackPattern::Laser(pattern) => {
+                        let beam_end = muzzle_pos_absolute + pattern.length * player_dir;
+                        for target_entity in find_enemies_on_line(
+                            &self.world,
                             muzzle_pos_absolute,
-                            player_dir,
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute_left,
-                            player_dir.rotated(deg_to_rad(90.0)),
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                        self.commands.add_entity(Archetypes::new_projectile(
-                            muzzle_pos_absolute_right,
-                            player_dir.rotated(deg_to_rad(-90.0)),
-                            200.0,
-                            4.0,
-                            player.attack.color,
-                            100.0,
-                        ));
-                    }
-                    AttackType::Homing => {
-                        self.commands.add_entity(Archetypes::new_projectile_homing(
+                            beam_end,
+                            pattern.width,
+                        ) {
+                            enemy_damage_buffer
+                                .push((target_entity, damage_multiplier * pattern.damage));
+                        }
+                        self.commands.add_entity(Archetypes::new_laser_effect(
                             muzzle_pos_absolute,
                             player_dir,
-                            200.0,
-                            4.0,
+                            pattern.length,
+                            pattern.width,
                             player.attack.color,
-                            100.0,
                         ));
                     }
                 }
 
                 if player.ammo <= 0.0 {
                     player.ammo = player.ammo_max;
-                    player.attack = ATTACKS[&AttackType::Neutral];
+                    player.attack = ATTACKS[&AttackType::Neutral].clone();
                     player.reload_timer = TriggerRepeating::new(player.attack.reload_time);
                 }
             }
@@ -3410,8 +5884,8 @@ impl Scene for SceneStage {
                 );
 
                 for &point in &exhaust_points {
-                    let size = globals.random.f32_in_range_closed(2.0, 4.0);
-                    let lifetime = globals.random.f32_in_range_closed(0.15, 0.25);
+                    let size = self.random.f32_in_range_closed(2.0, 4.0);
+                    let lifetime = self.random.f32_in_range_closed(0.15, 0.25);
                     let color = if boost_active {
                         COLOR_BOOST
                     } else {
@@ -3431,11 +5905,15 @@ impl Scene for SceneStage {
             // TICK EFFECT
             if player.cycle_timer.update_and_check_if_triggered(deltatime) {
                 player.cycle_timer = TimerSimple::new_started(player.cycle_cooldown);
+                proc_trigger_buffer.push(ProcTrigger::CycleTick);
                 self.commands
                     .add_entity(Archetypes::new_tick_effect(player_entity));
             }
 
             // TAKING DAMAGE
+            if self.debug_player_invincible {
+                player_damage = 0.0;
+            }
             if player_damage > 0.0 {
                 player.hp = clampf(player.hp - player_damage, 0.0, player.hp_max);
 
@@ -3445,6 +5923,7 @@ impl Scene for SceneStage {
                     if player_damage >= 30.0 {
                         player.invincible_timer.restart();
                     }
+                    proc_trigger_buffer.push(ProcTrigger::Hit);
                 }
 
                 let (
@@ -3468,38 +5947,136 @@ impl Scene for SceneStage {
                     }
                 };
 
-                let screen_shake = ModulatorScreenShake::new(
-                    &mut globals.random,
-                    screenshake_amplitude,
-                    screenshake_duration,
-                    screenshake_frequency,
-                );
-                globals.camera.add_shake(screen_shake);
+                if self.screenshake_enabled {
+                    let screen_shake = ModulatorScreenShake::new(
+                        &mut self.random_visual,
+                        screenshake_amplitude,
+                        screenshake_duration,
+                        screenshake_frequency,
+                    );
+                    context.camera.add_shake(screen_shake);
+                }
 
                 self.slowmotion
                     .add_slowmotion(slowmotion_duration, slowmotion_factor);
 
                 self.commands.add_entity(Archetypes::new_screenflash(
-                    globals.canvas_width,
-                    globals.canvas_height,
+                    context.canvas_width,
+                    context.canvas_height,
                     screenflash_framecount,
                 ));
 
-                for _ in 0..globals
+                for _ in 0..self
                     .random
                     .gen_range(particle_count_min, particle_count_max)
                 {
                     self.commands.add_entity(Archetypes::new_explode_particle(
                         player_xform.pos,
-                        rad_to_deg(globals.random.vec2_in_unit_disk().to_angle_flipped_y()),
-                        globals.random.f32_in_range_closed(120.0, 300.0),
-                        globals.random.f32_in_range_closed(1.0, 2.0),
-                        globals.random.f32_in_range_closed(3.0, 15.0),
-                        globals.random.f32_in_range_closed(0.3, 0.5),
+                        rad_to_deg(self.random.vec2_in_unit_disk().to_angle_flipped_y()),
+                        self.random.f32_in_range_closed(120.0, 300.0),
+                        self.random.f32_in_range_closed(1.0, 2.0),
+                        self.random.f32_in_range_closed(3.0, 15.0),
+                        self.random.f32_in_range_closed(0.3, 0.5),
                         COLOR_DEFAULT,
                     ));
                 }
             }
+
+            // PROCS
+            //
+            // NOTE: We roll all procs first so that their actions can freely modify the player
+            let mut actions_triggered = Vec::new();
+            for &trigger in &proc_trigger_buffer {
+                for proc in &player.procs {
+                    if proc.trigger == trigger && self.random.gen_bool(proc.chance as f64 / 100.0) {
+                        actions_triggered.push(proc.action);
+                    }
+                }
+            }
+            if player.hp == 0.0 {
+                actions_triggered.clear();
+            }
+            for action in actions_triggered {
+                match action {
+                    OneTimeAction::RegainHp => player.add_hp(),
+                    OneTimeAction::LaunchHomingProjectile => {
+                        let speed = 200.0;
+                        let projectile = Projectile::new(4.0, COLOR_SKILL_POINT, 100.0, true);
+                        let radius = projectile.size;
+                        let entity = self.world.reserve_entity();
+                        self.commands.add_component_bundle(
+                            entity,
+                            Archetypes::new_projectile_homing(
+                                player_pos,
+                                self.random.vec2_in_unit_circle(),
+                                speed,
+                                projectile,
+                            ),
+                        );
+                        add_continuous_collision_if_fast(
+                            &mut self.commands,
+                            entity,
+                            player_pos,
+                            speed,
+                            radius,
+                        );
+                    }
+                    OneTimeAction::SpawnHasteArea => self
+                        .commands
+                        .add_entity(Archetypes::new_haste_area(player_pos)),
+                    OneTimeAction::AttackSpeedBoost => buffs.add(BuffType::AttackSpeedBoost),
+                    OneTimeAction::MovementSpeedBoost => buffs.add(BuffType::MovementSpeedBoost),
+                    OneTimeAction::DamageBoost => buffs.add(BuffType::DamageBoost),
+                    OneTimeAction::Invulnerability => player.invincible_timer.restart(),
+                    OneTimeAction::Barrage => {
+                        for _ in 0..BARRAGE_PROJECTILE_COUNT {
+                            let angle = self
+                                .random
+                                .f32_in_range_closed(-BARRAGE_SPREAD_ANGLE, BARRAGE_SPREAD_ANGLE);
+                            let speed = 200.0 * projectile_speed_multiplier;
+                            let projectile = Projectile::new(
+                                2.5 * player.projectile_size_multiplier,
+                                COLOR_DEFAULT,
+                                damage_multiplier * 100.0,
+                                false,
+                            );
+                            let radius = projectile.size;
+                            let entity = self.world.reserve_entity();
+                            self.commands.add_component_bundle(
+                                entity,
+                                Archetypes::new_projectile(
+                                    player_pos,
+                                    player_dir.rotated(deg_to_rad(angle)),
+                                    speed,
+                                    projectile,
+                                ),
+                            );
+                            add_continuous_collision_if_fast(
+                                &mut self.commands,
+                                entity,
+                                player_pos,
+                                speed,
+                                radius,
+                            );
+                        }
+                    }
+                    OneTimeAction::SpawnAttack(attacktype) => {
+                        let (pos, vel) = create_spawn_pos_vel(
+                            &mut self.random,
+                            context.canvas_width,
+                            context.canvas_height,
+                        );
+                        self.commands
+                            .add_entity(Archetypes::new_attack_collectible(pos, vel, attacktype));
+                    }
+                }
+
+                // Create infotext
+                let text_pos = self.random.vec2_in_disk(player_pos, collider.radius);
+                let text = &action.get_infotext_string();
+                let text_color = action.get_infotext_color();
+                infotext_create_buffer.push(InfoText::new(text_pos, text, text_color));
+            }
         }
 
         //------------------------------------------------------------------------------------------
@@ -3549,11 +6126,18 @@ impl Scene for SceneStage {
         //------------------------------------------------------------------------------------------
         // UPDATE PROJECTILES
 
-        for (entity, (xform, motion, projectile, collider)) in
+        for (entity, (xform, motion, projectile)) in
             &mut self
                 .world
-                .query::<(&mut Transform, &Motion, &mut Projectile, &Collider)>()
+                .query::<(&mut Transform, &mut Motion, &mut Projectile)>()
         {
+            // Spinning projectiles
+            if projectile.spin_speed != 0.0 {
+                motion.vel = motion
+                    .vel
+                    .rotated(deg_to_rad(projectile.spin_speed * deltatime));
+            }
+
             // Face towards movement direction
             xform.dir_angle = rad_to_deg(motion.vel.to_angle_flipped_y());
 
@@ -3588,8 +6172,8 @@ impl Scene for SceneStage {
 
             // Trail
             if projectile.timer_trail_particles.update_and_check(deltatime) {
-                let size = globals.random.f32_in_range_closed(1.0, projectile.size);
-                let lifetime = globals.random.f32_in_range_closed(0.05, 0.15);
+                let size = self.random.f32_in_range_closed(1.0, projectile.size);
+                let lifetime = self.random.f32_in_range_closed(0.05, 0.15);
                 let color = projectile.color;
                 self.commands.add_entity(Archetypes::new_trailparticle(
                     xform.pos,
@@ -3600,18 +6184,100 @@ impl Scene for SceneStage {
                 ));
             }
 
-            // Remove
+            // NOTE: Hits were already resolved together with the collision events
             let mut explode = false;
-            if !collider.collisions.is_empty() {
+            if let Some(&point_of_impact) = projectiles_used_up.get(&entity) {
+                // Fast projectiles may have already moved past whatever they hit
+                xform.pos = point_of_impact;
                 explode = true;
             }
-            let canvas_rect = Rect::from_width_height(globals.canvas_width, globals.canvas_height);
+            let hit_something = explode;
+
+            // Remove
+            let canvas_rect = Rect::from_width_height(context.canvas_width, context.canvas_height);
             if !canvas_rect.contains_point(xform.pos) {
-                explode = true;
+                if projectile.modifiers.bounces > 0 {
+                    projectile.modifiers.bounces -= 1;
+                    if xform.pos.x < 0.0 || xform.pos.x > context.canvas_width {
+                        motion.vel.x = -motion.vel.x;
+                    }
+                    if xform.pos.y < 0.0 || xform.pos.y > context.canvas_height {
+                        motion.vel.y = -motion.vel.y;
+                    }
+                    xform.pos = xform.pos.clamped_to_rect(canvas_rect);
+                } else {
+                    explode = true;
+                }
             }
             if explode {
                 self.commands.remove_entity(entity);
 
+                if hit_something {
+                    // Split
+                    //
+                    // NOTE: Children keep our remaining pierces and bounces but do not split again
+                    let speed = motion.vel.magnitude();
+                    let dir = motion.vel.normalized();
+                    for &split_angle in &projectile.modifiers.split_angles {
+                        let mut child = Projectile::new(
+                            projectile.size,
+                            projectile.color,
+                            projectile.damage,
+                            false,
+                        );
+                        child.modifiers = ProjectileModifiers {
+                            split_angles: Vec::new(),
+                            ..projectile.modifiers.clone()
+                        };
+                        child.enemies_hit = projectile.enemies_hit.clone();
+                        let child_entity = self.world.reserve_entity();
+                        self.commands.add_component_bundle(
+                            child_entity,
+                            Archetypes::new_projectile(
+                                xform.pos,
+                                dir.rotated(deg_to_rad(split_angle)),
+                                speed,
+                                child,
+                            ),
+                        );
+                        add_continuous_collision_if_fast(
+                            &mut self.commands,
+                            child_entity,
+                            xform.pos,
+                            speed,
+                            projectile.size,
+                        );
+                    }
+
+                    // Area damage
+                    let explosion_radius = projectile.modifiers.explosion_radius;
+                    if explosion_radius > 0.0 {
+                        for (enemy_entity, distance) in
+                            find_enemies_in_radius(&self.world, xform.pos, explosion_radius)
+                        {
+                            // NOTE: Enemies we directly hit already took our damage
+                            if projectile.enemies_hit.contains(&enemy_entity) {
+                                continue;
+                            }
+                            let falloff = lerp(
+                                1.0,
+                                EXPLOSION_DAMAGE_PERCENTAGE_AT_EDGE,
+                                distance / explosion_radius,
+                            );
+                            enemy_damage_buffer.push((enemy_entity, falloff * projectile.damage));
+                        }
+                        self.commands.add_entity(Archetypes::new_hit_effect_round(
+                            xform.pos,
+                            explosion_radius,
+                            COLOR_DEFAULT,
+                            0.1,
+                            projectile.color,
+                            0.15,
+                            true,
+                        ));
+                    }
+                }
+
                 self.commands.add_entity(Archetypes::new_hit_effect(
                     xform.pos.clamped_to_rect(canvas_rect),
                     7.0,
@@ -3626,9 +6292,47 @@ impl Scene for SceneStage {
             }
         }
 
+        //------------------------------------------------------------------------------------------
+        // DAMAGE ENEMIES
+
+        // NOTE: This collects damage from projectile hits, explosions, lightning and lasers. Dead
+        //       enemies get removed on the next update.
+        // NOTE: Orbitters are shielded as long as any of their satellites live
+        let shielded_enemies: HashSet<Entity> = self
+            .world
+            .query::<&SnapToParent>()
+            .with::<Orbit>()
+            .iter()
+            .map(|(_entity, snap)| snap.parent)
+            .collect();
+        for (enemy_entity, damage) in enemy_damage_buffer.drain(..) {
+            if shielded_enemies.contains(&enemy_entity) {
+                continue;
+            }
+            if let Some(mut enemy) = self.world.get_mut::<Enemy>(enemy_entity).ok() {
+                enemy.hp = clampf(enemy.hp - damage, 0.0, enemy.hp_max);
+                enemy.hitflash_timer.restart();
+            }
+        }
+
         //------------------------------------------------------------------------------------------
         // UPDATE COLLECTIBLES
 
+        self.debug_colliding_entities = collision_events
+            .iter()
+            .flat_map(|event| {
+                let (entity_a, entity_b) = event.get_entities();
+                vec![entity_a, entity_b]
+            })
+            .collect();
+
+        let collectibles_collected: HashSet<Entity> = collision_events
+            .iter()
+            .filter_map(|event| match *event {
+                CollisionEvent::PlayerCollectible { collectible, .. } => Some(collectible),
+                _ => None,
+            })
+            .collect();
         for (entity, (xform, motion, collectible, collider)) in
             &mut self
                 .world
@@ -3638,7 +6342,7 @@ impl Scene for SceneStage {
             let mut collected = false;
 
             // Check if collectible needs to be removed from game
-            if !collider.collisions.is_empty() {
+            if collectibles_collected.contains(&entity) {
                 remove_self = true;
                 collected = true;
             }
@@ -3646,14 +6350,14 @@ impl Scene for SceneStage {
                 CollectibleType::Ammo => {
                     // Follower collectibles
                     let canvas_rect =
-                        Rect::from_width_height(globals.canvas_width, globals.canvas_height);
+                        Rect::from_width_height(context.canvas_width, context.canvas_height);
                     if !canvas_rect.contains_point(xform.pos) {
                         remove_self = true;
                     }
                 }
                 _ => {
                     // Horizontal moving collectibles
-                    if motion.vel.x > 0.0 && xform.pos.x >= globals.canvas_width {
+                    if motion.vel.x > 0.0 && xform.pos.x >= context.canvas_width {
                         remove_self = true;
                     }
                     if motion.vel.x < 0.0 && xform.pos.x < 0.0 {
@@ -3669,14 +6373,14 @@ impl Scene for SceneStage {
                 match collectible.collectible {
                     CollectibleType::Attack(_) => {}
                     _ => {
-                        for _ in 0..globals.random.gen_range(4, 8) {
+                        for _ in 0..self.random.gen_range(4, 8) {
                             self.commands.add_entity(Archetypes::new_explode_particle(
                                 xform.pos,
-                                rad_to_deg(globals.random.vec2_in_unit_disk().to_angle_flipped_y()),
-                                globals.random.f32_in_range_closed(50.0, 100.0),
-                                globals.random.f32_in_range_closed(1.0, 2.0),
-                                globals.random.f32_in_range_closed(3.0, 8.0),
-                                globals.random.f32_in_range_closed(0.3, 0.5),
+                                rad_to_deg(self.random.vec2_in_unit_disk().to_angle_flipped_y()),
+                                self.random.f32_in_range_closed(50.0, 100.0),
+                                self.random.f32_in_range_closed(1.0, 2.0),
+                                self.random.f32_in_range_closed(3.0, 8.0),
+                                self.random.f32_in_range_closed(0.3, 0.5),
                                 collectible.color,
                             ));
                         }
@@ -3699,7 +6403,7 @@ impl Scene for SceneStage {
                 } else {
                     // Create collect effect
                     let text = collectible.collectible.get_infotext_string();
-                    let text_pos = globals.random.vec2_in_disk(xform.pos, collider.radius);
+                    let text_pos = self.random.vec2_in_disk(xform.pos, collider.radius);
                     infotext_create_buffer.push(InfoText::new(text_pos, text, collectible.color));
 
                     match collectible.collectible {
@@ -3942,25 +6646,457 @@ impl Scene for SceneStage {
         //------------------------------------------------------------------------------------------
         // TWEENERS
 
-        for (_entity, (tween_scale, drawable)) in
-            &mut self.world.query::<(&mut TweenScale, &mut Drawable)>()
-        {
-            tween_scale.update(drawable, deltatime);
+        for (_entity, (tween_scale, drawable)) in
+            &mut self.world.query::<(&mut TweenScale, &mut Drawable)>()
+        {
+            tween_scale.update(drawable, deltatime);
+        }
+
+        for (_entity, (tween_color, drawable)) in
+            &mut self.world.query::<(&mut TweenColor, &mut Drawable)>()
+        {
+            tween_color.update(drawable, deltatime);
+        }
+
+        //------------------------------------------------------------------------------------------
+        // BLINKER
+
+        for (_entity, (blinker, drawable)) in
+            &mut self.world.query::<(&mut Blinker, &mut Drawable)>()
+        {
+            drawable.visible = blinker.update_and_check(deltatime);
+        }
+
+        //------------------------------------------------------------------------------------------
+        // ENEMY CHARGE PARTICLES
+
+        for (entity, particle) in &mut self.world.query::<&mut EnemyChargeParticle>() {
+            if particle.timer.update_and_check_if_triggered(deltatime) {
+                self.commands.remove_entity(entity);
+            }
+
+            if let Some(muzzle_entity_xform) =
+                self.world.get::<Transform>(particle.muzzle_entity).ok()
+            {
+                particle.muzzle_pos = muzzle_entity_xform.pos + particle.muzzle_pos_offset;
+            }
+        }
+
+        //------------------------------------------------------------------------------------------
+        // INFOTEXT
+
+        let random_visual = &mut self.random_visual;
+        self.infotexts.retain_mut(|infotext| {
+            !infotext.update_and_check_if_finished(random_visual, deltatime)
+        });
+
+        // NOTE: Placing infotexts needs the font metrics so they are only created when drawing
+        self.infotexts_to_create = infotext_create_buffer;
+
+        self.commands.execute(&mut self.world);
+
+        //------------------------------------------------------------------------------------------
+        // REPLAY CHECKSUM
+
+        let frame_count = match &self.replay_mode {
+            ReplayMode::Recording(replay) => replay.frames.len(),
+            ReplayMode::Playback { frame_index, .. } => *frame_index,
+        };
+        if frame_count % REPLAY_CHECKSUM_INTERVAL == 0 {
+            let checksum = self.compute_checksum();
+            match &mut self.replay_mode {
+                ReplayMode::Recording(replay) => replay.checksums.push(checksum),
+                ReplayMode::Playback {
+                    replay,
+                    desync_frame,
+                    ..
+                } => {
+                    let checksum_index = frame_count / REPLAY_CHECKSUM_INTERVAL - 1;
+                    if let Some(&checksum_recorded) = replay.checksums.get(checksum_index) {
+                        if checksum != checksum_recorded && desync_frame.is_none() {
+                            log::warn!("Replay desynced at frame {}", frame_count);
+                            *desync_frame = Some(frame_count);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the current state. Besides placing new infotexts, which needs font metrics, it does not
+    /// touch the simulation. Only works for stages that were not created headless as we need fonts
+    /// for drawing
+    pub fn draw(&mut self, draw: &mut Drawstate, globals: &Globals) {
+        draw.set_clear_color_and_depth(COLOR_BACKGROUND, DEPTH_BACKGROUND);
+
+        //------------------------------------------------------------------------------------------
+        // DRAW GUI
+
+        // Score
+        draw.draw_text(
+            &self.score.to_string(),
+            &self.fonts["gui_font"],
+            1.0,
+            Vec2::new(globals.canvas_width - 20.0, 10.0),
+            Vec2::zero(),
+            Some(TextAlignment {
+                x: AlignmentHorizontal::Right,
+                y: AlignmentVertical::Top,
+                origin_is_baseline: false,
+                ignore_whitespace: false,
+            }),
+            None,
+            DEPTH_GUI,
+            COLOR_DEFAULT,
+            ADDITIVITY_NONE,
+        );
+
+        // Skillpoints
+        draw.draw_text(
+            &format!("{} SP", self.skillpoint_count),
+            &self.fonts["gui_font"],
+            1.0,
+            Vec2::new(20.0, 10.0),
+            Vec2::zero(),
+            Some(TextAlignment {
+                x: AlignmentHorizontal::Left,
+                y: AlignmentVertical::Top,
+                origin_is_baseline: false,
+                ignore_whitespace: false,
+            }),
+            None,
+            DEPTH_GUI,
+            COLOR_SKILL_POINT,
+            ADDITIVITY_NONE,
+        );
+
+        // Bars
+        fn draw_bar(
+            draw: &mut Drawstate,
+            font: &SpriteFont,
+            text_title: &str,
+            text_value: &str,
+            bar_color: Color,
+            bar_center: Vec2,
+            bar_width: f32,
+            bar_height: f32,
+            bar_filled_percentage: f32,
+            swap_title_and_value_text_pos: bool,
+        ) {
+            let bar_left_top = bar_center - Vec2::new(bar_width, bar_height) / 2.0;
+            draw.draw_rect(
+                Rect::from_pos_width_height(
+                    bar_left_top,
+                    bar_width * bar_filled_percentage,
+                    bar_height,
+                ),
+                true,
+                DEPTH_GUI,
+                bar_color,
+                ADDITIVITY_NONE,
+            );
+            draw.draw_rect(
+                Rect::from_pos_width_height(bar_left_top, bar_width, bar_height),
+                false,
+                DEPTH_GUI,
+                Color::new(
+                    bar_color.r - (32.0 / 255.0),
+                    bar_color.g - (32.0 / 255.0),
+                    bar_color.b - (32.0 / 255.0),
+                    1.0,
+                ),
+                ADDITIVITY_NONE,
+            );
+
+            let mut pos_title = bar_center - Vec2::filled_y(8.0);
+            let mut pos_value = bar_center + Vec2::filled_y(8.0);
+            if swap_title_and_value_text_pos {
+                std::mem::swap(&mut pos_title, &mut pos_value);
+            }
+
+            draw.draw_text(
+                text_title,
+                font,
+                1.0,
+                pos_title,
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Center,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                bar_color,
+                ADDITIVITY_NONE,
+            );
+            draw.draw_text(
+                text_value,
+                font,
+                1.0,
+                pos_value,
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Center,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                bar_color,
+                ADDITIVITY_NONE,
+            );
+        }
+
+        let (
+            player_hp,
+            player_hp_max,
+            player_boost,
+            player_boost_max,
+            player_ammo,
+            player_ammo_max,
+            player_cycle_percentage,
+        ) = if let Some(player) = self.world.get::<Player>(self.player).ok() {
+            (
+                player.hp,
+                player.hp_max,
+                player.boost,
+                player.boost_max,
+                player.ammo,
+                player.ammo_max,
+                player.cycle_timer.completion_ratio(),
+            )
+        } else {
+            (0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+        };
+
+        let bar_width = 48.0;
+        let bar_height = 4.0;
+
+        draw_bar(
+            draw,
+            &self.fonts["gui_font"],
+            "AMMO",
+            &format!("{}/{}", roundi(player_ammo), roundi(player_ammo_max)),
+            COLOR_AMMO,
+            Vec2::new(globals.canvas_width / 2.0 - (bar_width / 2.0 + 4.0), 16.0),
+            bar_width,
+            bar_height,
+            player_ammo / player_ammo_max,
+            true,
+        );
+        draw_bar(
+            draw,
+            &self.fonts["gui_font"],
+            "BOOST",
+            &format!("{}/{}", roundi(player_boost), roundi(player_boost_max)),
+            COLOR_BOOST,
+            Vec2::new(globals.canvas_width / 2.0 + (bar_width / 2.0 + 4.0), 16.0),
+            bar_width,
+            bar_height,
+            player_boost / player_boost_max,
+            true,
+        );
+        draw_bar(
+            draw,
+            &self.fonts["gui_font"],
+            "HP",
+            &format!("{}/{}", roundi(player_hp), roundi(player_hp_max)),
+            COLOR_HP,
+            Vec2::new(
+                globals.canvas_width / 2.0 - (bar_width / 2.0 + 4.0),
+                globals.canvas_height - 16.0,
+            ),
+            bar_width,
+            bar_height,
+            player_hp / player_hp_max,
+            false,
+        );
+        draw_bar(
+            draw,
+            &self.fonts["gui_font"],
+            "CYCLE",
+            "",
+            COLOR_DEFAULT,
+            Vec2::new(
+                globals.canvas_width / 2.0 + (bar_width / 2.0 + 4.0),
+                globals.canvas_height - 16.0,
+            ),
+            bar_width,
+            bar_height,
+            player_cycle_percentage,
+            false,
+        );
+
+        // Buffs
+        let buff_icons: Vec<(String, Color, f32)> =
+            if let Some(buffs) = self.world.get::<Buffs>(self.player).ok() {
+                buffs
+                    .active
+                    .iter()
+                    .map(|buff| {
+                        let definition = buff.bufftype.get_definition();
+                        let text = if buff.stacks > 1 {
+                            format!("{}x{}", definition.name_abbreviation, buff.stacks)
+                        } else {
+                            definition.name_abbreviation.to_owned()
+                        };
+                        (text, definition.color, 1.0 - buff.timer.completion_ratio())
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+        let buff_icon_width = 16.0;
+        let buff_icons_left =
+            globals.canvas_width / 2.0 - (buff_icons.len() as f32 - 1.0) * buff_icon_width / 2.0;
+        for (index, (text, color, remaining_percentage)) in buff_icons.iter().enumerate() {
+            let icon_center = Vec2::new(buff_icons_left + index as f32 * buff_icon_width, 36.0);
+            draw.draw_text(
+                text,
+                &self.fonts["gui_font"],
+                1.0,
+                icon_center,
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Center,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                *color,
+                ADDITIVITY_NONE,
+            );
+            // Remaining duration
+            let duration_bar_width = buff_icon_width - 4.0;
+            draw.draw_rect(
+                Rect::from_pos_width_height(
+                    icon_center + Vec2::new(-duration_bar_width / 2.0, 6.0),
+                    duration_bar_width * remaining_percentage,
+                    1.0,
+                ),
+                true,
+                DEPTH_GUI,
+                *color,
+                ADDITIVITY_NONE,
+            );
         }
 
-        for (_entity, (tween_color, drawable)) in
-            &mut self.world.query::<(&mut TweenColor, &mut Drawable)>()
-        {
-            tween_color.update(drawable, deltatime);
+        // Boss
+        let boss_hp_percentage = self
+            .world
+            .query::<&Enemy>()
+            .with::<Boss>()
+            .iter()
+            .next()
+            .map(|(_entity, enemy)| enemy.hp / enemy.hp_max);
+        if let Some(boss_hp_percentage) = boss_hp_percentage {
+            let boss_bar_center = Vec2::new(globals.canvas_width / 2.0, 60.0);
+            let boss_bar_width = 4.0 * bar_width;
+            draw_bar(
+                draw,
+                &self.fonts["gui_font"],
+                "BOSS",
+                &format!("{}%", roundi(100.0 * boss_hp_percentage)),
+                COLOR_HP,
+                boss_bar_center,
+                boss_bar_width,
+                bar_height,
+                boss_hp_percentage,
+                false,
+            );
+            // Phase thresholds
+            for &threshold in &BOSS_PHASE_HP_THRESHOLDS {
+                let marker_x = boss_bar_center.x + (threshold - 0.5) * boss_bar_width;
+                draw.draw_rect(
+                    Rect::from_pos_width_height(
+                        Vec2::new(marker_x, boss_bar_center.y - bar_height),
+                        1.0,
+                        2.0 * bar_height,
+                    ),
+                    true,
+                    DEPTH_GUI,
+                    COLOR_DEFAULT,
+                    ADDITIVITY_NONE,
+                );
+            }
         }
 
+        let round_percentage = self.director.timer_round.completion_ratio();
+        draw_bar(
+            draw,
+            &self.fonts["gui_font"],
+            "DIFFICULTY",
+            &self.director.difficulty.to_string(),
+            COLOR_NEGATIVE_AMMO,
+            Vec2::new(bar_width, globals.canvas_height - 16.0),
+            bar_width,
+            bar_height,
+            round_percentage,
+            false,
+        );
+
         //------------------------------------------------------------------------------------------
-        // BLINKER
+        // RESTART GAME
 
-        for (_entity, (blinker, drawable)) in
-            &mut self.world.query::<(&mut Blinker, &mut Drawable)>()
-        {
-            drawable.visible = blinker.update_and_check(deltatime);
+        if self.world.get::<Player>(self.player).is_err() {
+            let canvas_center =
+                Rect::from_width_height(globals.canvas_width, globals.canvas_height).center();
+            draw.draw_text(
+                "PRESS ANY KEY TO RESTART",
+                &self.fonts["gui_font"],
+                1.0,
+                canvas_center,
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Center,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                COLOR_DEFAULT,
+                ADDITIVITY_NONE,
+            );
+            draw.draw_text(
+                "PRESS T TO OPEN THE SKILLTREE",
+                &self.fonts["gui_font"],
+                1.0,
+                canvas_center + Vec2::filled_y(12.0),
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Center,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                COLOR_SKILL_POINT,
+                ADDITIVITY_NONE,
+            );
+            draw.draw_text(
+                "PRESS C TO OPEN THE CONSOLE",
+                &self.fonts["gui_font"],
+                1.0,
+                canvas_center + Vec2::filled_y(24.0),
+                Vec2::zero(),
+                Some(TextAlignment {
+                    x: AlignmentHorizontal::Center,
+                    y: AlignmentVertical::Center,
+                    origin_is_baseline: false,
+                    ignore_whitespace: true,
+                }),
+                None,
+                DEPTH_GUI,
+                COLOR_SKILL_POINT,
+                ADDITIVITY_NONE,
+            );
         }
 
         //------------------------------------------------------------------------------------------
@@ -3968,32 +7104,15 @@ impl Scene for SceneStage {
 
         {
             let gui_font = self.fonts.get("gui_font").unwrap();
-            for (entity, infotext) in &mut self.world.query::<&mut InfoText>() {
-                if infotext.update_and_check_if_finished(
-                    draw,
-                    &mut globals.random,
-                    gui_font,
-                    deltatime,
-                ) {
-                    self.commands.remove_entity(entity);
-                }
+            for infotext in &self.infotexts {
+                infotext.draw(draw, gui_font);
             }
         }
 
         //------------------------------------------------------------------------------------------
         // ENEMY CHARGE PARTICLES
 
-        for (entity, particle) in &mut self.world.query::<&mut EnemyChargeParticle>() {
-            if particle.timer.update_and_check_if_triggered(deltatime) {
-                self.commands.remove_entity(entity);
-            }
-
-            if let Some(muzzle_entity_xform) =
-                self.world.get::<Transform>(particle.muzzle_entity).ok()
-            {
-                particle.muzzle_pos = muzzle_entity_xform.pos + particle.muzzle_pos_offset;
-            }
-
+        for (_entity, particle) in &mut self.world.query::<&EnemyChargeParticle>() {
             let pos = Vec2::lerp(
                 particle.start_pos,
                 particle.muzzle_pos,
@@ -4022,20 +7141,20 @@ impl Scene for SceneStage {
             &mut self.world.query::<(&Transform, &DrawableMulti)>()
         {
             for drawable in &multi_drawable.drawables {
-                draw_drawable(&self.fonts, draw, globals, xform, drawable);
+                draw_drawable(&self.fonts, draw, &mut self.random_visual, xform, drawable);
             }
         }
         for (_entity, (xform, drawable)) in &mut self.world.query::<(&Transform, &Drawable)>() {
-            draw_drawable(&self.fonts, draw, globals, xform, drawable);
+            draw_drawable(&self.fonts, draw, &mut self.random_visual, xform, drawable);
         }
 
         //------------------------------------------------------------------------------------------
         // DEBUG DRAWING
 
-        if DEBUG_DRAW_ENABLE {
+        if self.debug_draw_enabled {
             // Colliders
-            for (_entity, (xform, collider)) in &mut self.world.query::<(&Transform, &Collider)>() {
-                let color = if collider.collisions.len() > 0 {
+            for (entity, (xform, collider)) in &mut self.world.query::<(&Transform, &Collider)>() {
+                let color = if self.debug_colliding_entities.contains(&entity) {
                     Color::red()
                 } else {
                     Color::yellow()
@@ -4051,17 +7170,15 @@ impl Scene for SceneStage {
         }
 
         //------------------------------------------------------------------------------------------
-        // CREATE / DELETE ENTITIES
-
         // INFOTEXT CREATION
+
         {
             let gui_font = self.fonts.get("gui_font").unwrap();
-            for mut infotext_to_create in infotext_create_buffer.drain(..) {
+            for mut infotext_to_create in self.infotexts_to_create.drain(..) {
                 // Collect existing infotext bounding boxes
                 let text_rects_existing: Vec<Recti> = {
                     let mut result = Vec::new();
-                    for (_entity, infotext) in &mut self.world.query::<&InfoText>() {
-                        let infotext: &InfoText = infotext;
+                    for infotext in &self.infotexts {
                         let text: String = infotext.text.iter().collect();
                         let rect = gui_font
                             .get_text_bounding_rect(&text, 1, false)
@@ -4083,10 +7200,382 @@ impl Scene for SceneStage {
                     .get_closest_position_without_overlapping(&text_rects_existing)
                     .into();
 
-                self.world.spawn((infotext_to_create,));
+                self.infotexts.push(infotext_to_create);
             }
         }
 
-        self.commands.execute(&mut self.world);
+        //------------------------------------------------------------------------------------------
+        // REPLAY
+
+        if let Some(frame) = self.get_replay_desync_frame() {
+            draw.debug_log(format!("Replay desynced at frame {}", frame));
+        }
+
+        //------------------------------------------------------------------------------------------
+        // DRAW DEBUG TERMINAL
+
+        if self.debug_terminal_open {
+            let terminal_rect = Rect::from_pos_width_height(
+                Vec2::new(0.0, globals.canvas_height / 2.0),
+                globals.canvas_width,
+                globals.canvas_height / 2.0,
+            );
+            draw.draw_rect(
+                terminal_rect,
+                true,
+                DEPTH_DEBUG_TERMINAL_BACKGROUND,
+                COLOR_BACKGROUND,
+                ADDITIVITY_NONE,
+            );
+            self.debug_terminal.draw(
+                draw,
+                &globals.font_default,
+                terminal_rect,
+                DEPTH_DEBUG_TERMINAL,
+            );
+        }
+    }
+
+    fn get_player_mut(&self) -> Result<RefMut<'_, Player>, String> {
+        self.world
+            .get_mut::<Player>(self.player)
+            .map_err(|_| "The player is dead".to_string())
+    }
+}
+
+impl Scene for SceneStage {
+    fn update_and_draw(
+        &mut self,
+        draw: &mut Drawstate,
+        _audio: &mut Audiostate,
+        _assets: &mut GameAssets,
+        input: &GameInput,
+        globals: &mut Globals,
+        out_game_events: &mut Vec<GameEvent>,
+    ) {
+        //------------------------------------------------------------------------------------------
+        // DEBUG TERMINAL
+
+        if input.keyboard.recently_pressed(Scancode::Grave) {
+            self.debug_terminal_open = !self.debug_terminal_open;
+        } else if self.debug_terminal_open {
+            // NOTE: We can't use `Terminal::update_and_run_commands` here as the commands need
+            //       mutable access to the whole stage including the terminal
+            match self.debug_terminal.update(input, globals.deltatime) {
+                Some(TerminalAction::Submit(line)) => {
+                    let output = DEBUG_COMMANDS.execute(self, &line);
+                    if output.clear_terminal {
+                        self.debug_terminal.clear();
+                    }
+                    for (text, color) in &output.lines {
+                        self.debug_terminal.print(text, *color);
+                    }
+                    out_game_events.extend(output.game_events);
+                }
+                Some(TerminalAction::RequestCompletion) => {
+                    let candidates = DEBUG_COMMANDS
+                        .get_completions(&self.debug_terminal.get_input_before_cursor());
+                    self.debug_terminal.complete(&candidates);
+                }
+                None => {}
+            }
+        }
+        // The keyboard belongs to the terminal while it is open
+        let gameplay_input_enabled = !self.debug_terminal_open;
+
+        let input_frame = if gameplay_input_enabled {
+            create_replay_frame(input, globals.deltatime)
+        } else {
+            ReplayFrame::new(globals.deltatime)
+        };
+
+        // After a replay has ended the user can leave it with any key
+        if self.is_replay_finished()
+            && gameplay_input_enabled
+            && input.keyboard.has_press_event
+            && !input.keyboard.recently_pressed(Scancode::Grave)
+        {
+            out_game_events.push(GameEvent::SwitchToScene {
+                scene_name: "console".to_string(),
+            })
+        }
+
+        let mut context = StageContext::from_globals(globals);
+        self.update(input_frame, &mut context, out_game_events);
+        // NOTE: A playback replaces the deltatime with the recorded one
+        let deltatime = context.deltatime;
+        globals.deltatime = deltatime;
+
+        self.draw(draw, globals);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Remembers how many components each entity should have
+    struct CountingWorld {
+        world: World,
+        component_counts: HashMap<Entity, usize>,
+    }
+
+    impl CountingWorld {
+        fn spawn<BundleType>(&mut self, component_count: usize, bundle: BundleType) -> Entity
+        where
+            BundleType: DynamicBundle,
+        {
+            let entity = self.world.spawn(bundle);
+            self.component_counts.insert(entity, component_count);
+            entity
+        }
+
+        fn insert<BundleType>(&mut self, entity: Entity, component_count: usize, bundle: BundleType)
+        where
+            BundleType: DynamicBundle,
+        {
+            *self.component_counts.get_mut(&entity).unwrap() += component_count;
+            self.world.insert(entity, bundle).unwrap();
+        }
+    }
+
+    fn sorted_component_counts(snapshot: &WorldSnapshot) -> Vec<usize> {
+        let mut counts: Vec<usize> = snapshot
+            .entities
+            .iter()
+            .map(|(_entity, components)| components.len())
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    #[test]
+    fn world_snapshot_keeps_all_components_of_all_archetypes() {
+        let mut random = Random::new_from_seed(0);
+        let mut world = CountingWorld {
+            world: World::new(),
+            component_counts: HashMap::new(),
+        };
+
+        let pos = Vec2::new(100.0, 100.0);
+        let vel = Vec2::new(10.0, 0.0);
+        let dir = Vec2::new(1.0, 0.0);
+
+        let player = world.spawn(6, Archetypes::new_player(pos, ShipType::Fighter, &[]));
+        world.spawn(3, Archetypes::new_screenflash(480.0, 270.0, 4));
+        world.spawn(5, Archetypes::new_muzzleflash(player, dir, 0.0));
+        world.spawn(
+            4,
+            Archetypes::new_trailparticle(pos, 2.0, 0.2, COLOR_DEFAULT, DEPTH_EFFECTS),
+        );
+        let projectile = world.spawn(
+            5,
+            Archetypes::new_projectile(
+                pos,
+                dir,
+                100.0,
+                Projectile::new(2.5, COLOR_DEFAULT, 1.0, true),
+            ),
+        );
+        world.insert(projectile, 1, (ContinuousCollision { pos_previous: pos },));
+        world.spawn(
+            6,
+            Archetypes::new_projectile_homing(
+                pos,
+                dir,
+                100.0,
+                Projectile::new(2.5, COLOR_DEFAULT, 1.0, true),
+            ),
+        );
+        let enemy_projectile = world.spawn(
+            5,
+            Archetypes::new_enemy_projectile(pos, dir, 100.0, 4.0, 10.0),
+        );
+        world.insert(
+            enemy_projectile,
+            1,
+            (SineMotion {
+                vel_base: 100.0 * dir,
+                amplitude: 10.0,
+                frequency: 2.0,
+                time: 0.0,
+            },),
+        );
+        world.spawn(
+            6,
+            Archetypes::new_ammo_collectible(pos, vel, 0.0, 90.0, player),
+        );
+        world.spawn(
+            5,
+            Archetypes::new_skillpoints_collectible(pos, vel, 0.0, 90.0),
+        );
+        world.spawn(5, Archetypes::new_boost_collectible(pos, vel, 0.0, 90.0));
+        world.spawn(
+            5,
+            Archetypes::new_attack_collectible(pos, vel, AttackType::Double),
+        );
+        world.spawn(5, Archetypes::new_hp_collectible(pos, vel));
+        world.spawn(
+            4,
+            Archetypes::new_hit_effect(
+                pos,
+                8.0,
+                4.0,
+                0.0,
+                COLOR_DEFAULT,
+                0.1,
+                COLOR_DEFAULT,
+                0.1,
+                true,
+            ),
+        );
+        world.spawn(
+            4,
+            Archetypes::new_hit_effect_round(
+                pos,
+                4.0,
+                COLOR_DEFAULT,
+                0.1,
+                COLOR_DEFAULT,
+                0.1,
+                false,
+            ),
+        );
+        world.spawn(5, Archetypes::new_haste_area(pos));
+        world.spawn(
+            4,
+            Archetypes::new_lightning_effect(pos, pos + vel, COLOR_DEFAULT),
+        );
+        world.spawn(
+            4,
+            Archetypes::new_laser_effect(pos, dir, 50.0, 4.0, COLOR_DEFAULT),
+        );
+        world.spawn(
+            5,
+            Archetypes::new_explode_particle(pos, 0.0, 100.0, 2.0, 8.0, 0.4, COLOR_DEFAULT),
+        );
+        world.spawn(5, Archetypes::new_tick_effect(player));
+        world.spawn(6, Archetypes::new_enemy_rock(&mut random, pos, vel, 8.0));
+        let shooter = world.spawn(5, Archetypes::new_enemy_shooter(pos, vel));
+        world.spawn(6, Archetypes::new_enemy_big_rock(&mut random, pos, vel));
+        world.spawn(6, Archetypes::new_enemy_waver(pos, vel));
+        world.spawn(6, Archetypes::new_enemy_seeker(pos, vel, player));
+        let mine = world.spawn(6, Archetypes::new_enemy_mine(pos));
+        world.insert(mine, 1, (Blinker::new(true, 0.2, 0.05),));
+        let orbitter = world.spawn(5, Archetypes::new_enemy_orbitter(pos, vel));
+        world.spawn(7, Archetypes::new_enemy_satellite(orbitter, pos, 90.0));
+        world.spawn(6, Archetypes::new_enemy_sapper(pos, vel, player));
+        world.spawn(6, Archetypes::new_enemy_trailer(pos, vel));
+        world.spawn(6, Archetypes::new_trail_segment(pos));
+        let boss = world.spawn(6, Archetypes::new_boss(pos, vel));
+        world.spawn(6, Archetypes::new_boss_armor(boss, pos, 45.0));
+        world.spawn(
+            1,
+            (EnemyChargeParticle::new(
+                pos,
+                dir,
+                shooter,
+                2.0,
+                0.2,
+                pos + vel,
+            ),),
+        );
+
+        let snapshot = WorldSnapshot::new(&world.world);
+        for (entity, components) in &snapshot.entities {
+            assert_eq!(
+                components.len(),
+                world.component_counts[entity],
+                "Snapshot lost components of entity {:?} - is a type missing in \
+                 `snapshot_components!`?",
+                entity
+            );
+        }
+
+        let (restored_world, restored_player) = snapshot.restore(player);
+        let restored_snapshot = WorldSnapshot::new(&restored_world);
+        assert_eq!(
+            sorted_component_counts(&snapshot),
+            sorted_component_counts(&restored_snapshot)
+        );
+
+        // Handles stored in components point to the restored entities
+        assert!(restored_world.get::<Player>(restored_player).is_ok());
+        let restored_parents: Vec<Entity> = restored_world
+            .query::<&SnapToParent>()
+            .iter()
+            .map(|(_entity, snap)| snap.parent)
+            .collect();
+        assert!(restored_parents.contains(&restored_player));
+    }
+
+    #[test]
+    fn player_procs_keep_skill_order() {
+        let proc_skill = |trigger, action, chance| {
+            Skill::Proc(Proc {
+                trigger,
+                action,
+                chance,
+            })
+        };
+        let skills = [
+            proc_skill(ProcTrigger::Kill, OneTimeAction::RegainHp, 5),
+            proc_skill(ProcTrigger::Hit, OneTimeAction::Barrage, 10),
+            Skill::AddHp(10),
+            proc_skill(ProcTrigger::BoostStart, OneTimeAction::SpawnHasteArea, 15),
+            proc_skill(ProcTrigger::Kill, OneTimeAction::RegainHp, 5),
+            proc_skill(ProcTrigger::CycleTick, OneTimeAction::DamageBoost, 20),
+            proc_skill(ProcTrigger::Hit, OneTimeAction::Barrage, 10),
+            proc_skill(
+                ProcTrigger::AttackPickup,
+                OneTimeAction::Invulnerability,
+                25,
+            ),
+        ];
+
+        let proc_order = |player: &Player| -> Vec<(ProcTrigger, OneTimeAction, i32)> {
+            player
+                .procs
+                .iter()
+                .map(|proc| (proc.trigger, proc.action, proc.chance))
+                .collect()
+        };
+        let first = Player::new(12.0, ShipType::Fighter, &skills);
+        let second = Player::new(12.0, ShipType::Fighter, &skills);
+        assert_eq!(proc_order(&first), proc_order(&second));
+        assert_eq!(
+            proc_order(&first),
+            vec![
+                (ProcTrigger::Kill, OneTimeAction::RegainHp, 10),
+                (ProcTrigger::Hit, OneTimeAction::Barrage, 20),
+                (ProcTrigger::BoostStart, OneTimeAction::SpawnHasteArea, 15),
+                (ProcTrigger::CycleTick, OneTimeAction::DamageBoost, 20),
+                (
+                    ProcTrigger::AttackPickup,
+                    OneTimeAction::Invulnerability,
+                    25
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn world_snapshot_restore_does_not_revive_dead_entity_handles() {
+        let mut world = World::new();
+        let dead_player = world.spawn(Archetypes::new_player(Vec2::zero(), ShipType::Fighter, &[]));
+        world.despawn(dead_player).unwrap();
+        world.spawn(Archetypes::new_enemy_seeker(
+            Vec2::zero(),
+            Vec2::zero(),
+            dead_player,
+        ));
+
+        let (restored_world, restored_player) = WorldSnapshot::new(&world).restore(dead_player);
+        assert!(restored_world.get::<Transform>(restored_player).is_err());
+        for (_entity, move_towards) in restored_world.query::<&MoveTowardsTarget>().iter() {
+            assert!(restored_world
+                .get::<Transform>(move_towards.target)
+                .is_err());
+        }
     }
 }